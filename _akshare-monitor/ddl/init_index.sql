@@ -37,3 +37,19 @@ CREATE TABLE IF NOT EXISTS index_stock_info
 )
 ENGINE = MergeTree
 ORDER BY index_code;
+
+-- 中证指数-指数成份股以及权重，每一个权重生效日期保存一份完整的成份股列表
+-- 某一天的成份股为该日期之前(含)最近一个权重生效日期的列表，相邻两份列表的差异即为成份股的调入以及调出
+CREATE TABLE IF NOT EXISTS index_stock_cons_weight
+(
+    `index_code` LowCardinality(String), -- 6位指数代码，例如000300
+    `date` Date, -- 权重生效日期
+    `stock_code` LowCardinality(String), -- 6位成分券代码
+    `index_name` String, -- 指数名称
+    `stock_name` String, -- 成分券名称
+    `exchange` LowCardinality(String), -- 交易所
+    `weight` Float64, -- 权重(%)
+    `ts` DateTime64(3, 'Asia/Shanghai') -- 数据收集时间戳，毫秒等级
+)
+ENGINE = ReplacingMergeTree(ts)
+ORDER BY (index_code, date, stock_code);
@@ -8,4 +8,30 @@ CREATE TABLE IF NOT EXISTS alarm_hist
     `proto_data` String
 )
 ENGINE = MergeTree
-ORDER BY (event_time, id)
\ No newline at end of file
+ORDER BY (event_time, id);
+
+-- 交易日历-新浪，每天重新拉取一次，包含当年剩余的交易日
+CREATE TABLE IF NOT EXISTS tool_trade_date_hist_sina
+(
+    `trade_date` Date, -- 交易日
+    `ts` DateTime64(3, 'Asia/Shanghai') -- 数据收集时间戳
+)
+ENGINE = ReplacingMergeTree(ts)
+ORDER BY trade_date;
+
+-- 证券主表，沪深京股票列表(包括终止上市)以及新浪指数列表，每天重新拉取一次
+-- 所有数据收集任务的代码范围以及接口之中的代码校验都以这张表为准
+CREATE TABLE IF NOT EXISTS security_master
+(
+    `code` String, -- 交易所后缀写法的代码，例如600198.SH
+    `symbol` String, -- 6位数字代码
+    `name` String, -- 证券简称
+    `kind` Enum8('Stock' = 0, 'Index' = 1), -- 证券种类
+    `exchange` LowCardinality(String), -- 交易所，SH、SZ或者BJ
+    `board` LowCardinality(String), -- 板块，Main、ChiNext、Star或者Bse，指数以及B股为空
+    `list_date` Nullable(Date), -- 上市日期
+    `status` Enum8('Listed' = 0, 'Delisted' = 1), -- 上市状态
+    `ts` DateTime64(3, 'Asia/Shanghai') -- 数据收集时间戳
+)
+ENGINE = ReplacingMergeTree(ts)
+ORDER BY (kind, code);
@@ -0,0 +1,108 @@
+-- 调度器相关表格，注意结尾一定要带分号
+
+-- 调度任务持久化表，每个调度任务(以任务名称区分)只保留最新的一行，
+-- monitor重启时通过这张表恢复任务的uuid、cron表达式以及启用状态
+CREATE TABLE IF NOT EXISTS schedule_task
+(
+    `key` String, -- 调度任务uuid
+    `name` String, -- 调度任务名称，同时作为恢复任务时的标识
+    `description` String, -- 调度任务描述
+    `cron_expr` String, -- cron表达式
+    `tag` LowCardinality(String), -- 调度任务类型
+    `enabled` Bool, -- 是否启用
+    `ts` DateTime64(3, 'Asia/Shanghai') -- 记录更新时间
+)
+ENGINE = ReplacingMergeTree(ts)
+ORDER BY name;
+
+-- 调度任务执行历史表，每一次定时或者手动触发的执行都会记录一行
+CREATE TABLE IF NOT EXISTS task_run_hist
+(
+    `task_key` String, -- 调度任务uuid
+    `task_name` LowCardinality(String), -- 调度任务名称
+    `run_id` String, -- 本次执行的uuid
+    `trigger` Enum8('Cron' = 0, 'Manual' = 1, 'Misfire' = 2), -- 触发来源
+    `start_time` DateTime64(3, 'Asia/Shanghai'), -- 开始执行时间
+    `end_time` DateTime64(3, 'Asia/Shanghai'), -- 结束执行时间
+    `status` Enum8('Success' = 0, 'Failed' = 1, 'TimedOut' = 2, 'Cancelled' = 3), -- 执行结果
+    `error` String, -- 执行失败时的错误链，成功时为空字符串
+    `rows_written` UInt64 -- 本次执行写入的数据行数
+)
+ENGINE = MergeTree
+ORDER BY (task_key, start_time);
+
+-- 历史数据回填任务表，每次状态变化都会写入一行新的记录
+CREATE TABLE IF NOT EXISTS backfill_job
+(
+    `job_id` String, -- 回填任务uuid
+    `target` LowCardinality(String), -- 回填的数据，例如stock_zh_a_hist
+    `start_date` Date, -- 开始日期
+    `end_date` Date, -- 结束日期
+    `codes` Array(String), -- 回填的代码列表，为空代表所有代码
+    `adj_types` Array(UInt8), -- 复权方式，0: 不复权，1: 前复权，2: 后复权
+    `status` Enum8('Running' = 0, 'Completed' = 1, 'Failed' = 2, 'Cancelled' = 3, 'Interrupted' = 4), -- 回填状态
+    `error` String, -- 执行失败时的错误链
+    `created_at` DateTime64(3, 'Asia/Shanghai'), -- 创建时间
+    `ts` DateTime64(3, 'Asia/Shanghai') -- 记录更新时间
+)
+ENGINE = ReplacingMergeTree(ts)
+ORDER BY job_id;
+
+-- 历史数据回填检查点表，每个代码回填完成之后记录一行，恢复回填任务时跳过已经完成的代码
+CREATE TABLE IF NOT EXISTS backfill_checkpoint
+(
+    `job_id` String, -- 回填任务uuid
+    `code` String, -- 已经完成回填的代码
+    `rows_written` UInt64, -- 该代码写入的数据行数
+    `ts` DateTime64(3, 'Asia/Shanghai') -- 完成时间
+)
+ENGINE = ReplacingMergeTree(ts)
+ORDER BY (job_id, code);
+
+-- 数据收集死信表，单个参数在重试之后依旧请求失败时记录一行，之后由dead_letter_sweeper定期重试
+CREATE TABLE IF NOT EXISTS dead_letter
+(
+    `id` String, -- 调度任务名称加上序列化之后的参数
+    `task_name` LowCardinality(String), -- 调度任务名称
+    `endpoint` LowCardinality(String), -- aktools接口路径
+    `code` String, -- 参数之中的代码，没有时为空字符串
+    `params` String, -- json序列化之后的参数
+    `error` String, -- 最近一次失败的错误链
+    `attempts` UInt32, -- 尝试次数，包括第一次失败
+    `status` Enum8('Pending' = 0, 'Resolved' = 1, 'GaveUp' = 2), -- 死信状态
+    `created_at` DateTime64(3, 'Asia/Shanghai'), -- 第一次失败的时间
+    `ts` DateTime64(3, 'Asia/Shanghai') -- 记录更新时间
+)
+ENGINE = ReplacingMergeTree(ts)
+ORDER BY id;
+
+-- aktools返回数据的结构漂移隔离表，无法反序列化的原始数据样本，grafana按照最近一段时间之内是否有数据告警
+CREATE TABLE IF NOT EXISTS schema_drift
+(
+    `task_name` LowCardinality(String), -- 调度任务名称
+    `endpoint` LowCardinality(String), -- aktools接口路径
+    `params` String, -- json序列化之后的请求参数
+    `payload` String, -- 无法反序列化的原始数据
+    `error` String, -- serde错误
+    `missing_keys` Array(String), -- 期望存在但是没有出现的字段
+    `unexpected_keys` Array(String), -- 出现但是不在期望之中的字段
+    `failed_rows` UInt64, -- 本次请求之中无法反序列化的数据条数
+    `total_rows` UInt64, -- 本次请求返回的数据条数
+    `ts` DateTime64(3, 'Asia/Shanghai') -- 记录时间
+)
+ENGINE = MergeTree
+ORDER BY (endpoint, ts);
+
+-- 数据保留规则的清理结果，clickhouse cleanup任务每次执行时每个规则记录一行
+CREATE TABLE IF NOT EXISTS retention_report
+(
+    `table` LowCardinality(String), -- 被清理的表名
+    `action` Enum8('DropPartition' = 0, 'Ttl' = 1), -- 清理方式，删除过期分区或者设置TTL
+    `cutoff` Date, -- 早于该日期的数据被清理
+    `partitions` Array(String), -- 被删除的分区
+    `rows` UInt64, -- 删除分区时为被删除的行数，设置TTL时为等待TTL删除的行数
+    `bytes` UInt64, -- 删除分区时为释放的磁盘字节数，设置TTL时为按照行数比例估算的字节数
+    `ts` DateTime64(3, 'Asia/Shanghai') -- 清理时间
+)
+ENGINE = MergeTree
+ORDER BY (table, ts);
@@ -0,0 +1,64 @@
+-- clickhouse tables，注意结尾一定要带分号
+
+-- 东方财富-沪深京板块-行业板块/概念板块的板块列表以及行情
+-- 接口：stock_board_industry_name_em、stock_board_concept_name_em，每个交易日收盘之后收集一次
+CREATE TABLE IF NOT EXISTS stock_board_em
+(
+    `kind` Enum8('Industry' = 0, 'Concept' = 1), -- 板块种类(行业板块/概念板块)
+    `date` Date, -- 交易日期
+    `code` LowCardinality(String), -- 板块代码，例如BK1027
+    `name` String, -- 板块名称
+    `rank` UInt32, -- 按照涨跌幅的排名
+    `latest_price` Nullable(Float64), -- 最新价
+    `change_amount` Nullable(Float64), -- 涨跌额
+    `change_percentage` Nullable(Float64), -- 涨跌幅(%)
+    `total_market_value` Nullable(Float64), -- 总市值(元)
+    `turnover_rate` Nullable(Float64), -- 换手率(%)
+    `rise_count` UInt32, -- 上涨家数
+    `fall_count` UInt32, -- 下跌家数
+    `leading_stock` String, -- 领涨股票
+    `leading_stock_change_percentage` Nullable(Float64), -- 领涨股票-涨跌幅(%)
+    `ts` DateTime64(3, 'Asia/Shanghai') -- 数据收集时间戳，毫秒等级
+)
+ENGINE = ReplacingMergeTree(ts)
+ORDER BY (kind, date, code);
+
+-- 东方财富-沪深京板块-行业板块/概念板块的日频历史行情
+-- 接口：stock_board_industry_hist_em、stock_board_concept_hist_em
+CREATE TABLE IF NOT EXISTS stock_board_hist_em
+(
+    `kind` Enum8('Industry' = 0, 'Concept' = 1), -- 板块种类(行业板块/概念板块)
+    `code` LowCardinality(String), -- 板块代码
+    `name` String, -- 板块名称
+    `date` Date, -- 交易日期
+    `open` Float64, -- 开盘
+    `close` Float64, -- 收盘
+    `high` Float64, -- 最高
+    `low` Float64, -- 最低
+    `change_percentage` Float64, -- 涨跌幅(%)
+    `change_amount` Float64, -- 涨跌额
+    `trading_volume` Float64, -- 成交量(手)
+    `trading_value` Float64, -- 成交额(元)
+    `amplitude` Float64, -- 振幅(%)
+    `turnover_rate` Float64, -- 换手率(%)
+    `ts` DateTime64(3, 'Asia/Shanghai') -- 数据收集时间戳，毫秒等级
+)
+ENGINE = ReplacingMergeTree(ts)
+ORDER BY (kind, code, date);
+
+-- 东方财富-沪深京板块-行业板块/概念板块的成份股
+-- 接口：stock_board_industry_cons_em、stock_board_concept_cons_em
+-- 每次收集写入当天的成份股快照，板块最新一次快照之中的股票为当前成份股，
+-- 比较相邻两次快照即可得到成份股的调入以及调出
+CREATE TABLE IF NOT EXISTS stock_board_cons_em
+(
+    `kind` Enum8('Industry' = 0, 'Concept' = 1), -- 板块种类(行业板块/概念板块)
+    `board_code` LowCardinality(String), -- 板块代码
+    `date` Date, -- 快照日期
+    `stock_code` LowCardinality(String), -- 6位股票代码
+    `board_name` String, -- 板块名称
+    `stock_name` String, -- 股票名称
+    `ts` DateTime64(3, 'Asia/Shanghai') -- 数据收集时间戳，毫秒等级
+)
+ENGINE = ReplacingMergeTree(ts)
+ORDER BY (kind, board_code, date, stock_code);
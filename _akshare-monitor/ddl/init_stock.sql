@@ -53,6 +53,70 @@ PARTITION BY toYYYYMMDD(date)  -- 按年月分区
 ORDER BY (code, timestamp)   -- 复合排序键：先按股票代码，再按时间戳
 SETTINGS index_granularity = 8192;
 
+-- A股1分钟K线，由astock_realtime_data的物化视图聚合得到
+-- 实时行情之中的成交量、成交额为当天的累计值，这里记录每根K线结束时的累计值，查询时减去上一根K线的累计值得到该周期的成交量
+CREATE TABLE IF NOT EXISTS astock_bar_1m
+(
+    `code` LowCardinality(String), -- 股票代码
+    `bar_time` DateTime('Asia/Shanghai'), -- K线的开始时间
+    `open` AggregateFunction(argMin, Float64, DateTime64(3, 'Asia/Shanghai')), -- 开盘价，周期之内第一个快照的最新价
+    `high` SimpleAggregateFunction(max, Float64), -- 最高价
+    `low` SimpleAggregateFunction(min, Float64), -- 最低价
+    `close` AggregateFunction(argMax, Float64, DateTime64(3, 'Asia/Shanghai')), -- 收盘价，周期之内最后一个快照的最新价
+    `volume_cum` SimpleAggregateFunction(max, Float64), -- 周期结束时当天的累计成交量(手)
+    `value_cum` SimpleAggregateFunction(max, Float64) -- 周期结束时当天的累计成交额(元)
+)
+ENGINE = AggregatingMergeTree
+PARTITION BY toYYYYMMDD(bar_time)
+ORDER BY (code, bar_time);
+
+CREATE MATERIALIZED VIEW IF NOT EXISTS astock_bar_1m_mv TO astock_bar_1m AS
+SELECT
+    code,
+    toStartOfMinute(timestamp) AS bar_time,
+    argMinState(latest_price, timestamp) AS open,
+    max(latest_price) AS high,
+    min(latest_price) AS low,
+    argMaxState(latest_price, timestamp) AS close,
+    max(trading_volume) AS volume_cum,
+    max(trading_value) AS value_cum
+FROM astock_realtime_data
+-- 停牌以及缺失最新价(写入时为-1)的快照不参与聚合
+WHERE trading_status != 'SUSPENDED' AND latest_price > 0
+GROUP BY code, bar_time;
+
+-- A股5分钟K线，由astock_realtime_data的物化视图聚合得到
+-- 实时行情之中的成交量、成交额为当天的累计值，这里记录每根K线结束时的累计值，查询时减去上一根K线的累计值得到该周期的成交量
+CREATE TABLE IF NOT EXISTS astock_bar_5m
+(
+    `code` LowCardinality(String), -- 股票代码
+    `bar_time` DateTime('Asia/Shanghai'), -- K线的开始时间
+    `open` AggregateFunction(argMin, Float64, DateTime64(3, 'Asia/Shanghai')), -- 开盘价，周期之内第一个快照的最新价
+    `high` SimpleAggregateFunction(max, Float64), -- 最高价
+    `low` SimpleAggregateFunction(min, Float64), -- 最低价
+    `close` AggregateFunction(argMax, Float64, DateTime64(3, 'Asia/Shanghai')), -- 收盘价，周期之内最后一个快照的最新价
+    `volume_cum` SimpleAggregateFunction(max, Float64), -- 周期结束时当天的累计成交量(手)
+    `value_cum` SimpleAggregateFunction(max, Float64) -- 周期结束时当天的累计成交额(元)
+)
+ENGINE = AggregatingMergeTree
+PARTITION BY toYYYYMMDD(bar_time)
+ORDER BY (code, bar_time);
+
+CREATE MATERIALIZED VIEW IF NOT EXISTS astock_bar_5m_mv TO astock_bar_5m AS
+SELECT
+    code,
+    toStartOfFiveMinutes(timestamp) AS bar_time,
+    argMinState(latest_price, timestamp) AS open,
+    max(latest_price) AS high,
+    min(latest_price) AS low,
+    argMaxState(latest_price, timestamp) AS close,
+    max(trading_volume) AS volume_cum,
+    max(trading_value) AS value_cum
+FROM astock_realtime_data
+-- 停牌以及缺失最新价(写入时为-1)的快照不参与聚合
+WHERE trading_status != 'SUSPENDED' AND latest_price > 0
+GROUP BY code, bar_time;
+
 -- 东方财富-沪深京 A 股日频率数据; 历史数据按日频率更新, 当日收盘价在收盘后获取
 CREATE TABLE IF NOT EXISTS stock_zh_a_hist
 (
@@ -88,6 +152,19 @@ CREATE TABLE IF NOT EXISTS stock_zh_a_hist
 ENGINE = ReplacingMergeTree
 ORDER BY (code, date, adj_type);
 
+-- 新浪财经-A股后复权因子，复权因子只在除权除息日变化，每一条数据从date开始生效
+-- stock_zh_a_hist只保存不复权数据，前复权以及后复权价格在查询时计算：
+-- 后复权价格 = 不复权价格 x 当日生效的复权因子，前复权价格 = 后复权价格 / 最新的复权因子
+CREATE TABLE IF NOT EXISTS stock_adjust_factor
+(
+    `code` LowCardinality(String), -- 6位股票代码
+    `date` Date, -- 复权因子开始生效的日期
+    `hfq_factor` Float64, -- 后复权因子
+    `ts` DateTime64(3, 'Asia/Shanghai') -- 数据收集时间戳，毫秒等级
+)
+ENGINE = ReplacingMergeTree(ts)
+ORDER BY (code, date);
+
 -- 东方财富网-数据中心-资金流向-沪深港通资金流向-沪深港通历史数据
 CREATE TABLE IF NOT EXISTS stock_hsgt_hist_em
 (
@@ -176,4 +253,41 @@ CREATE TABLE IF NOT EXISTS stock_rank_lxsz_ths
     `consecutive_change_percentage` Float64 COMMENT '连续涨跌幅，单位：百分比 (%)',
 )
 ENGINE = MergeTree
-ORDER BY (stock_code)
+ORDER BY (stock_code);
+
+-- 同花顺-财务指标-主要指标
+-- 接口：stock_financial_abstract_ths，每个季报披露截止日之后按照报告期、年度以及单季度收集所有股票的数据
+-- 金额单位统一为元，增长率、利润率、收益率以及资产负债率单位为%，无法解析的指标为NULL
+CREATE TABLE IF NOT EXISTS stock_financial_abstract_ths
+(
+    `code` LowCardinality(String), -- 股票代码
+    `period_type` Enum8('Report' = 0, 'Annual' = 1, 'Quarter' = 2), -- 报告期类型(按报告期/按年度/按单季度)
+    `report_date` Date, -- 报告期末日期，按年度的数据为当年12月31日
+    `net_profit` Nullable(Float64), -- 净利润(元)
+    `net_profit_growth_rate` Nullable(Float64), -- 净利润同比增长率(%)
+    `deducted_net_profit` Nullable(Float64), -- 扣非净利润(元)
+    `deducted_net_profit_growth_rate` Nullable(Float64), -- 扣非净利润同比增长率(%)
+    `total_revenue` Nullable(Float64), -- 营业总收入(元)
+    `total_revenue_growth_rate` Nullable(Float64), -- 营业总收入同比增长率(%)
+    `basic_eps` Nullable(Float64), -- 基本每股收益(元)
+    `net_assets_per_share` Nullable(Float64), -- 每股净资产(元)
+    `capital_reserve_per_share` Nullable(Float64), -- 每股资本公积金(元)
+    `undistributed_profit_per_share` Nullable(Float64), -- 每股未分配利润(元)
+    `operating_cash_flow_per_share` Nullable(Float64), -- 每股经营现金流(元)
+    `net_profit_margin` Nullable(Float64), -- 销售净利率(%)
+    `gross_profit_margin` Nullable(Float64), -- 销售毛利率(%)
+    `roe` Nullable(Float64), -- 净资产收益率(%)
+    `roe_diluted` Nullable(Float64), -- 净资产收益率-摊薄(%)
+    `operating_cycle` Nullable(Float64), -- 营业周期(天)
+    `inventory_turnover` Nullable(Float64), -- 存货周转率(次)
+    `inventory_turnover_days` Nullable(Float64), -- 存货周转天数(天)
+    `accounts_receivable_turnover_days` Nullable(Float64), -- 应收账款周转天数(天)
+    `current_ratio` Nullable(Float64), -- 流动比率
+    `quick_ratio` Nullable(Float64), -- 速动比率
+    `conservative_quick_ratio` Nullable(Float64), -- 保守速动比率
+    `equity_ratio` Nullable(Float64), -- 产权比率
+    `debt_asset_ratio` Nullable(Float64), -- 资产负债率(%)
+    `ts` DateTime64(3, 'Asia/Shanghai') -- 数据收集时间戳，毫秒等级
+)
+ENGINE = ReplacingMergeTree(ts)
+ORDER BY (code, period_type, report_date);
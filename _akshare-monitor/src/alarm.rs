@@ -0,0 +1,58 @@
+//! monitor内部产生的告警，通过kafka的`grafana_alarm` topic发送，
+//! 消息格式与`/hook`之中由grafana告警转换得到的[`GrafanaAlert`]相同
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, OnceLock},
+};
+
+use chrono::Utc;
+use dm_proto::kafka_alarm::GrafanaAlert;
+use prost::Message;
+use rskafka::{
+    client::partition::{Compression, PartitionClient},
+    record::Record,
+};
+
+static ALARM_PRODUCER: OnceLock<Arc<PartitionClient>> = OnceLock::new();
+
+/// 挂载kafka生产者，在调度器启动之前调用
+pub fn attach_producer(kafka_client: Arc<PartitionClient>) {
+    if ALARM_PRODUCER.set(kafka_client).is_err() {
+        ftlog::warn!("[alarm] kafka producer has been attached already");
+    }
+}
+
+/// 发送一条告警，没有挂载kafka生产者时只记录日志，
+/// 同一个问题重复发送的告警需要使用相同的`fingerprint`，下游据此去重
+pub async fn send_alarm(
+    fingerprint: String,
+    alertname: String,
+    alerts: Vec<String>,
+    summary: String,
+    description: String,
+) -> anyhow::Result<()> {
+    let Some(producer) = ALARM_PRODUCER.get() else {
+        ftlog::warn!(
+            "[alarm] kafka producer is not attached, alarm {} dropped",
+            alertname
+        );
+        return Ok(());
+    };
+    let alert = GrafanaAlert {
+        alertname,
+        alerts,
+        fingerprint,
+        summary,
+        description,
+    };
+    let record = Record {
+        key: None,
+        value: Some(alert.encode_to_vec()),
+        headers: BTreeMap::new(),
+        timestamp: Utc::now(),
+    };
+    producer
+        .produce(vec![record], Compression::NoCompression)
+        .await?;
+    Ok(())
+}
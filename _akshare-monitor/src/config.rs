@@ -2,6 +2,7 @@ use std::sync::LazyLock;
 
 use clap::Parser;
 use config::{Environment, File};
+use data_mind::{limiter::LimiterConfig, upstream::UpstreamConfig};
 use serde::Deserialize;
 
 pub static INIT_CONFIG: LazyLock<InitConfig> = LazyLock::new(|| InitConfig::new().unwrap());
@@ -37,11 +38,101 @@ pub struct KafkaConfig {
     pub partition: i32,
 }
 
+/// 调度锁使用的redis，没有填写的字段使用本地redis的默认设置，
+/// 环境变量`REDIS_HOST`、`REDIS_PORT`、`REDIS_PASSWORD`、`REDIS_TTL`可以覆盖对应的字段
+#[derive(Debug, Deserialize)]
+pub struct RedisConfig {
+    #[serde(default = "default_redis_host")]
+    pub host: String,
+    #[serde(default = "default_redis_port")]
+    pub port: u16,
+    /// 没有设置密码的redis不需要填写
+    #[serde(default)]
+    pub password: Option<String>,
+    /// 调度锁的过期时间(秒)，持有锁期间会定期续期
+    #[serde(default = "default_redis_ttl")]
+    pub ttl: u64,
+}
+
+fn default_redis_host() -> String {
+    "127.0.0.1".to_owned()
+}
+
+fn default_redis_port() -> u16 {
+    6379
+}
+
+fn default_redis_ttl() -> u64 {
+    60
+}
+
+/// 单个数据收集任务的配置，通过任务名称与代码之中定义的任务对应，
+/// 没有填写的字段沿用代码之中的默认设置
+#[derive(Debug, Deserialize)]
+pub struct MonitorConfig {
+    /// 调度任务名称，例如`stock_zh_a_hist`
+    pub name: String,
+    /// 是否启用，停用的任务不会被加入调度器
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// 覆盖默认的cron表达式，同时优先于通过api修改之后持久化的cron表达式
+    pub cron_expr: Option<String>,
+    /// 同时进行的接口请求数量
+    pub concurrency: Option<usize>,
+    /// 单次重试的最大间隔(秒)
+    pub retry_max_interval: Option<u64>,
+    /// 单个参数总的最大重试时间(秒)
+    pub retry_max_elapsed: Option<u64>,
+    /// 增量收集时从已有数据的最新日期往前重新收集的天数，用于覆盖上游对近期数据的修正
+    pub overlap_days: Option<u32>,
+    /// 写入的clickhouse表名
+    pub table: Option<String>,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// 单个clickhouse表格的数据保留规则，由每天执行的清理任务应用
+#[derive(Debug, Clone, Deserialize)]
+pub struct RetentionConfig {
+    /// 表名，例如`astock_realtime_data`
+    pub table: String,
+    /// 用于判断数据是否过期的日期或者时间字段
+    pub column: String,
+    /// 保留的时长，单位为`unit`
+    pub keep: u32,
+    pub unit: RetentionUnit,
+}
+
+/// 数据保留时长的单位
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RetentionUnit {
+    Day,
+    Month,
+    Year,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct InitConfig {
     pub server: ServerConfig,
     pub clickhouse: ClickhouseConfig,
     pub kafka: KafkaConfig,
+    /// 调度锁使用的redis，没有配置时所有任务都不加锁运行
+    #[serde(default)]
+    pub redis: Option<RedisConfig>,
+    /// aktools上游配置，没有配置时使用本地的aktools
+    #[serde(default)]
+    pub aktools: UpstreamConfig,
+    /// aktools请求限流器配置，没有配置时使用默认设置
+    #[serde(default)]
+    pub aktools_limiter: LimiterConfig,
+    #[serde(default)]
+    pub monitors: Vec<MonitorConfig>,
+    /// 数据保留规则，没有配置的表格不会被清理
+    #[serde(default)]
+    pub retention: Vec<RetentionConfig>,
 }
 
 impl InitConfig {
@@ -59,8 +150,18 @@ impl InitConfig {
                     .keep_prefix(true)
                     .separator("_"),
             )
+            .add_source(
+                Environment::with_prefix("redis")
+                    .keep_prefix(true)
+                    .separator("_"),
+            )
             .build()?;
 
         Ok(s.try_deserialize()?)
     }
+
+    /// 获取对应名称的数据收集任务配置
+    pub fn monitor(&self, name: &str) -> Option<&MonitorConfig> {
+        self.monitors.iter().find(|monitor| monitor.name == name)
+    }
 }
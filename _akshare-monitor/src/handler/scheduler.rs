@@ -1,10 +1,21 @@
+use data_mind::{
+    limiter::{AKTOOLS_LIMITER, LimiterStats},
+    upstream::{AKTOOLS_UPSTREAMS, UpstreamStatus},
+};
 use poem::{
     Route, RouteMethod, get, handler,
-    web::{Json, Path, Query},
+    http::StatusCode,
+    post, put,
+    web::{Data, Json, Path, Query},
 };
 use serde::Deserialize;
 
-use crate::scheduler::{SCHEDULE_TASK_MANAGER, ScheduleTaskType, TaskMetaShow};
+use crate::{
+    scheduler::{SCHEDULE_TASK_MANAGER, ScheduleTaskType, TaskMeta, TaskMetaShow, TaskRunShow},
+    tasks::{
+        BACKFILL_MANAGER, BackfillJobShow, BackfillRequest, SchemaDriftSummary, drift_summary,
+    },
+};
 
 pub const PATH_NAME: &'static str = "/scheduler";
 pub fn scheduler_api() -> Route {
@@ -14,7 +25,46 @@ pub fn scheduler_api() -> Route {
             PATH_NAME,
             RouteMethod::new().get(schedule_inspect_api), // 获取现在所有正在运行的调度任务
         )
-        .at(format!("/{}/:task_id", PATH_NAME), get(trigger_task_api))
+        .at(format!("/{}/kinds", PATH_NAME), get(task_kinds_api))
+        .at(format!("/{}/limiter", PATH_NAME), get(limiter_stats_api))
+        .at(format!("/{}/upstreams", PATH_NAME), get(upstreams_api))
+        .at(
+            format!("/{}/schema_drift", PATH_NAME),
+            get(schema_drift_api),
+        )
+        .at(
+            format!("/{}/backfill", PATH_NAME),
+            get(backfill_jobs_api).post(submit_backfill_api),
+        )
+        .at(
+            format!("/{}/backfill/:job_id", PATH_NAME),
+            get(backfill_job_api).delete(cancel_backfill_api),
+        )
+        .at(
+            format!("/{}/backfill/:job_id/resume", PATH_NAME),
+            post(resume_backfill_api),
+        )
+        .at(
+            format!("/{}/kinds/:kind", PATH_NAME),
+            post(recreate_task_api),
+        )
+        .at(
+            format!("/{}/:task_id", PATH_NAME),
+            get(trigger_task_api).delete(cancel_task_api),
+        )
+        .at(format!("/{}/:task_id/runs", PATH_NAME), get(task_runs_api))
+        .at(
+            format!("/{}/:task_id/cron", PATH_NAME),
+            put(update_cron_api),
+        )
+        .at(
+            format!("/{}/:task_id/pause", PATH_NAME),
+            post(pause_task_api),
+        )
+        .at(
+            format!("/{}/:task_id/resume", PATH_NAME),
+            post(resume_task_api),
+        )
 }
 
 #[derive(Debug, Deserialize)]
@@ -34,3 +84,134 @@ async fn trigger_task_api(Path(task_id): Path<String>) {
     // println!("task_id = {task_id}")
     SCHEDULE_TASK_MANAGER.trigger_task(task_id).await;
 }
+
+#[derive(Debug, Deserialize)]
+struct RunsQuery {
+    /// 返回的执行历史条数，默认为20
+    limit: Option<u64>,
+}
+
+/// 按照开始时间倒序获取一个任务最近的执行历史
+#[handler]
+async fn task_runs_api(
+    Path(task_id): Path<String>,
+    Query(RunsQuery { limit }): Query<RunsQuery>,
+) -> poem::Result<Json<Vec<TaskRunShow>>> {
+    let runs = SCHEDULE_TASK_MANAGER
+        .runs(&task_id, limit.unwrap_or(20))
+        .await
+        .inspect_err(|err| ftlog::error!("error occur when query task run hist = {:?}", err))
+        .map_err(|err| {
+            poem::Error::from_string(format!("{err:#}"), StatusCode::INTERNAL_SERVER_ERROR)
+        })?;
+    Ok(Json(runs))
+}
+
+/// 获取所有可以被重新创建的任务种类
+#[handler]
+async fn task_kinds_api() -> Json<Vec<TaskMeta>> {
+    Json(SCHEDULE_TASK_MANAGER.kinds())
+}
+
+/// 获取aktools请求限流器的当前状态，包括并发上限、正在进行以及等待的请求数量、被上游限流的请求数量等
+#[handler]
+async fn limiter_stats_api() -> Json<LimiterStats> {
+    Json(AKTOOLS_LIMITER.stats())
+}
+
+/// 获取所有aktools上游的健康状态
+#[handler]
+async fn upstreams_api() -> Json<Vec<UpstreamStatus>> {
+    Json(AKTOOLS_UPSTREAMS.status())
+}
+
+#[derive(Debug, Deserialize)]
+struct SchemaDriftQuery {
+    /// 查询最近多少小时之内的结构漂移，默认为24
+    hours: Option<u32>,
+}
+
+/// 获取最近一段时间之内每个aktools接口无法反序列化的情况，包括缺少以及多出的字段，没有结构漂移时返回空列表
+#[handler]
+async fn schema_drift_api(
+    Query(SchemaDriftQuery { hours }): Query<SchemaDriftQuery>,
+    ch_client: Data<&clickhouse::Client>,
+) -> poem::Result<Json<Vec<SchemaDriftSummary>>> {
+    let summary = drift_summary(&ch_client, hours.unwrap_or(24))
+        .await
+        .inspect_err(|err| ftlog::error!("error occur when query schema drift = {:?}", err))
+        .map_err(|err| {
+            poem::Error::from_string(format!("{err:#}"), StatusCode::INTERNAL_SERVER_ERROR)
+        })?;
+    Ok(Json(summary))
+}
+
+/// 通过任务种类(任务名称，例如`stock_zh_index_daily`)重新创建一个被取消的任务，返回任务uuid
+#[handler]
+async fn recreate_task_api(Path(kind): Path<String>) -> poem::Result<String> {
+    Ok(SCHEDULE_TASK_MANAGER.recreate_task(&kind).await?)
+}
+
+#[derive(Debug, Deserialize)]
+struct UpdateCronBody {
+    cron_expr: String,
+}
+
+/// 修改一个任务的cron表达式，修改会被持久化，无效的cron表达式会返回400
+#[handler]
+async fn update_cron_api(
+    Path(task_id): Path<String>,
+    Json(UpdateCronBody { cron_expr }): Json<UpdateCronBody>,
+) -> poem::Result<()> {
+    Ok(SCHEDULE_TASK_MANAGER
+        .update_cron(&task_id, cron_expr)
+        .await?)
+}
+
+/// 暂停一个任务，任务依旧保留在调度器之中
+#[handler]
+async fn pause_task_api(Path(task_id): Path<String>) -> poem::Result<()> {
+    Ok(SCHEDULE_TASK_MANAGER.pause_task(&task_id).await?)
+}
+
+/// 恢复一个被暂停的任务
+#[handler]
+async fn resume_task_api(Path(task_id): Path<String>) -> poem::Result<()> {
+    Ok(SCHEDULE_TASK_MANAGER.resume_task(&task_id).await?)
+}
+
+/// 取消一个任务，将其从调度器之中移除，之后可以通过任务种类重新创建
+#[handler]
+async fn cancel_task_api(Path(task_id): Path<String>) -> poem::Result<()> {
+    Ok(SCHEDULE_TASK_MANAGER.cancel_task(task_id).await?)
+}
+
+/// 创建一个历史数据回填任务并立即开始执行，返回回填任务uuid
+#[handler]
+async fn submit_backfill_api(Json(request): Json<BackfillRequest>) -> poem::Result<String> {
+    Ok(BACKFILL_MANAGER.submit(request).await?)
+}
+
+/// 获取所有回填任务的进度
+#[handler]
+async fn backfill_jobs_api() -> Json<Vec<BackfillJobShow>> {
+    Json(BACKFILL_MANAGER.jobs())
+}
+
+/// 获取一个回填任务的进度
+#[handler]
+async fn backfill_job_api(Path(job_id): Path<String>) -> poem::Result<Json<BackfillJobShow>> {
+    Ok(Json(BACKFILL_MANAGER.job(&job_id)?))
+}
+
+/// 取消一个正在执行的回填任务，已经完成的代码会保留检查点
+#[handler]
+async fn cancel_backfill_api(Path(job_id): Path<String>) -> poem::Result<()> {
+    Ok(BACKFILL_MANAGER.cancel(&job_id)?)
+}
+
+/// 恢复一个被中断、取消或者执行失败的回填任务，跳过已经完成的代码
+#[handler]
+async fn resume_backfill_api(Path(job_id): Path<String>) -> poem::Result<()> {
+    Ok(BACKFILL_MANAGER.resume(&job_id).await?)
+}
@@ -1,7 +1,7 @@
 use std::sync::Arc;
 
 use config::INIT_CONFIG;
-use data_mind::utils::perform_ch_ddl;
+use data_mind::{limiter::AKTOOLS_LIMITER, upstream::AKTOOLS_UPSTREAMS, utils::perform_ch_ddl};
 use ftlog::{
     LevelFilter,
     appender::{Duration, FileAppender, Period},
@@ -10,11 +10,13 @@ use handler::get_app;
 use init::ExternalResource;
 use poem::{EndpointExt, Server, listener::TcpListener};
 
+mod alarm;
 mod config;
 mod handler;
 mod init;
 mod scheduler;
 mod tasks;
+mod trade_calendar;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -53,6 +55,9 @@ async fn main() -> anyhow::Result<()> {
         .try_init()
         .expect("logger build or set failed");
 
+    AKTOOLS_LIMITER.configure(INIT_CONFIG.aktools_limiter.clone());
+    AKTOOLS_UPSTREAMS.configure(INIT_CONFIG.aktools.clone())?;
+    AKTOOLS_UPSTREAMS.spawn_health_check();
     let ext_res = ExternalResource::init();
     let kafka_client = Arc::new(
         data_mind::utils::connect_kafka(
@@ -62,6 +67,7 @@ async fn main() -> anyhow::Result<()> {
         )
         .await,
     );
+    alarm::attach_producer(Arc::clone(&kafka_client));
     let clickhouse_client = ext_res.ch_client.clone();
 
     perform_ddl(&ext_res.ch_client).await;
@@ -82,7 +88,9 @@ async fn perform_ddl(ch_client: &clickhouse::Client) {
     let ddls = [
         include_str!("../ddl/init_stock.sql"),
         include_str!("../ddl/init_index.sql"),
+        include_str!("../ddl/init_sector.sql"),
         include_str!("../ddl/init_other.sql"),
+        include_str!("../ddl/init_scheduler.sql"),
     ];
 
     for ddl in ddls {
@@ -0,0 +1,37 @@
+//! 调度任务管理器相关错误
+
+use poem::{error::ResponseError, http::StatusCode};
+use snafu::Snafu;
+
+#[derive(Debug, Snafu)]
+#[snafu(visibility(pub(crate)))]
+pub enum SchedulerError {
+    /// cron表达式无法被解析
+    #[snafu(display("Invalid cron expression `{}`: {}", cron_expr, source))]
+    InvalidCron {
+        cron_expr: String,
+        source: cron::error::Error,
+    },
+    /// cron表达式可以被解析，但是之后再也不会被触发
+    #[snafu(display("Cron expression `{}` will never be fired", cron_expr))]
+    NeverFired { cron_expr: String },
+    /// 调度器之中不存在该uuid对应的任务
+    #[snafu(display("Task `{}` not found", key))]
+    TaskNotFound { key: String },
+    /// 没有注册过的任务种类
+    #[snafu(display("Task kind `{}` not registered", kind))]
+    KindNotFound { kind: String },
+}
+
+impl ResponseError for SchedulerError {
+    fn status(&self) -> StatusCode {
+        match self {
+            SchedulerError::InvalidCron { .. } | SchedulerError::NeverFired { .. } => {
+                StatusCode::BAD_REQUEST
+            }
+            SchedulerError::TaskNotFound { .. } | SchedulerError::KindNotFound { .. } => {
+                StatusCode::NOT_FOUND
+            }
+        }
+    }
+}
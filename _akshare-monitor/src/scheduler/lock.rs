@@ -0,0 +1,145 @@
+//! 基于redis的调度锁，多个monitor实例同时部署时保证同一个任务的同一次调度只会在一个实例上执行
+//!
+//! 每一次调度(以任务名称和调度时间作为标识)对应redis之中的一个key，值为持有该锁的实例标识，
+//! 执行期间会定期续期，执行结束之后不会主动释放，而是等待其自然过期，
+//! 这样即使多个实例之间存在一定的时钟偏差，较晚触发的实例也无法再次获取同一次调度的锁
+use std::time::Duration;
+
+use chrono::{DateTime, FixedOffset};
+use redis::{
+    AsyncCommands, Client, ConnectionAddr, ConnectionInfo, RedisConnectionInfo, Script,
+    aio::ConnectionManager,
+};
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+use crate::config::RedisConfig;
+
+const LOCK_KEY_PREFIX: &str = "data_mind:akshare_monitor:lock";
+
+/// 只有值依旧为当前实例时才对锁进行续期
+const RENEW_SCRIPT: &str = r#"
+if redis.call('GET', KEYS[1]) == ARGV[1] then
+    return redis.call('PEXPIRE', KEYS[1], ARGV[2])
+else
+    return 0
+end
+"#;
+
+/// 尝试获取一次调度的锁的结果
+pub enum LockOutcome {
+    /// 当前实例获取到了锁，guard被drop时停止续期
+    Acquired(TaskLockGuard),
+    /// 锁已经被其他实例持有，携带持有者的实例标识
+    Held(String),
+}
+
+/// 持有锁期间负责定期续期，被drop时停止续期，之后锁会在ttl之后自然过期
+pub struct TaskLockGuard {
+    renewal: JoinHandle<()>,
+}
+
+impl Drop for TaskLockGuard {
+    fn drop(&mut self) {
+        self.renewal.abort();
+    }
+}
+
+/// 调度锁
+pub struct TaskLocker {
+    conn: ConnectionManager,
+    /// 当前实例的标识，作为锁的值写入redis
+    owner: String,
+    ttl: Duration,
+}
+
+impl TaskLocker {
+    /// 连接redis，`ConnectionManager`会在连接断开之后自动重连
+    pub async fn connect(config: &RedisConfig) -> anyhow::Result<Self> {
+        let info = ConnectionInfo {
+            addr: ConnectionAddr::Tcp(config.host.clone(), config.port),
+            redis: RedisConnectionInfo {
+                password: config.password.clone(),
+                ..Default::default()
+            },
+        };
+        let conn = ConnectionManager::new(Client::open(info)?).await?;
+
+        Ok(Self {
+            conn,
+            owner: instance_id(),
+            ttl: Duration::from_secs(config.ttl),
+        })
+    }
+
+    /// 当前实例的标识
+    pub fn owner(&self) -> &str {
+        &self.owner
+    }
+
+    /// 尝试获取任务`task_name`在`tick`这一次调度的锁，获取成功之后每隔ttl的三分之一续期一次
+    pub async fn try_lock(
+        &self,
+        task_name: &str,
+        tick: DateTime<FixedOffset>,
+    ) -> anyhow::Result<LockOutcome> {
+        let lock_key = format!(
+            "{}:{}:{}",
+            LOCK_KEY_PREFIX,
+            task_name,
+            tick.format("%Y%m%d%H%M%S")
+        );
+        let mut conn = self.conn.clone();
+        let acquired: Option<String> = redis::cmd("SET")
+            .arg(&lock_key)
+            .arg(&self.owner)
+            .arg("NX")
+            .arg("PX")
+            .arg(self.ttl.as_millis() as u64)
+            .query_async(&mut conn)
+            .await?;
+
+        if acquired.is_none() {
+            // 锁可能恰好在两次请求之间过期，此时持有者未知
+            let owner: Option<String> = conn.get(&lock_key).await?;
+            return Ok(LockOutcome::Held(owner.unwrap_or_default()));
+        }
+
+        let owner = self.owner.clone();
+        let ttl = self.ttl;
+        let renewal = tokio::spawn(async move {
+            let script = Script::new(RENEW_SCRIPT);
+            loop {
+                tokio::time::sleep(ttl / 3).await;
+                let renewed = script
+                    .key(&lock_key)
+                    .arg(&owner)
+                    .arg(ttl.as_millis() as u64)
+                    .invoke_async::<i64>(&mut conn)
+                    .await;
+                match renewed {
+                    Ok(1) => {}
+                    Ok(_) => {
+                        ftlog::warn!(
+                            "[TaskLocker] lock {} has been lost, stop renewing",
+                            lock_key
+                        );
+                        break;
+                    }
+                    Err(err) => {
+                        ftlog::error!("[TaskLocker] renew lock {} error: {:?}", lock_key, err)
+                    }
+                }
+            }
+        });
+
+        Ok(LockOutcome::Acquired(TaskLockGuard { renewal }))
+    }
+}
+
+/// 实例标识，容器内的`HOSTNAME`加上一段随机字符串，避免同一台机器上的多个实例冲突
+fn instance_id() -> String {
+    let hostname = std::env::var("HOSTNAME").unwrap_or_else(|_| "akshare-monitor".to_owned());
+    let suffix = Uuid::new_v4().simple().to_string();
+    format!("{}-{}", hostname, &suffix[..8])
+}
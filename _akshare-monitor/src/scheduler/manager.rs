@@ -1,35 +1,86 @@
-//! 本模块提供了一个在运行时工作的调度任务管理器，支持CRUD，对相关定时任务进行持久化的功能并不包含在本模块之中
+//! 本模块提供了一个在运行时工作的调度任务管理器，支持CRUD，
+//! 在挂载了[`TaskStore`]之后，对调度任务的增删改会同时持久化到clickhouse之中
+use anyhow::anyhow;
 use chrono::{DateTime, FixedOffset, Utc};
 use serde::Serialize;
+use snafu::{OptionExt, ResultExt};
 use std::{
-    collections::HashMap,
+    collections::{HashMap, VecDeque},
     future::Future,
     str::FromStr,
-    sync::{Arc, LazyLock, Mutex},
+    sync::{
+        Arc, LazyLock, Mutex, OnceLock,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
 };
-use tokio::sync::{mpsc, oneshot};
+use tokio::sync::{Semaphore, mpsc, oneshot};
+use tokio_util::sync::CancellationToken;
 use uuid::Uuid;
 
-use super::ScheduleTaskType;
+use crate::trade_calendar::TRADE_CALENDAR;
+
+use super::{
+    InvalidCronSnafu, KindNotFoundSnafu, LockOutcome, NeverFiredSnafu, RunStatus, ScheduleTaskType,
+    SchedulerError, TaskLocker, TaskNotFoundSnafu, TaskRunHist, TaskRunShow, TaskStore,
+    TriggerSource,
+};
 
 pub const CST: FixedOffset = FixedOffset::east_opt(8 * 3600).unwrap();
 pub static SCHEDULE_TASK_MANAGER: LazyLock<TaskManager> = LazyLock::new(|| TaskManager::new());
 
 /// 指令类型定义(CRUD)
 enum TaskCommand {
-    Add(ScheduleTask),
-    Update(ScheduleTask), // new task with the same key
-    Remove(String),       // remove with the key of task
+    Add(ScheduleTask, bool), // task with enabled flag
+    Update(ScheduleTask),    // new task with the same key
+    Remove(String),          // remove with the key of task
+    Pause(String),           // stop scheduling but keep the task
+    Resume(String),          // restart scheduling of a paused task
     Trigger(String),
 }
 
 /// 调度任务描述性元信息
 #[derive(Debug, Clone, Serialize)]
 pub struct TaskMeta {
+    /// 调度任务名称，同时作为持久化之后恢复任务的标识，需要保证唯一
     pub name: String,
     pub desc: String,
     pub cron_expr: String,
     pub tag: Option<ScheduleTaskType>,
+    /// 上一次执行还没有结束时，新的一次执行应该如何处理
+    pub overlap: OverlapPolicy,
+    /// 单次执行的超时时间，超时之后本次执行会被中止，`None`代表不设置超时
+    pub timeout: Option<Duration>,
+    /// 是否只在交易日进行定时调度，手动触发不受此限制
+    pub trading_days_only: bool,
+    /// monitor停止期间错过的调度应该如何处理
+    pub misfire: MisfirePolicy,
+}
+
+/// 错过调度的补跑策略，以该任务最近一次执行成功的时间为基准计算错过的调度，
+/// 只在任务加入调度器(monitor启动或者重新创建任务)时生效，从来没有执行成功过的任务不会补跑
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum MisfirePolicy {
+    /// 忽略错过的调度
+    Ignore,
+    /// 无论错过了多少次调度，只补跑一次
+    RunOnce,
+    /// 每一次错过的调度都补跑一次，最多补跑最近的`MAX_MISFIRE_RUNS`次
+    RunAll,
+}
+
+/// `MisfirePolicy::RunAll`策略下最多补跑的次数
+const MAX_MISFIRE_RUNS: usize = 32;
+
+/// 调度任务的重叠执行策略，定时触发和手动触发都会遵循该策略
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum OverlapPolicy {
+    /// 允许多次执行同时进行
+    Allow,
+    /// 已经有一次执行正在进行时，直接跳过新的这次执行
+    Skip,
+    /// 已经有一次执行正在进行时，新的这次执行排队等待上一次执行结束
+    Queue,
 }
 
 /// 调度任务展示信息
@@ -38,8 +89,17 @@ pub struct TaskMetaShow {
     pub name: String,
     pub desc: String,
     pub cron_expr: String,
-    pub next_time: DateTime<FixedOffset>,
+    /// 下一次执行时间，cron表达式之后不会再被触发时为`None`
+    pub next_time: Option<DateTime<FixedOffset>>,
     pub is_alive: bool,
+    pub enabled: bool,
+    pub overlap: OverlapPolicy,
+    /// 正在进行的执行次数
+    pub in_flight: usize,
+    /// 排队等待执行的次数
+    pub queued: usize,
+    /// 最近一次定时调度的锁持有者(即执行该次调度的实例)，没有挂载调度锁时为`None`
+    pub lock_owner: Option<String>,
     pub tag: String,
     pub uuid: String,
 }
@@ -51,8 +111,21 @@ pub trait Schedulable: Sync + Send + 'static {
     }
     /// 调度任务相关描述性字段
     fn gen_meta(&self) -> TaskMeta;
-    /// 在一次调度之中做什么
-    fn execute(self: Arc<Self>) -> Box<dyn Future<Output = anyhow::Result<()>> + Send + 'static>;
+    /// 在一次调度之中做什么，返回本次调度写入的数据行数，
+    /// 任务被取消或者执行超时时`cancel`会被触发，耗时较长的任务应该在合适的时机检查它并尽早退出
+    fn execute(
+        self: Arc<Self>,
+        cancel: CancellationToken,
+    ) -> Box<dyn Future<Output = anyhow::Result<u64>> + Send + 'static>;
+    /// cron表达式是否由配置文件指定，为`true`时不再从持久化存储之中恢复cron表达式
+    fn pinned_cron(&self) -> bool {
+        false
+    }
+    /// 是否只维护实例自身的状态(例如内存之中的交易日历)，为`true`时每个实例都需要执行，不获取调度锁，
+    /// 会写入共享存储的任务必须保持默认的`false`
+    fn instance_local(&self) -> bool {
+        false
+    }
     /// 是否取消当前的这个调度任务，返回`true`代表取消，默认永远不取消
     fn cancel_or_not(self: Arc<Self>) -> Box<dyn Future<Output = bool> + Send + 'static> {
         Box::new(async { false })
@@ -63,16 +136,74 @@ pub trait Schedulable: Sync + Send + 'static {
 pub struct ScheduleTask {
     pub key: String,
     pub task_meta: TaskMeta,
+    /// 由`task_meta.cron_expr`解析得到的调度计划，在任务加入管理器之前完成校验
+    schedule: cron::Schedule,
     schedulable: Arc<dyn Schedulable>,
+    /// 运行时状态，任务被更新之后依旧沿用旧任务的运行时状态
+    runtime: Arc<TaskRuntime>,
+}
+
+/// 调度任务运行时状态，记录正在进行的执行，用于实现[`OverlapPolicy`]
+struct TaskRuntime {
+    in_flight: AtomicUsize,
+    queued: AtomicUsize,
+    /// `Skip`和`Queue`策略下的执行许可，同一时间只有一次执行能够持有
+    permit: Semaphore,
+    /// 任务被取消时触发，每一次执行都会持有它的一个child token
+    cancel: CancellationToken,
+    /// 最近一次定时调度的锁持有者
+    lock_owner: Mutex<Option<String>>,
+}
+
+impl TaskRuntime {
+    fn new() -> Arc<Self> {
+        Arc::new(Self {
+            in_flight: AtomicUsize::new(0),
+            queued: AtomicUsize::new(0),
+            permit: Semaphore::new(1),
+            cancel: CancellationToken::new(),
+            lock_owner: Mutex::new(None),
+        })
+    }
+}
+
+/// 在一次执行结束(或者被中止)时减少正在进行的执行次数
+struct InFlightGuard<'a>(&'a AtomicUsize);
+
+impl<'a> InFlightGuard<'a> {
+    fn new(in_flight: &'a AtomicUsize) -> Self {
+        in_flight.fetch_add(1, Ordering::SeqCst);
+        Self(in_flight)
+    }
+}
+
+impl Drop for InFlightGuard<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
 }
 
 // pub type TasksScheduleMapSnapShot = HashMap<String, TaskMeta>;
-pub type TasksScheduleMap = HashMap<String, (ScheduleTask, oneshot::Sender<()>)>;
+/// tokio task's handler为`None`时代表该任务处于停用状态
+pub type TasksScheduleMap = HashMap<String, (ScheduleTask, Option<oneshot::Sender<()>>)>;
+/// 在调度器启动时挂载的外部依赖，会被共享到每一个tokio task之中
+#[derive(Default)]
+struct Attachments {
+    /// 持久化存储，用于恢复任务以及记录执行历史
+    store: OnceLock<TaskStore>,
+    /// 调度锁，多实例部署时保证每一次调度只在一个实例上执行，未挂载时不加锁
+    locker: OnceLock<TaskLocker>,
+}
+type SharedAttachments = Arc<Attachments>;
 /// 任务调度管理器，用于执行`TaskCommand`之中的相关命令
 pub struct TaskManager {
     /// HashMap<Key, (Task, tokio task's handler)>映射到`Task`到调度任务
     tasks_map: Arc<Mutex<TasksScheduleMap>>,
     command_tx: mpsc::Sender<TaskCommand>,
+    /// 调度任务持久化存储以及调度锁，在调度器启动时挂载
+    attached: SharedAttachments,
+    /// HashMap<任务名称, Schedulable>，所有加入过管理器的任务种类，用于重新创建已经被取消的任务
+    kinds: Mutex<HashMap<String, Arc<dyn Schedulable>>>,
 }
 
 /// private
@@ -81,42 +212,57 @@ impl TaskManager {
     fn new() -> Self {
         let (tx, rx) = mpsc::channel(100);
         let tasks_map = Arc::new(Mutex::new(HashMap::new()));
+        let attached = Arc::new(Attachments::default());
 
         let manager = TaskManager {
             tasks_map: Arc::clone(&tasks_map),
             command_tx: tx,
+            attached: Arc::clone(&attached),
+            kinds: Mutex::new(HashMap::new()),
         };
-        Self::start_command_processor(Arc::clone(&tasks_map), rx);
+        Self::start_command_processor(Arc::clone(&tasks_map), attached, rx);
         manager
     }
 
     /// 开始调度任务管理循环
     fn start_command_processor(
         tasks_map: Arc<Mutex<TasksScheduleMap>>,
+        attached: SharedAttachments,
         mut rx: mpsc::Receiver<TaskCommand>,
     ) {
         tokio::spawn(async move {
             while let Some(cmd) = rx.recv().await {
                 match cmd {
-                    TaskCommand::Add(task) => Self::_add_task(Arc::clone(&tasks_map), task).await,
+                    TaskCommand::Add(task, enabled) => {
+                        Self::_add_task(Arc::clone(&tasks_map), &attached, task, enabled).await
+                    }
                     TaskCommand::Update(new_task) => {
-                        Self::_update_task(Arc::clone(&tasks_map), new_task).await
+                        Self::_update_task(Arc::clone(&tasks_map), &attached, new_task).await
                     }
                     TaskCommand::Remove(key) => {
                         Self::_cancel_task(Arc::clone(&tasks_map), key).await
                     }
+                    TaskCommand::Pause(key) => Self::_pause_task(Arc::clone(&tasks_map), key).await,
+                    TaskCommand::Resume(key) => {
+                        Self::_resume_task(Arc::clone(&tasks_map), &attached, key).await
+                    }
                     TaskCommand::Trigger(task_id) => {
-                        Self::_trigger_task(Arc::clone(&tasks_map), &task_id).await
+                        Self::_trigger_task(Arc::clone(&tasks_map), &attached, &task_id).await
                     }
                 }
             }
         });
     }
 
-    /// 添加新任务到调度任务管理器之中
-    async fn _add_task(tasks_map: Arc<Mutex<TasksScheduleMap>>, task: ScheduleTask) {
+    /// 添加新任务到调度任务管理器之中，停用的任务只会被记录，不会被调度
+    async fn _add_task(
+        tasks_map: Arc<Mutex<TasksScheduleMap>>,
+        attached: &SharedAttachments,
+        task: ScheduleTask,
+        enabled: bool,
+    ) {
         // 创建并启动tokio任务
-        let handle = Self::spawn_task_handler(&task);
+        let handle = enabled.then(|| Self::spawn_task_handler(&task, attached, true));
         tasks_map
             .lock()
             .unwrap()
@@ -124,40 +270,93 @@ impl TaskManager {
     }
 
     /// 更新任务管理器之中存在的相关任务
-    async fn _update_task(tasks_map: Arc<Mutex<TasksScheduleMap>>, new_task: ScheduleTask) {
+    async fn _update_task(
+        tasks_map: Arc<Mutex<TasksScheduleMap>>,
+        attached: &SharedAttachments,
+        new_task: ScheduleTask,
+    ) {
         let mut tasks_guard = tasks_map.lock().unwrap();
         // 检查任务是否存在并获取旧任务句柄，如果没有旧的任务句柄的话，旧相当于直接新增一个任务
+        // 处于停用状态的任务在更新之后依旧保持停用
+        let mut enabled = true;
+        let mut new_task = new_task;
         if let Some((old_task, handle)) = tasks_guard.remove(&new_task.key) {
             assert_eq!(old_task.key, new_task.key);
+            new_task.runtime = old_task.runtime;
+            enabled = handle.is_some();
+            if let Some(handle) = handle {
+                let _ = handle.send(()).inspect_err(|_| {
+                    ftlog::error!("[in update_task] send error, may be the task has been canceled!")
+                });
+            }
+        }
+        let new_handle = enabled.then(|| Self::spawn_task_handler(&new_task, attached, false));
+        tasks_guard.insert(new_task.key.clone(), (new_task, new_handle));
+    }
+
+    /// 暂停一个任务，停止对其进行调度，但是依旧保留在任务管理器之中
+    async fn _pause_task(tasks_map: Arc<Mutex<TasksScheduleMap>>, key: String) {
+        let mut tasks_guard = tasks_map.lock().unwrap();
+        if let Some((_, handle)) = tasks_guard.get_mut(&key)
+            && let Some(handle) = handle.take()
+        {
             let _ = handle.send(()).inspect_err(|_| {
-                ftlog::error!("[in update_task] send error, may be the task has been canceled!")
+                ftlog::error!("[in pause_task] send error, may be the task has been canceled!")
             });
         }
-        let new_handle = Self::spawn_task_handler(&new_task);
-        tasks_guard.insert(new_task.key.clone(), (new_task, new_handle));
+    }
+
+    /// 恢复一个被暂停的任务
+    async fn _resume_task(
+        tasks_map: Arc<Mutex<TasksScheduleMap>>,
+        attached: &SharedAttachments,
+        key: String,
+    ) {
+        let mut tasks_guard = tasks_map.lock().unwrap();
+        if let Some((task, handle)) = tasks_guard.get_mut(&key)
+            && handle.is_none()
+        {
+            *handle = Some(Self::spawn_task_handler(task, attached, false));
+        }
     }
 
     /// 直接触发任务管理其之中一个uuid对应的任务的`execute`方法
-    async fn _trigger_task(tasks_map: Arc<Mutex<TasksScheduleMap>>, task_id: &str) {
+    async fn _trigger_task(
+        tasks_map: Arc<Mutex<TasksScheduleMap>>,
+        attached: &SharedAttachments,
+        task_id: &str,
+    ) {
         let schedulable_handle = {
             let task_guard = tasks_map.lock().unwrap();
             let schedule_task = task_guard.get(task_id);
-            schedule_task.map(|task| (task.0.schedulable.clone(), task.0.task_meta.clone()))
+            schedule_task.map(|task| {
+                (
+                    task.0.schedulable.clone(),
+                    task.0.task_meta.clone(),
+                    task.0.runtime.clone(),
+                )
+            })
         };
 
-        let Some((schedulable, task_meta)) = schedulable_handle else {
+        let Some((schedulable, task_meta, runtime)) = schedulable_handle else {
             ftlog::warn!("[trigger_task] input task_id not found");
             return;
         };
 
         ftlog::info!("[trigger_task] task_meta = {task_meta:?} triggered");
+        let attached = Arc::clone(attached);
+        let key = task_id.to_owned();
         tokio::spawn(async move {
-            Box::into_pin(schedulable.execute())
-                .await
-                .inspect(|_| ftlog::info!("[trigger_task complete] task_meta = {task_meta:?}"))
-                .inspect_err(|err| {
-                    ftlog::error!("[trigger_task] task_meta = {task_meta:?}, error = {err}")
-                });
+            Self::run_task(
+                &attached,
+                &key,
+                &task_meta,
+                schedulable,
+                &runtime,
+                TriggerSource::Manual,
+                None,
+            )
+            .await;
         });
     }
 
@@ -167,20 +366,41 @@ impl TaskManager {
         let task_opt = { tasks_map.lock().unwrap().remove(&key) };
         if let Some((task, handle)) = task_opt {
             assert_eq!(task.key, key);
-            let _ = handle.send(()).inspect_err(|_| {
-                ftlog::error!("[in cancel_task] send error, may be the task has been canceled!")
-            });
+            // 中止所有正在进行的执行
+            task.runtime.cancel.cancel();
+            if let Some(handle) = handle {
+                let _ = handle.send(()).inspect_err(|_| {
+                    ftlog::error!("[in cancel_task] send error, may be the task has been canceled!")
+                });
+            }
         }
     }
 
-    /// 创建调度任务
-    fn spawn_task_handler(task: &ScheduleTask) -> oneshot::Sender<()> {
+    /// 创建调度任务，`catch_up`为`true`时首先按照任务的`MisfirePolicy`补跑错过的调度
+    fn spawn_task_handler(
+        task: &ScheduleTask,
+        attached: &SharedAttachments,
+        catch_up: bool,
+    ) -> oneshot::Sender<()> {
+        let key = task.key.clone();
+        let attached = Arc::clone(attached);
         let task_meta = task.task_meta.clone();
         let schedulable = Arc::clone(&task.schedulable);
-        // FIXME 这个东西是网络传进来的数据，很可能是错误的，不能直接unwrap
-        let schedule = cron::Schedule::from_str(&task_meta.cron_expr).unwrap();
+        let runtime = Arc::clone(&task.runtime);
+        let schedule = task.schedule.clone();
         let (tx, rx) = oneshot::channel();
 
+        if catch_up && task_meta.misfire != MisfirePolicy::Ignore {
+            tokio::spawn(Self::catch_up(
+                Arc::clone(&attached),
+                key.clone(),
+                task_meta.clone(),
+                schedule.clone(),
+                Arc::clone(&schedulable),
+                Arc::clone(&runtime),
+            ));
+        }
+
         tokio::spawn(async move {
             // let snippet = snippet_gen();
             tokio::select! {
@@ -201,11 +421,27 @@ impl TaskManager {
                         if Box::into_pin(schedulable.clone().cancel_or_not()).await {
                             break;
                         }
-                        Box::into_pin(schedulable.clone().execute())
-                            .await
-                            .inspect_err(
-                                |err| ftlog::error!("An error occurred while collecting data, task = {:?}, error = {:?}", schedulable.gen_meta(), err)
+                        if task_meta.trading_days_only && !TRADE_CALENDAR.is_trading_day(next.date_naive()) {
+                            ftlog::info!(
+                                target: "scheduler::info",
+                                "[meta = {:?}] {} 不是交易日，跳过本次执行",
+                                task_meta,
+                                next.format("%Y-%m-%d")
                             );
+                            continue;
+                        }
+                        // 每次执行都在单独的tokio task之中进行，避免一次缓慢的执行阻塞之后的调度，
+                        // 多次执行之间如何重叠由`OverlapPolicy`决定
+                        let (attached, key, task_meta, schedulable, runtime) = (
+                            Arc::clone(&attached),
+                            key.clone(),
+                            task_meta.clone(),
+                            Arc::clone(&schedulable),
+                            Arc::clone(&runtime),
+                        );
+                        tokio::spawn(async move {
+                            Self::run_task(&attached, &key, &task_meta, schedulable, &runtime, TriggerSource::Cron, Some(next)).await;
+                        });
                     }
                 }  => {}
             }
@@ -213,42 +449,350 @@ impl TaskManager {
 
         tx
     }
+
+    /// 按照任务的`MisfirePolicy`补跑monitor停止期间错过的调度
+    async fn catch_up(
+        attached: SharedAttachments,
+        key: String,
+        task_meta: TaskMeta,
+        schedule: cron::Schedule,
+        schedulable: Arc<dyn Schedulable>,
+        runtime: Arc<TaskRuntime>,
+    ) {
+        let Some(task_store) = attached.store.get() else {
+            return;
+        };
+        let last_success = match task_store.last_success(&key).await {
+            Ok(Some(last_success)) => last_success.with_timezone(&CST),
+            Ok(None) => return,
+            Err(err) => {
+                ftlog::error!(
+                    "[catch_up] query last success run error, task_meta = {:?}, error = {:?}",
+                    task_meta,
+                    err
+                );
+                return;
+            }
+        };
+
+        // 只保留最近的`MAX_MISFIRE_RUNS`次错过的调度，RunOnce补跑的是最近一次错过的调度
+        let now = cst_now();
+        let mut missed_count = 0;
+        let mut missed = VecDeque::with_capacity(MAX_MISFIRE_RUNS);
+        for tick in schedule
+            .after(&last_success)
+            .take_while(|tick| *tick <= now)
+            .filter(|tick| {
+                !task_meta.trading_days_only || TRADE_CALENDAR.is_trading_day(tick.date_naive())
+            })
+        {
+            missed_count += 1;
+            if missed.len() == MAX_MISFIRE_RUNS {
+                missed.pop_front();
+            }
+            missed.push_back(tick);
+        }
+        let missed = Vec::from(missed);
+        // 补跑时以错过的调度时间作为调度锁的标识，多个实例同时启动时只会有一个实例进行补跑
+        let ticks = match task_meta.misfire {
+            MisfirePolicy::Ignore => &[][..],
+            MisfirePolicy::RunOnce => &missed[missed.len().saturating_sub(1)..],
+            MisfirePolicy::RunAll => &missed[..],
+        };
+        if ticks.is_empty() {
+            return;
+        }
+
+        ftlog::info!(
+            target: "scheduler::info",
+            "[meta = {:?}] 上次成功执行时间: {}, 错过了{}次调度, 补跑{}次",
+            task_meta,
+            last_success.format("%Y-%m-%d %H:%M:%S"),
+            missed_count,
+            ticks.len()
+        );
+        for tick in ticks {
+            Self::run_task(
+                &attached,
+                &key,
+                &task_meta,
+                Arc::clone(&schedulable),
+                &runtime,
+                TriggerSource::Misfire,
+                Some(*tick),
+            )
+            .await;
+        }
+    }
+
+    /// 按照任务的`OverlapPolicy`执行一次调度任务，并将本次执行的结果记录到执行历史之中，
+    /// `tick`为本次执行对应的调度时间，不为`None`时需要先获取该次调度的调度锁，手动触发不加锁
+    async fn run_task(
+        attached: &SharedAttachments,
+        key: &str,
+        task_meta: &TaskMeta,
+        schedulable: Arc<dyn Schedulable>,
+        runtime: &TaskRuntime,
+        trigger: TriggerSource,
+        tick: Option<DateTime<FixedOffset>>,
+    ) {
+        let _permit = match task_meta.overlap {
+            OverlapPolicy::Allow => None,
+            OverlapPolicy::Skip => match runtime.permit.try_acquire() {
+                Ok(permit) => Some(permit),
+                Err(_) => {
+                    ftlog::warn!(
+                        "[run_task skipped] task_meta = {:?}, trigger = {:?}, previous run is still in flight",
+                        task_meta,
+                        trigger
+                    );
+                    return;
+                }
+            },
+            OverlapPolicy::Queue => {
+                runtime.queued.fetch_add(1, Ordering::SeqCst);
+                // 信号量永远不会被关闭，这里的acquire只会因为任务被取消而放弃
+                let permit = tokio::select! {
+                    permit = runtime.permit.acquire() => permit.ok(),
+                    _ = runtime.cancel.cancelled() => None,
+                };
+                runtime.queued.fetch_sub(1, Ordering::SeqCst);
+                if permit.is_none() {
+                    return;
+                }
+                permit
+            }
+        };
+        // 只维护实例自身状态的任务每个实例都需要执行，不加锁
+        let tick = tick.filter(|_| !schedulable.instance_local());
+        let _lock = match (attached.locker.get(), tick) {
+            (Some(locker), Some(tick)) => match locker.try_lock(&task_meta.name, tick).await {
+                Ok(LockOutcome::Acquired(guard)) => {
+                    *runtime.lock_owner.lock().unwrap() = Some(locker.owner().to_owned());
+                    Some(guard)
+                }
+                Ok(LockOutcome::Held(owner)) => {
+                    ftlog::info!(
+                        target: "scheduler::info",
+                        "[meta = {:?}] {} 这次调度已经由实例 {} 执行，跳过本次执行",
+                        task_meta,
+                        tick.format("%Y-%m-%d %H:%M:%S"),
+                        owner
+                    );
+                    *runtime.lock_owner.lock().unwrap() = Some(owner);
+                    return;
+                }
+                // redis不可用时依旧执行，宁可重复采集也不要漏采
+                Err(err) => {
+                    ftlog::error!(
+                        "[run_task] acquire schedule lock error, run without lock, task_meta = {:?}, error = {:?}",
+                        task_meta,
+                        err
+                    );
+                    None
+                }
+            },
+            _ => None,
+        };
+        let _in_flight = InFlightGuard::new(&runtime.in_flight);
+
+        let run_id = Uuid::new_v4().to_string();
+        let start_time = Utc::now();
+        let cancel = runtime.cancel.child_token();
+        let execution = Box::into_pin(schedulable.execute(cancel.clone()));
+        let deadline = async {
+            match task_meta.timeout {
+                Some(timeout) => tokio::time::sleep(timeout).await,
+                None => std::future::pending().await,
+            }
+        };
+        // 超时或者取消时直接drop掉正在执行的future，同时触发cancel token通知可能存在的子任务
+        let result = tokio::select! {
+            result = execution => result.map_err(|err| (RunStatus::Failed, err)),
+            _ = cancel.cancelled() => Err((RunStatus::Cancelled, anyhow!("task has been cancelled"))),
+            _ = deadline => {
+                cancel.cancel();
+                Err((
+                    RunStatus::TimedOut,
+                    anyhow!("task timed out after {:?}", task_meta.timeout.unwrap_or_default()),
+                ))
+            }
+        };
+        let end_time = Utc::now();
+
+        let (status, error, rows_written) = match result {
+            Ok(rows_written) => {
+                ftlog::info!(
+                    "[run_task complete] task_meta = {:?}, trigger = {:?}, rows_written = {}",
+                    task_meta,
+                    trigger,
+                    rows_written
+                );
+                (RunStatus::Success, String::new(), rows_written)
+            }
+            Err((status, err)) => {
+                ftlog::error!(
+                    "An error occurred while collecting data, task = {:?}, trigger = {:?}, status = {:?}, error = {:?}",
+                    task_meta,
+                    trigger,
+                    status,
+                    err
+                );
+                (status, format!("{:#}", err), 0)
+            }
+        };
+
+        let Some(store) = attached.store.get() else {
+            return;
+        };
+        let hist = TaskRunHist {
+            task_key: key.to_owned(),
+            task_name: task_meta.name.clone(),
+            run_id,
+            trigger,
+            start_time,
+            end_time,
+            status,
+            error,
+            rows_written,
+        };
+        let _ = store.record_run(&hist).await.inspect_err(|err| {
+            ftlog::error!(
+                "[in TaskManager::run_task] error occurred when record run hist = {:?}, error = {:?}",
+                hist,
+                err
+            )
+        });
+    }
 }
 
 /// public
 impl TaskManager {
-    pub async fn add_task<S: Schedulable>(&self, task: S) -> String {
-        let key = task.gen_key();
-        let schedule_task = ScheduleTask {
-            key: key.clone(),
-            task_meta: task.gen_meta(),
-            schedulable: Arc::new(task),
+    /// 挂载调度任务持久化存储，需要在添加任何调度任务之前调用，重复挂载会被忽略
+    pub fn attach_store(&self, store: TaskStore) {
+        if self.attached.store.set(store).is_err() {
+            ftlog::warn!("[in TaskManager::attach_store] task store has been attached already");
+        }
+    }
+
+    /// 挂载调度锁，同样需要在添加任何调度任务之前调用，重复挂载会被忽略
+    pub fn attach_locker(&self, locker: TaskLocker) {
+        if self.attached.locker.set(locker).is_err() {
+            ftlog::warn!("[in TaskManager::attach_locker] task locker has been attached already");
+        }
+    }
+
+    /// 添加一个调度任务，假如持久化存储之中已经存在同名任务的记录，
+    /// 则沿用记录之中的uuid、cron表达式以及启用状态
+    pub async fn add_task<S: Schedulable>(&self, task: S) -> Result<String, SchedulerError> {
+        let schedulable: Arc<dyn Schedulable> = Arc::new(task);
+        self.kinds
+            .lock()
+            .unwrap()
+            .insert(schedulable.gen_meta().name, Arc::clone(&schedulable));
+        self.add_schedulable(schedulable, false).await
+    }
+
+    /// 通过任务种类(即任务名称)重新创建一个之前被取消的任务，返回任务的uuid，
+    /// 假如该任务依旧存在于管理器之中，则直接恢复对它的调度
+    pub async fn recreate_task(&self, kind: &str) -> Result<String, SchedulerError> {
+        let existed = self.inspect_by(|tasks_map| {
+            tasks_map
+                .iter()
+                .find(|(_, (task, _))| task.task_meta.name == kind)
+                .map(|(key, _)| key.clone())
+        });
+        if let Some(key) = existed {
+            self.resume_task(&key).await?;
+            return Ok(key);
+        }
+
+        let schedulable = self
+            .kinds
+            .lock()
+            .unwrap()
+            .get(kind)
+            .cloned()
+            .context(KindNotFoundSnafu { kind })?;
+        self.add_schedulable(schedulable, true).await
+    }
+
+    /// 获取所有注册过的任务种类
+    pub fn kinds(&self) -> Vec<TaskMeta> {
+        self.kinds
+            .lock()
+            .unwrap()
+            .values()
+            .map(|schedulable| schedulable.gen_meta())
+            .collect()
+    }
+
+    /// 修改一个任务的cron表达式
+    pub async fn update_cron(&self, key: &str, cron_expr: String) -> Result<(), SchedulerError> {
+        let schedule = parse_cron(&cron_expr)?;
+        let Some((schedulable, mut task_meta, enabled)) = self.inspect_by(|tasks_map| {
+            tasks_map.get(key).map(|(task, handle)| {
+                (
+                    Arc::clone(&task.schedulable),
+                    task.task_meta.clone(),
+                    handle.is_some(),
+                )
+            })
+        }) else {
+            return TaskNotFoundSnafu { key }.fail();
         };
+        task_meta.cron_expr = cron_expr;
 
-        ftlog::info!(
-            "[In TaskManager add_task] task_meta = {:?}",
-            schedule_task.task_meta
-        );
+        ftlog::info!("[In TaskManager update_cron] task_meta = {:?}", task_meta);
+        self.persist(key, &task_meta, enabled).await;
 
+        let schedule_task = ScheduleTask {
+            key: key.to_owned(),
+            task_meta,
+            schedule,
+            schedulable,
+            runtime: TaskRuntime::new(),
+        };
         let _ = self
             .command_tx
-            .send(TaskCommand::Add(schedule_task))
+            .send(TaskCommand::Update(schedule_task))
             .await
             .inspect_err(|err| {
                 ftlog::error!(
-                    "[in TaskManager::add_task] error occurred when add task to task manager: {}",
-                    err.to_string()
+                    "[in TaskManager::update_cron] error occurred when update task in task manager: {}",
+                    err
                 )
             });
+        Ok(())
+    }
+
+    /// 暂停一个任务，暂停状态会被持久化
+    pub async fn pause_task(&self, key: &str) -> Result<(), SchedulerError> {
+        self.set_enabled(key, false).await
+    }
+
+    /// 恢复一个被暂停的任务
+    pub async fn resume_task(&self, key: &str) -> Result<(), SchedulerError> {
+        self.set_enabled(key, true).await
+    }
 
-        key
+    /// 任务管理器之中是否存在该uuid对应的任务
+    pub fn contains_task(&self, key: &str) -> bool {
+        self.inspect_by(|tasks_map| tasks_map.contains_key(key))
     }
 
-    pub async fn update_task<S: Schedulable>(&self, old_key: String, new_task: S) {
+    pub async fn update_task<S: Schedulable>(
+        &self,
+        old_key: String,
+        new_task: S,
+    ) -> Result<(), SchedulerError> {
+        let task_meta = new_task.gen_meta();
         let schedule_task = ScheduleTask {
             key: old_key,
-            task_meta: new_task.gen_meta(),
+            schedule: parse_cron(&task_meta.cron_expr)?,
+            task_meta,
             schedulable: Arc::new(new_task),
+            runtime: TaskRuntime::new(),
         };
 
         ftlog::info!(
@@ -256,6 +800,15 @@ impl TaskManager {
             schedule_task.task_meta
         );
 
+        // 与`_update_task`一致，被暂停的任务在更新之后依旧保持暂停状态
+        let enabled = self.inspect_by(|tasks_map| {
+            tasks_map
+                .get(&schedule_task.key)
+                .is_none_or(|(_, handle)| handle.is_some())
+        });
+        self.persist(&schedule_task.key, &schedule_task.task_meta, enabled)
+            .await;
+
         let _ = self
             .command_tx
             .send(TaskCommand::Update(schedule_task))
@@ -266,6 +819,7 @@ impl TaskManager {
                     err
                 )
             });
+        Ok(())
     }
 
     pub async fn trigger_task(&self, task_id: String) {
@@ -277,19 +831,33 @@ impl TaskManager {
             });
     }
 
-    pub async fn cancel_task(&self, key: String) {
+    pub async fn cancel_task(&self, key: String) -> Result<(), SchedulerError> {
+        if !self.contains_task(&key) {
+            return TaskNotFoundSnafu { key }.fail();
+        }
+        // 被取消的任务在重启之后依旧保持停用状态
+        if let Some(store) = self.attached.store.get() {
+            let _ = store.set_enabled(&key, false).await.inspect_err(|err| {
+                ftlog::error!(
+                    "[in TaskManager::cancel_task] error occurred when persist task: {:?}",
+                    err
+                )
+            });
+        }
+
         let _ = self.command_tx.send(TaskCommand::Remove(key)).await.inspect_err(|err| {
                 ftlog::error!(
                     "[in TaskManager::cancel_task] error occurred when cancel task to task manager: {}",
                     err
                 )
             });
+        Ok(())
     }
 
     /// 清除僵尸️🧟‍♀️任务
     pub fn wait_tasks(&self) {
         let mut maps_guard = self.tasks_map.lock().unwrap();
-        maps_guard.retain(|_, (_, sender)| !sender.is_closed());
+        maps_guard.retain(|_, (_, sender)| !sender.as_ref().is_some_and(|s| s.is_closed()));
     }
 
     pub fn inspect(&self, tag: Option<ScheduleTaskType>) -> Vec<TaskMetaShow> {
@@ -300,8 +868,7 @@ impl TaskManager {
                 task.task_meta.tag == tag || matches!(tag, Some(ScheduleTaskType::All))
             })
             .map(|(key, (task, sender))| {
-                let schedule = cron::Schedule::from_str(&task.task_meta.cron_expr).unwrap();
-                let next_time = schedule.upcoming(CST).next().unwrap();
+                let next_time = next_fire_time(&task.schedule, task.task_meta.trading_days_only);
                 TaskMetaShow {
                     name: task.task_meta.name.clone(),
                     desc: task.task_meta.desc.clone(),
@@ -313,13 +880,27 @@ impl TaskManager {
                         .as_ref()
                         .map(|tag| tag.to_string())
                         .unwrap_or("None".to_owned()),
-                    is_alive: !sender.is_closed(),
+                    is_alive: sender.as_ref().is_some_and(|s| !s.is_closed()),
+                    enabled: sender.is_some(),
+                    overlap: task.task_meta.overlap,
+                    in_flight: task.runtime.in_flight.load(Ordering::SeqCst),
+                    queued: task.runtime.queued.load(Ordering::SeqCst),
+                    lock_owner: task.runtime.lock_owner.lock().unwrap().clone(),
                     uuid: key.clone(),
                 }
             })
             .collect()
     }
 
+    /// 获取一个调度任务最近的执行历史，没有挂载持久化存储时返回空列表
+    pub async fn runs(&self, key: &str, limit: u64) -> anyhow::Result<Vec<TaskRunShow>> {
+        let Some(store) = self.attached.store.get() else {
+            return Ok(Vec::new());
+        };
+        let hists = store.runs(key, limit).await?;
+        Ok(hists.into_iter().map(TaskRunShow::from).collect())
+    }
+
     pub fn inspect_by<F, R>(&self, f: F) -> R
     where
         F: FnOnce(&TasksScheduleMap) -> R,
@@ -329,6 +910,178 @@ impl TaskManager {
     }
 }
 
+/// private helpers
+impl TaskManager {
+    /// 将一个`Schedulable`加入到任务管理器之中，假如持久化存储之中已经存在同名任务的记录，
+    /// 则沿用记录之中的uuid、cron表达式以及启用状态，`force_enable`为`true`时忽略记录之中的启用状态
+    async fn add_schedulable(
+        &self,
+        schedulable: Arc<dyn Schedulable>,
+        force_enable: bool,
+    ) -> Result<String, SchedulerError> {
+        let mut task_meta = schedulable.gen_meta();
+        let mut schedule = parse_cron(&task_meta.cron_expr)?;
+        let mut enabled = true;
+        let key = match self
+            .attached
+            .store
+            .get()
+            .and_then(|store| store.restore(&task_meta.name))
+        {
+            Some(record) => {
+                // 持久化记录之中的cron表达式无效时沿用任务默认的cron表达式，
+                // 由配置文件指定的cron表达式优先于持久化记录
+                if !schedulable.pinned_cron() {
+                    match parse_cron(&record.cron_expr) {
+                        Ok(restored) => {
+                            task_meta.cron_expr = record.cron_expr;
+                            schedule = restored;
+                        }
+                        Err(err) => ftlog::warn!(
+                            "[in TaskManager::add_task] restored cron of task {} is invalid: {}",
+                            task_meta.name,
+                            err
+                        ),
+                    }
+                }
+                enabled = record.enabled || force_enable;
+                record.key
+            }
+            None => schedulable.gen_key(),
+        };
+
+        let schedule_task = ScheduleTask {
+            key: key.clone(),
+            task_meta,
+            schedule,
+            schedulable,
+            runtime: TaskRuntime::new(),
+        };
+
+        ftlog::info!(
+            "[In TaskManager add_task] task_meta = {:?}, enabled = {}",
+            schedule_task.task_meta,
+            enabled
+        );
+
+        self.persist(&key, &schedule_task.task_meta, enabled).await;
+
+        let _ = self
+            .command_tx
+            .send(TaskCommand::Add(schedule_task, enabled))
+            .await
+            .inspect_err(|err| {
+                ftlog::error!(
+                    "[in TaskManager::add_task] error occurred when add task to task manager: {}",
+                    err.to_string()
+                )
+            });
+
+        Ok(key)
+    }
+
+    /// 修改任务的启用状态并持久化
+    async fn set_enabled(&self, key: &str, enabled: bool) -> Result<(), SchedulerError> {
+        if !self.contains_task(key) {
+            return TaskNotFoundSnafu { key }.fail();
+        }
+        if let Some(store) = self.attached.store.get() {
+            let _ = store.set_enabled(key, enabled).await.inspect_err(|err| {
+                ftlog::error!(
+                    "[in TaskManager::set_enabled] error occurred when persist task: {:?}",
+                    err
+                )
+            });
+        }
+
+        let command = if enabled {
+            TaskCommand::Resume(key.to_owned())
+        } else {
+            TaskCommand::Pause(key.to_owned())
+        };
+        let _ = self.command_tx.send(command).await.inspect_err(|err| {
+            ftlog::error!(
+                "[in TaskManager::set_enabled] error occurred when send command to task manager: {}",
+                err
+            )
+        });
+        Ok(())
+    }
+
+    /// 将调度任务写入持久化存储，没有挂载存储时什么都不做
+    async fn persist(&self, key: &str, task_meta: &TaskMeta, enabled: bool) {
+        let Some(store) = self.attached.store.get() else {
+            return;
+        };
+        let _ = store
+            .save(key, task_meta, enabled)
+            .await
+            .inspect_err(|err| {
+                ftlog::error!(
+                    "[in TaskManager::persist] error occurred when persist task = {:?}, error = {:?}",
+                    task_meta,
+                    err
+                )
+            });
+    }
+}
+
+/// 解析并校验一个cron表达式，无法解析或者之后再也不会被触发的表达式都会被拒绝
+pub fn parse_cron(cron_expr: &str) -> Result<cron::Schedule, SchedulerError> {
+    let schedule = cron::Schedule::from_str(cron_expr).context(InvalidCronSnafu { cron_expr })?;
+    if schedule.upcoming(CST).next().is_none() {
+        return NeverFiredSnafu { cron_expr }.fail();
+    }
+    Ok(schedule)
+}
+
+/// 计算一个调度计划的下一次执行时间，`trading_days_only`为`true`时跳过非交易日，
+/// 最多向后查找一年，找不到时返回`None`
+fn next_fire_time(
+    schedule: &cron::Schedule,
+    trading_days_only: bool,
+) -> Option<DateTime<FixedOffset>> {
+    let mut next = schedule.upcoming(CST).next()?;
+    for _ in 0..366 {
+        if !trading_days_only || TRADE_CALENDAR.is_trading_day(next.date_naive()) {
+            return Some(next);
+        }
+        // 直接跳到下一天的零点之后继续查找
+        let next_day = next
+            .date_naive()
+            .succ_opt()?
+            .and_hms_opt(0, 0, 0)?
+            .and_local_timezone(CST)
+            .single()?;
+        next = schedule
+            .after(&(next_day - chrono::Duration::seconds(1)))
+            .next()?;
+    }
+    None
+}
+
 pub fn cst_now() -> DateTime<FixedOffset> {
     Utc::now().with_timezone(&CST)
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_cron() {
+        assert!(parse_cron("0 50 18 * * MON-FRI").is_ok());
+        assert!(matches!(
+            parse_cron("0 50 25 * * MON-FRI"),
+            Err(SchedulerError::InvalidCron { .. })
+        ));
+        assert!(matches!(
+            parse_cron("not a cron"),
+            Err(SchedulerError::InvalidCron { .. })
+        ));
+        assert!(matches!(
+            parse_cron("0 0 0 1 1 * 2000"),
+            Err(SchedulerError::NeverFired { .. })
+        ));
+    }
+}
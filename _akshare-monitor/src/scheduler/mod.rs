@@ -1,12 +1,19 @@
 #![allow(unused)]
 
+mod error;
+mod lock;
 mod manager;
+mod persist;
 use std::sync::Arc;
 
+pub use error::*;
+pub use lock::*;
 pub use manager::*;
+pub use persist::*;
 use serde::{Deserialize, Serialize};
+use tokio_util::sync::CancellationToken;
 
-use crate::{init::ExternalResource, tasks};
+use crate::{config::INIT_CONFIG, init::ExternalResource, tasks};
 
 /// 当前调度任务管理器之中的所有调度任务类型
 #[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
@@ -34,11 +41,33 @@ impl ToString for ScheduleTaskType {
 
 /// 在服务器启动时开启一些默认启动的调度任务
 pub async fn scheduler_start_up(ext_res: ExternalResource) -> anyhow::Result<()> {
+    // 首先挂载持久化存储，之后加入的任务会尝试从中恢复之前的uuid以及运行时修改
+    let store = TaskStore::load(ext_res.ch_client.clone()).await?;
+    SCHEDULE_TASK_MANAGER.attach_store(store);
+    // 挂载基于redis的调度锁，多个monitor实例同时部署时每一次调度只会在一个实例上执行，
+    // 没有配置redis或者redis不可用时不加锁运行，不影响monitor启动
+    match &INIT_CONFIG.redis {
+        Some(redis) => match TaskLocker::connect(redis).await {
+            Ok(locker) => {
+                ftlog::info!(
+                    "scheduler lock owner of current instance: {}",
+                    locker.owner()
+                );
+                SCHEDULE_TASK_MANAGER.attach_locker(locker);
+            }
+            Err(err) => ftlog::error!(
+                "failed to connect redis for scheduler lock, running without lock: {:?}",
+                err
+            ),
+        },
+        None => ftlog::warn!("redis is not configured, running scheduler without lock"),
+    }
+
     // 加入心跳检测任务和清除zombie task任务
-    SCHEDULE_TASK_MANAGER.add_task(SchedHeartBeat).await;
-    SCHEDULE_TASK_MANAGER.add_task(SchedWaitZombie).await;
+    SCHEDULE_TASK_MANAGER.add_task(SchedHeartBeat).await?;
+    SCHEDULE_TASK_MANAGER.add_task(SchedWaitZombie).await?;
 
-    tasks::start_up_monitor_tasks(ext_res).await;
+    tasks::start_up_monitor_tasks(ext_res).await?;
 
     let snap_shot = SCHEDULE_TASK_MANAGER.inspect(Some(ScheduleTaskType::All));
     let message = format!("当前调度器内部状态：{:#?}", snap_shot);
@@ -58,17 +87,26 @@ impl Schedulable for SchedHeartBeat {
             desc: "心跳任务，每10分钟将调度器状态写入日志".to_owned(),
             cron_expr: "0 */10 * * * * *".to_string(),
             tag: Some(ScheduleTaskType::System),
+            overlap: OverlapPolicy::Skip,
+            timeout: None,
+            trading_days_only: false,
+            misfire: MisfirePolicy::Ignore,
         }
     }
 
+    fn instance_local(&self) -> bool {
+        true
+    }
+
     fn execute(
         self: Arc<Self>,
-    ) -> Box<dyn std::future::Future<Output = anyhow::Result<()>> + Send + 'static> {
+        _cancel: CancellationToken,
+    ) -> Box<dyn std::future::Future<Output = anyhow::Result<u64>> + Send + 'static> {
         Box::new(async move {
             let snap_shot = SCHEDULE_TASK_MANAGER.inspect(Some(ScheduleTaskType::All));
             let message = format!("当前调度器内部状态：{:#?}", snap_shot);
             ftlog::info!(target: "scheduler::info", "{}", message);
-            Ok(())
+            Ok(0)
         })
     }
 }
@@ -84,15 +122,24 @@ impl Schedulable for SchedWaitZombie {
             desc: "定时清除僵尸任务，每分钟的第5秒执行一次，其余的调度任务最好都设置为每xx的第0秒执行一次，方便此定时任务及时进行清理".to_owned(),
             cron_expr: "5 * * * * * *".to_string(),
             tag: Some(ScheduleTaskType::System),
+            overlap: OverlapPolicy::Skip,
+            timeout: None,
+            trading_days_only: false,
+            misfire: MisfirePolicy::Ignore,
         }
     }
 
+    fn instance_local(&self) -> bool {
+        true
+    }
+
     fn execute(
         self: Arc<Self>,
-    ) -> Box<dyn std::future::Future<Output = anyhow::Result<()>> + Send + 'static> {
+        _cancel: CancellationToken,
+    ) -> Box<dyn std::future::Future<Output = anyhow::Result<u64>> + Send + 'static> {
         Box::new(async move {
             SCHEDULE_TASK_MANAGER.wait_tasks();
-            Ok(())
+            Ok(0)
         })
     }
 }
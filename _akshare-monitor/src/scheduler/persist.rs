@@ -0,0 +1,265 @@
+//! 调度任务持久化层，将调度任务的key、元信息以及启用状态保存在clickhouse之中，
+//! 在monitor重启时通过任务名称恢复对应任务的uuid，保证脚本之中使用的uuid在多次部署之间保持稳定，
+//! 同时记录每一个调度任务的执行历史
+use std::{collections::HashMap, sync::Mutex};
+
+use chrono::{DateTime, FixedOffset, Utc};
+use clickhouse::Row;
+use serde::{Deserialize, Serialize};
+use serde_repr::{Deserialize_repr, Serialize_repr};
+
+use super::{CST, TaskMeta};
+
+const SCHEDULE_TASK_TABLE: &str = "schedule_task";
+const TASK_RUN_HIST_TABLE: &str = "task_run_hist";
+
+/// 调度任务持久化记录，对应clickhouse之中的`schedule_task`表
+#[derive(Debug, Clone, Row, Serialize, Deserialize)]
+pub struct ScheduleTaskRecord {
+    /// 调度任务uuid
+    pub key: String,
+    /// 调度任务名称，同时作为恢复任务时的标识
+    pub name: String,
+    /// 调度任务描述
+    pub description: String,
+    /// cron表达式
+    pub cron_expr: String,
+    /// 调度任务类型
+    pub tag: String,
+    /// 是否启用
+    pub enabled: bool,
+    /// 记录更新时间
+    #[serde(with = "clickhouse::serde::chrono::datetime64::millis")]
+    pub ts: DateTime<Utc>,
+}
+
+impl ScheduleTaskRecord {
+    fn from_with_meta(key: &str, task_meta: &TaskMeta, enabled: bool) -> Self {
+        Self {
+            key: key.to_owned(),
+            name: task_meta.name.clone(),
+            description: task_meta.desc.clone(),
+            cron_expr: task_meta.cron_expr.clone(),
+            tag: task_meta
+                .tag
+                .as_ref()
+                .map(|tag| tag.to_string())
+                .unwrap_or("None".to_owned()),
+            enabled,
+            ts: Utc::now(),
+        }
+    }
+}
+
+/// 调度任务执行的触发来源
+#[derive(Debug, Clone, Copy, PartialEq, Serialize_repr, Deserialize_repr)]
+#[repr(u8)]
+pub enum TriggerSource {
+    /// 由cron表达式定时触发
+    Cron,
+    /// 通过api手动触发
+    Manual,
+    /// monitor停止期间错过的调度，在任务加入调度器时补跑
+    Misfire,
+}
+
+/// 调度任务单次执行的结果
+#[derive(Debug, Clone, Copy, PartialEq, Serialize_repr, Deserialize_repr)]
+#[repr(u8)]
+pub enum RunStatus {
+    Success,
+    Failed,
+    /// 执行超时被中止
+    TimedOut,
+    /// 任务被取消导致执行被中止
+    Cancelled,
+}
+
+/// 调度任务执行历史，对应clickhouse之中的`task_run_hist`表
+#[derive(Debug, Clone, Row, Serialize, Deserialize)]
+pub struct TaskRunHist {
+    /// 调度任务uuid
+    pub task_key: String,
+    /// 调度任务名称
+    pub task_name: String,
+    /// 本次执行的uuid
+    pub run_id: String,
+    /// 触发来源
+    pub trigger: TriggerSource,
+    /// 开始执行时间
+    #[serde(with = "clickhouse::serde::chrono::datetime64::millis")]
+    pub start_time: DateTime<Utc>,
+    /// 结束执行时间
+    #[serde(with = "clickhouse::serde::chrono::datetime64::millis")]
+    pub end_time: DateTime<Utc>,
+    /// 执行结果
+    pub status: RunStatus,
+    /// 执行失败时的错误链，成功时为空字符串
+    pub error: String,
+    /// 本次执行写入的数据行数
+    pub rows_written: u64,
+}
+
+/// 调度任务执行历史展示信息
+#[derive(Debug, Serialize)]
+pub struct TaskRunShow {
+    pub run_id: String,
+    pub task_name: String,
+    pub trigger: &'static str,
+    pub start_time: DateTime<FixedOffset>,
+    pub end_time: DateTime<FixedOffset>,
+    /// 执行耗时，单位毫秒
+    pub duration_ms: i64,
+    pub status: &'static str,
+    pub error: String,
+    pub rows_written: u64,
+}
+
+impl From<TaskRunHist> for TaskRunShow {
+    fn from(hist: TaskRunHist) -> Self {
+        Self {
+            run_id: hist.run_id,
+            task_name: hist.task_name,
+            trigger: match hist.trigger {
+                TriggerSource::Cron => "Cron",
+                TriggerSource::Manual => "Manual",
+                TriggerSource::Misfire => "Misfire",
+            },
+            start_time: hist.start_time.with_timezone(&CST),
+            end_time: hist.end_time.with_timezone(&CST),
+            duration_ms: (hist.end_time - hist.start_time).num_milliseconds(),
+            status: match hist.status {
+                RunStatus::Success => "Success",
+                RunStatus::Failed => "Failed",
+                RunStatus::TimedOut => "TimedOut",
+                RunStatus::Cancelled => "Cancelled",
+            },
+            error: hist.error,
+            rows_written: hist.rows_written,
+        }
+    }
+}
+
+/// 调度任务存储，启动时一次性加载所有记录到内存之中，之后的每次修改都会同时写回clickhouse
+pub struct TaskStore {
+    ch_client: clickhouse::Client,
+    /// HashMap<任务名称, 持久化记录>
+    records: Mutex<HashMap<String, ScheduleTaskRecord>>,
+}
+
+impl TaskStore {
+    /// 从clickhouse之中加载所有已经持久化的调度任务
+    pub async fn load(ch_client: clickhouse::Client) -> anyhow::Result<Self> {
+        let records: Vec<ScheduleTaskRecord> = ch_client
+            .query(
+                "SELECT key, name, description, cron_expr, tag, enabled, ts \
+                FROM schedule_task FINAL",
+            )
+            .fetch_all()
+            .await?;
+
+        let records = records
+            .into_iter()
+            .map(|record| (record.name.clone(), record))
+            .collect();
+
+        Ok(Self {
+            ch_client,
+            records: Mutex::new(records),
+        })
+    }
+
+    /// 通过任务名称获取之前持久化的记录
+    pub fn restore(&self, name: &str) -> Option<ScheduleTaskRecord> {
+        self.records.lock().unwrap().get(name).cloned()
+    }
+
+    /// 通过任务uuid获取之前持久化的记录
+    pub fn restore_by_key(&self, key: &str) -> Option<ScheduleTaskRecord> {
+        self.records
+            .lock()
+            .unwrap()
+            .values()
+            .find(|record| record.key == key)
+            .cloned()
+    }
+
+    /// 保存(覆盖)一个调度任务的记录
+    pub async fn save(&self, key: &str, task_meta: &TaskMeta, enabled: bool) -> anyhow::Result<()> {
+        let record = ScheduleTaskRecord::from_with_meta(key, task_meta, enabled);
+
+        let mut inserter = self.ch_client.inserter(SCHEDULE_TASK_TABLE)?;
+        inserter.write(&record)?;
+        inserter.end().await?;
+
+        self.records
+            .lock()
+            .unwrap()
+            .insert(record.name.clone(), record);
+        Ok(())
+    }
+
+    /// 修改一个已经持久化的调度任务的启用状态
+    pub async fn set_enabled(&self, key: &str, enabled: bool) -> anyhow::Result<()> {
+        let Some(mut record) = self.restore_by_key(key) else {
+            return Ok(());
+        };
+        record.enabled = enabled;
+        record.ts = Utc::now();
+
+        let mut inserter = self.ch_client.inserter(SCHEDULE_TASK_TABLE)?;
+        inserter.write(&record)?;
+        inserter.end().await?;
+
+        self.records
+            .lock()
+            .unwrap()
+            .insert(record.name.clone(), record);
+        Ok(())
+    }
+
+    /// 记录一次调度任务的执行历史
+    pub async fn record_run(&self, hist: &TaskRunHist) -> anyhow::Result<()> {
+        let mut inserter = self.ch_client.inserter(TASK_RUN_HIST_TABLE)?;
+        inserter.write(hist)?;
+        inserter.end().await?;
+        Ok(())
+    }
+
+    /// 按照开始时间倒序获取一个调度任务最近的执行历史
+    pub async fn runs(&self, task_key: &str, limit: u64) -> anyhow::Result<Vec<TaskRunHist>> {
+        let hists = self
+            .ch_client
+            .query(
+                "SELECT task_key, task_name, run_id, trigger, start_time, end_time, \
+                status, error, rows_written \
+                FROM task_run_hist \
+                WHERE task_key = ? \
+                ORDER BY start_time DESC \
+                LIMIT ?",
+            )
+            .bind(task_key)
+            .bind(limit)
+            .fetch_all()
+            .await?;
+        Ok(hists)
+    }
+
+    /// 获取一个调度任务最近一次执行成功的开始时间，从来没有执行成功过时返回`None`
+    pub async fn last_success(&self, task_key: &str) -> anyhow::Result<Option<DateTime<Utc>>> {
+        let hists: Vec<TaskRunHist> = self
+            .ch_client
+            .query(
+                "SELECT task_key, task_name, run_id, trigger, start_time, end_time, \
+                status, error, rows_written \
+                FROM task_run_hist \
+                WHERE task_key = ? AND status = 'Success' \
+                ORDER BY start_time DESC \
+                LIMIT 1",
+            )
+            .bind(task_key)
+            .fetch_all()
+            .await?;
+        Ok(hists.first().map(|hist| hist.start_time))
+    }
+}
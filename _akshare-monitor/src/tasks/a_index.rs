@@ -1,212 +1,301 @@
-use chrono::Utc;
+use std::time::Duration;
+
+use chrono::{DateTime, NaiveDate, Utc};
 use data_mind::{
     repository::akshare::{
-        self, IndexOption50EtfQvixInsert, IndexStockInfoInsert, StockZhIndexDailyInsert,
-    },
-    schema::{
-        self,
-        akshare::{AkIndexOption50EtfQvix, AkStockZhIndexDaily, AkStockZhIndexSpotSina},
+        IndexOption50EtfQvixInsert, IndexStockConsWeightInsert, IndexStockInfoInsert,
+        StockZhIndexDailyInsert,
     },
-    utils::{config_backoff, with_base_url},
+    schema::akshare::{AkIndexOption50EtfQvix, AkIndexStockConsWeightCsindex, AkStockZhIndexDaily},
 };
-use futures::{StreamExt, TryStreamExt, stream};
+use serde::{Deserialize, Serialize};
 
-use crate::{init::ExternalResource, scheduler::SCHEDULE_TASK_MANAGER};
+use super::{
+    collector::{self, CollectContext, CollectSettings, Collector, Sink},
+    utils::get_index_codes,
+};
+use crate::{
+    init::ExternalResource,
+    scheduler::{CST, MisfirePolicy, OverlapPolicy, ScheduleTaskType, TaskMeta},
+};
 
-pub async fn start_a_index_tasks(ext_res: ExternalResource) {
-    let stock_zh_index_daily_monitor = StockZhIndexDailyMonitor {
-        codes_url: with_base_url("/stock_zh_index_spot_sina"),
-        data_url: with_base_url("/stock_zh_index_daily"),
-        data_table: "stock_zh_index_daily".to_string(),
-        ext_res: ext_res.clone(),
-    };
-    SCHEDULE_TASK_MANAGER
-        .add_task(stock_zh_index_daily_monitor)
-        .await;
-
-    let index_option_50etf_qvix = IndexOption50EtfQvixMonitor {
-        data_url: with_base_url("/index_option_50etf_qvix"),
-        data_table: "index_option_50etf_qvix".to_owned(),
-        ext_res: ext_res.clone(),
-    };
-    SCHEDULE_TASK_MANAGER
-        .add_task(index_option_50etf_qvix)
-        .await;
-
-    let index_stock_info_monitor = IndexStockInfoMonitor {
-        data_url: with_base_url("/index_stock_info"),
-        data_table: "index_stock_info".to_owned(),
-        ext_res: ext_res.clone(),
-    };
-    SCHEDULE_TASK_MANAGER
-        .add_task(index_stock_info_monitor)
-        .await;
+pub async fn start_a_index_tasks(ext_res: ExternalResource) -> anyhow::Result<()> {
+    collector::register(StockZhIndexDailyMonitor, &ext_res).await?;
+    collector::register(IndexOption50EtfQvixMonitor, &ext_res).await?;
+    collector::register(IndexStockInfoMonitor, &ext_res).await?;
+    collector::register(IndexStockConsWeightMonitor, &ext_res).await?;
+    Ok(())
 }
 
-/// 收集历史行情数据-新浪
-pub struct StockZhIndexDailyMonitor {
-    /// 获取指数数据代码的url
-    codes_url: String,
-    /// 获取历史数据的url
-    data_url: String,
-    /// 对接的数据库表名
-    data_table: String,
-    /// 相关外部资源（数据库连接池等等）
-    ext_res: ExternalResource,
+/// 收集指数历史行情数据-东方财富
+#[derive(Clone)]
+pub(super) struct StockZhIndexDailyMonitor;
+
+/// 单次请求的参数，日期为 yyyymmdd 格式的时间字符串
+#[derive(Serialize, Deserialize)]
+pub(super) struct StockZhIndexDailyParam {
+    /// 指数代码，例如`sz399982`
+    pub(super) code: String,
+    pub(super) start_date: String,
+    pub(super) end_date: String,
 }
 
-impl StockZhIndexDailyMonitor {
-    /// 获取所有指数数据代码
-    async fn get_codes(&self) -> anyhow::Result<Vec<String>> {
-        let values: Vec<AkStockZhIndexSpotSina> = self
-            .ext_res
-            .http_client
-            .get(&self.codes_url)
-            .send()
-            .await?
-            .error_for_status()?
-            .json()
-            .await?;
-
-        Ok(values.into_iter().map(|value| value.code).collect())
-    }
-
-    /// 获取单个code对应的指数的日频历史行情数据
-    async fn get_daily_hist(&self, code: String) -> anyhow::Result<Vec<StockZhIndexDailyInsert>> {
-        let backoff_s = config_backoff(5, 30);
-        let api_data = backoff::future::retry(backoff_s, || async {
-            let api_data: Vec<AkStockZhIndexDaily> = self
-                .ext_res
-                .http_client
-                .get(&self.data_url)
-                .query(&[("symbol", code.as_str())])
-                .send()
-                .await?
-                .error_for_status()?
-                .json()
-                .await
-                .map_err(backoff::Error::Permanent)?;
-            Ok(api_data)
-        })
-        .await?;
-
-        let now = Utc::now();
-
-        Ok(api_data
+/// 还没有任何数据的指数从这一天开始收集全部历史数据
+const INDEX_HIST_START: &str = "19900101";
+
+impl Collector for StockZhIndexDailyMonitor {
+    type Param = StockZhIndexDailyParam;
+    type Api = AkStockZhIndexDaily;
+    type Row = StockZhIndexDailyInsert;
+
+    fn meta(&self) -> TaskMeta {
+        TaskMeta {
+            name: "stock_zh_index_daily".to_owned(),
+            desc: "指数历史行情数据-东方财富, 股票指数的历史数据按日频率更新".to_owned(),
+            cron_expr: "0 50 18 * * MON-FRI".to_owned(),
+            tag: Some(ScheduleTaskType::Index),
+            overlap: OverlapPolicy::Skip,
+            timeout: Some(Duration::from_secs(30 * 60)),
+            trading_days_only: true,
+            misfire: MisfirePolicy::RunOnce,
+        }
+    }
+
+    fn path(&self) -> &'static str {
+        "/stock_zh_index_daily_em"
+    }
+
+    fn table(&self) -> &'static str {
+        "stock_zh_index_daily"
+    }
+
+    /// 所有指数代码，已经有数据的指数只请求最新日期(往前重叠若干天)之后的数据，
+    /// 还没有任何数据的指数请求全部历史数据
+    async fn params(
+        &self,
+        ctx: &CollectContext<'_>,
+    ) -> anyhow::Result<Vec<StockZhIndexDailyParam>> {
+        let codes = get_index_codes(&ctx.ext_res.ch_client).await?;
+        let latest_dates = ctx.latest_dates(None).await?;
+        let today = Utc::now().with_timezone(&CST).date_naive();
+        let end_date = today.format("%Y%m%d").to_string();
+
+        Ok(codes
             .into_iter()
-            .map(|data| StockZhIndexDailyInsert::from_with_ts(data, &code, now))
+            .map(|code| StockZhIndexDailyParam {
+                start_date: latest_dates
+                    .get(&code)
+                    .map(|latest| {
+                        ctx.incremental_start(*latest)
+                            .min(today)
+                            .format("%Y%m%d")
+                            .to_string()
+                    })
+                    .unwrap_or_else(|| INDEX_HIST_START.to_owned()),
+                end_date: end_date.clone(),
+                code,
+            })
             .collect())
     }
 
-    /// 通过股票代码获取所有指数历史数据
-    async fn get_daily_hists(
+    fn query(&self, param: &StockZhIndexDailyParam) -> Vec<(&'static str, String)> {
+        vec![
+            ("symbol", param.code.clone()),
+            ("start_date", param.start_date.clone()),
+            ("end_date", param.end_date.clone()),
+        ]
+    }
+
+    /// 日期无法解析的数据会被丢弃
+    fn transform(
         &self,
-        codes: Vec<String>,
-    ) -> anyhow::Result<Vec<StockZhIndexDailyInsert>> {
-        let res: Vec<Vec<StockZhIndexDailyInsert>> = stream::iter(codes)
-            .map(|code| self.get_daily_hist(code))
-            .buffer_unordered(50)
-            .try_collect()
-            .await?;
+        param: &StockZhIndexDailyParam,
+        value: AkStockZhIndexDaily,
+        ts: DateTime<Utc>,
+    ) -> Option<StockZhIndexDailyInsert> {
+        StockZhIndexDailyInsert::from_with_ts(value, &param.code, ts)
+    }
 
-        Ok(res.into_iter().flatten().collect())
+    fn settings(&self) -> CollectSettings {
+        CollectSettings {
+            concurrency: 50,
+            ..Default::default()
+        }
     }
+}
 
-    pub async fn collect_data(&self) -> anyhow::Result<()> {
-        let codes = self.get_codes().await?;
-        let daily_hists = self.get_daily_hists(codes).await?;
+// ------------------------------------------------------------------------
 
-        let mut inserter = self.ext_res.ch_client.inserter(&self.data_table)?;
-        for row in daily_hists {
-            inserter.write(&row)?;
+/// 50ETF 期权波动率指数 QVIX; 又称中国版的恐慌指数
+#[derive(Clone)]
+pub struct IndexOption50EtfQvixMonitor;
+
+impl Collector for IndexOption50EtfQvixMonitor {
+    type Param = ();
+    type Api = AkIndexOption50EtfQvix;
+    type Row = IndexOption50EtfQvixInsert;
+
+    fn meta(&self) -> TaskMeta {
+        TaskMeta {
+            name: "index_option_50etf_qvix".to_owned(),
+            desc: "50ETF 期权波动率指数 QVIX; 又称中国版的恐慌指数".to_owned(),
+            cron_expr: "0 30 15 * * MON-FRI".to_owned(),
+            tag: Some(ScheduleTaskType::Index),
+            overlap: OverlapPolicy::Skip,
+            timeout: Some(Duration::from_secs(5 * 60)),
+            trading_days_only: true,
+            misfire: MisfirePolicy::RunOnce,
         }
-        inserter.end().await?;
+    }
+
+    fn path(&self) -> &'static str {
+        "/index_option_50etf_qvix"
+    }
 
-        Ok(())
+    fn table(&self) -> &'static str {
+        "index_option_50etf_qvix"
+    }
+
+    async fn params(&self, _ctx: &CollectContext<'_>) -> anyhow::Result<Vec<()>> {
+        Ok(collector::single())
+    }
+
+    /// 接口返回的数据之中存在缺失值，这些数据会被丢弃
+    fn transform(
+        &self,
+        _param: &(),
+        value: AkIndexOption50EtfQvix,
+        ts: DateTime<Utc>,
+    ) -> Option<IndexOption50EtfQvixInsert> {
+        IndexOption50EtfQvixInsert::from_with_ts(value, ts)
     }
 }
 
 // ------------------------------------------------------------------------
 
-pub struct IndexOption50EtfQvixMonitor {
-    data_url: String,
-    data_table: String,
-    ext_res: ExternalResource,
-}
+/// 股票指数信息一览表，接口每次返回全量数据，每次收集替换整个表格
+#[derive(Clone)]
+pub struct IndexStockInfoMonitor;
+
+impl Collector for IndexStockInfoMonitor {
+    type Param = ();
+    type Api = IndexStockInfoInsert;
+    type Row = IndexStockInfoInsert;
+
+    fn meta(&self) -> TaskMeta {
+        TaskMeta {
+            name: "index_stock_info".to_owned(),
+            desc: "收集股票指数信息一览表".to_owned(),
+            cron_expr: "0 0 12 * * SAT".to_owned(),
+            tag: Some(ScheduleTaskType::Index),
+            overlap: OverlapPolicy::Skip,
+            timeout: Some(Duration::from_secs(5 * 60)),
+            trading_days_only: false,
+            misfire: MisfirePolicy::RunOnce,
+        }
+    }
 
-impl IndexOption50EtfQvixMonitor {
-    async fn get_data(&self) -> anyhow::Result<Vec<Option<IndexOption50EtfQvixInsert>>> {
-        let backoff_s = config_backoff(5, 30);
-        let api_data = backoff::future::retry(backoff_s, || async {
-            let api_data: Vec<AkIndexOption50EtfQvix> = self
-                .ext_res
-                .http_client
-                .get(&self.data_url)
-                .send()
-                .await?
-                .error_for_status()?
-                .json()
-                .await
-                .map_err(backoff::Error::Permanent)?;
-            Ok(api_data)
-        })
-        .await?;
-
-        let now = Utc::now();
-        let repo_data = api_data
-            .into_iter()
-            .map(|data| IndexOption50EtfQvixInsert::from_with_ts(data, now))
-            .collect::<Vec<_>>();
+    fn path(&self) -> &'static str {
+        "/index_stock_info"
+    }
 
-        Ok(repo_data)
+    fn table(&self) -> &'static str {
+        "index_stock_info"
     }
 
-    pub async fn collect_data(&self) -> anyhow::Result<()> {
-        let rows_with_none = self.get_data().await?;
-        let rows = rows_with_none
-            .into_iter()
-            .filter_map(|row| row)
-            .collect::<Vec<_>>();
+    async fn params(&self, _ctx: &CollectContext<'_>) -> anyhow::Result<Vec<()>> {
+        Ok(collector::single())
+    }
 
-        let mut inserter = self.ext_res.ch_client.inserter(&self.data_table)?;
-        for row in rows {
-            inserter.write(&row)?;
-        }
-        inserter.end().await?;
+    fn transform(
+        &self,
+        _param: &(),
+        value: IndexStockInfoInsert,
+        _ts: DateTime<Utc>,
+    ) -> Option<IndexStockInfoInsert> {
+        Some(value)
+    }
 
-        Ok(())
+    fn sink(&self) -> Sink {
+        Sink::Replace
     }
 }
 
-pub struct IndexStockInfoMonitor {
-    data_url: String,
-    data_table: String,
-    ext_res: ExternalResource,
-}
+// ------------------------------------------------------------------------
+
+/// 收集成份股以及权重的中证指数代码，中证指数官网只提供由中证指数公司发布的指数的权重文件
+const CSINDEX_CODES: [&str; 8] = [
+    "000016", // 上证50
+    "000300", // 沪深300
+    "000688", // 科创50
+    "000903", // 中证100
+    "000905", // 中证500
+    "000906", // 中证800
+    "000852", // 中证1000
+    "932000", // 中证2000
+];
+
+/// 中证指数-指数成份股以及权重，权重文件每个月更新一次，
+/// 每一个权重生效日期保存一份完整的成份股列表，用于查询某一天的成份股以及成份股的调入调出
+#[derive(Clone)]
+pub struct IndexStockConsWeightMonitor;
+
+impl Collector for IndexStockConsWeightMonitor {
+    type Param = String;
+    type Api = AkIndexStockConsWeightCsindex;
+    type Row = IndexStockConsWeightInsert;
+
+    fn meta(&self) -> TaskMeta {
+        TaskMeta {
+            name: "index_stock_cons_weight".to_owned(),
+            desc: "中证指数-指数成份股以及权重, 记录成份股的调入以及调出".to_owned(),
+            cron_expr: "0 0 19 * * MON-FRI".to_owned(),
+            tag: Some(ScheduleTaskType::Index),
+            overlap: OverlapPolicy::Skip,
+            timeout: Some(Duration::from_secs(10 * 60)),
+            trading_days_only: true,
+            misfire: MisfirePolicy::RunOnce,
+        }
+    }
 
-impl IndexStockInfoMonitor {
-    pub async fn collect_data(&self) -> anyhow::Result<()> {
-        let rows = IndexStockInfoInsert::from_astock_api(&self.ext_res.http_client).await?;
+    fn path(&self) -> &'static str {
+        "/index_stock_cons_weight_csindex"
+    }
 
-        // 首先删除当前表格之中的所有数据
-        let sql = format!("TRUNCATE TABLE {}", self.data_table);
-        self.ext_res.ch_client.query(&sql).execute().await?;
+    fn table(&self) -> &'static str {
+        "index_stock_cons_weight"
+    }
 
-        let mut inserter = self.ext_res.ch_client.inserter(&self.data_table)?;
-        for row in rows {
-            inserter.write(&row)?;
-        }
-        inserter.end().await?;
-        Ok(())
+    async fn params(&self, _ctx: &CollectContext<'_>) -> anyhow::Result<Vec<String>> {
+        Ok(CSINDEX_CODES
+            .iter()
+            .map(|code| (*code).to_owned())
+            .collect())
+    }
+
+    fn query(&self, param: &String) -> Vec<(&'static str, String)> {
+        vec![("symbol", param.clone())]
+    }
+
+    /// 权重生效日期无法解析的数据会被丢弃
+    fn transform(
+        &self,
+        _param: &String,
+        value: AkIndexStockConsWeightCsindex,
+        ts: DateTime<Utc>,
+    ) -> Option<IndexStockConsWeightInsert> {
+        IndexStockConsWeightInsert::from_with_ts(value, ts)
     }
 }
 
 #[cfg(test)]
 mod test {
+    use tokio_util::sync::CancellationToken;
+
     use super::*;
     use crate::{
         init::ExternalResource,
-        tasks::{TEST_CH_CLIENT, TEST_HTTP_CLIENT},
+        tasks::{TEST_CH_CLIENT, TEST_HTTP_CLIENT, collector::CollectorTask},
     };
 
     #[tokio::test]
@@ -216,14 +305,12 @@ mod test {
             http_client: TEST_HTTP_CLIENT.clone(),
         };
 
-        let stock_zh_index_daily_monitor = StockZhIndexDailyMonitor {
-            codes_url: with_base_url("/stock_zh_index_spot_sina"),
-            data_url: with_base_url("/stock_zh_index_daily"),
-            data_table: "stock_zh_index_daily".to_string(),
-            ext_res,
-        };
+        let stock_zh_index_daily_monitor = CollectorTask::new(StockZhIndexDailyMonitor, ext_res);
 
-        stock_zh_index_daily_monitor.collect_data().await.unwrap()
+        stock_zh_index_daily_monitor
+            .collect_data(&CancellationToken::new())
+            .await
+            .unwrap();
     }
 
     #[tokio::test]
@@ -233,12 +320,26 @@ mod test {
             http_client: TEST_HTTP_CLIENT.clone(),
         };
 
-        let index_option_50etf_qvix = IndexOption50EtfQvixMonitor {
-            data_url: with_base_url("/index_option_50etf_qvix"),
-            data_table: "index_option_50etf_qvix".to_owned(),
-            ext_res,
+        let index_option_50etf_qvix = CollectorTask::new(IndexOption50EtfQvixMonitor, ext_res);
+
+        index_option_50etf_qvix
+            .collect_data(&CancellationToken::new())
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_index_stock_cons_weight_monitor() {
+        let ext_res = ExternalResource {
+            ch_client: TEST_CH_CLIENT.clone(),
+            http_client: TEST_HTTP_CLIENT.clone(),
         };
 
-        index_option_50etf_qvix.collect_data().await.unwrap()
+        let index_stock_cons_weight = CollectorTask::new(IndexStockConsWeightMonitor, ext_res);
+
+        index_stock_cons_weight
+            .collect_data(&CancellationToken::new())
+            .await
+            .unwrap();
     }
 }
@@ -1,132 +1,122 @@
-use std::{pin::Pin, sync::Arc};
+use std::{collections::HashMap, time::Duration};
 
-use chrono::{Duration, Local, NaiveDate, Utc};
-use futures::{
-    FutureExt, StreamExt, TryStreamExt,
-    stream::{self, FuturesUnordered},
-};
-use strum::IntoEnumIterator;
+use chrono::{DateTime, Local, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
 
 use crate::{
     init::ExternalResource,
-    scheduler::{CST, SCHEDULE_TASK_MANAGER, Schedulable, ScheduleTaskType, TaskMeta},
+    scheduler::{CST, MisfirePolicy, OverlapPolicy, ScheduleTaskType, TaskMeta},
     tasks::utils::get_distinct_code,
 };
 use data_mind::{
     repository::{
-        self,
         akshare::{
-            self, FlowDirection, StockAdjustmentType, StockHsgtHistEmInsert, StockNewsMainCxInsert,
-            StockZhAHistInsert, StockZtPoolEmInsert,
+            self, FinancialPeriodType, FlowDirection, SecurityKind, StockAdjustFactorInsert,
+            StockAdjustmentType, StockFinancialAbstractThsInsert, StockHsgtHistEmInsert,
+            StockNewsMainCxInsert, StockRankLxszThsInsert, StockZhAHistInsert, StockZtPoolEmInsert,
         },
+        service::listed_security_codes,
     },
     schema::{
         self,
         akshare::{
-            AkStockHsgtHistEm, AkStockNewsMainCx, AkStockRankLxszThs, AkStockZhAHist,
-            AkStockZtPoolEm,
+            AkStockFinancialAbstractThs, AkStockHsgtHistEm, AkStockNewsMainCx,
+            AkStockZhADailyHfqFactor, AkStockZhAHist, AkStockZtPoolEm,
         },
     },
-    utils::{config_backoff, with_base_url},
 };
 
-use super::{TRADE_TIME_CRON, in_trade_time};
-
-/// 模块顶级方法，用于暴露给父模块调用将相关调度任务加入到全局调度器之中
-pub(super) async fn start_a_stock_tasks(ext_res: ExternalResource) {
-    // let realtime_stock_monitor = RealTimeStockMonitor {
-    //     data_url: with_base_url("/stock_zh_a_spot_em"),
-    //     data_table: "astock_realtime_data".to_owned(),
-    //     ext_res: ext_res.clone(),
-    // };
-    // SCHEDULE_TASK_MANAGER.add_task(realtime_stock_monitor).await;
-
-    // let stock_zh_a_hist_monitor = StockZhAHistMonitor {
-    //     data_url: with_base_url("/stock_zh_a_hist"),
-    //     data_table: "stock_zh_a_hist".to_owned(),
-    //     ext_res: ext_res.clone(),
-    // };
-    // SCHEDULE_TASK_MANAGER
-    //     .add_task(stock_zh_a_hist_monitor)
-    //     .await;
-
-    let stock_hsgt_hist_em_monitor = StockHsgtHistEmMonitor {
-        data_url: with_base_url("/stock_hsgt_hist_em"),
-        data_table: "stock_hsgt_hist_em".to_owned(),
-        ext_res: ext_res.clone(),
-    };
-    SCHEDULE_TASK_MANAGER
-        .add_task(stock_hsgt_hist_em_monitor)
-        .await;
-
-    let stock_zt_pool_em_monitor = StockZtPoolEmMonitor {
-        data_url: with_base_url("/stock_zt_pool_em"),
-        data_table: "stock_zt_pool_em".to_owned(),
-        ext_res: ext_res.clone(),
-    };
-    SCHEDULE_TASK_MANAGER
-        .add_task(stock_zt_pool_em_monitor)
-        .await;
+use super::{
+    TRADE_TIME_CRON,
+    collector::{self, CollectContext, CollectSettings, Collector, Sink},
+    in_trade_time,
+};
 
-    let stock_news_main_cx_monitor = StockNewsMainCxMonitor {
-        data_url: with_base_url("/stock_news_main_cx"),
-        data_table: "stock_news_main_cx".to_owned(),
-        ext_res: ext_res.clone(),
-    };
-    SCHEDULE_TASK_MANAGER
-        .add_task(stock_news_main_cx_monitor)
-        .await;
+/// 模块顶级方法，用于暴露给父模块调用将相关调度任务加入到全局调度器之中，
+/// 每个任务是否启用以及相关设置由配置文件之中的`[[monitors]]`决定
+pub(super) async fn start_a_stock_tasks(ext_res: ExternalResource) -> anyhow::Result<()> {
+    drop_adjusted_hist(&ext_res.ch_client).await;
+    collector::register(RealTimeStockMonitor, &ext_res).await?;
+    collector::register(StockZhAHistMonitor, &ext_res).await?;
+    collector::register(StockAdjustFactorMonitor, &ext_res).await?;
+    collector::register(StockHsgtHistEmMonitor, &ext_res).await?;
+    collector::register(StockZtPoolEmMonitor, &ext_res).await?;
+    collector::register(StockNewsMainCxMonitor, &ext_res).await?;
+    collector::register(StockRankLxszThsMonitor, &ext_res).await?;
+    collector::register(StockFinancialAbstractThsMonitor, &ext_res).await?;
+    Ok(())
+}
 
-    // StockRankLxszThsMonitor
-    let stock_rank_lxsz_ths_monitor = StockRankLxszThsMonitor {
-        data_table: "stock_rank_lxsz_ths".to_owned(),
-        ext_res: ext_res.clone(),
-    };
-    SCHEDULE_TASK_MANAGER
-        .add_task(stock_rank_lxsz_ths_monitor)
+/// `stock_zh_a_hist`只保存不复权数据，启动时删除之前收集的前复权以及后复权数据，
+/// 已经清理过时只执行一次计数查询，失败时只记录日志
+async fn drop_adjusted_hist(ch_client: &clickhouse::Client) {
+    let count = ch_client
+        .query("SELECT count() FROM stock_zh_a_hist WHERE adj_type != 'None'")
+        .fetch_one::<u64>()
         .await;
+    match count {
+        Ok(0) => {}
+        Ok(count) => {
+            ftlog::info!("[stock_zh_a_hist] dropping {} adjusted rows", count);
+            let _ = ch_client
+                .query("ALTER TABLE stock_zh_a_hist DELETE WHERE adj_type != 'None'")
+                .execute()
+                .await
+                .inspect_err(|err| {
+                    ftlog::error!("[stock_zh_a_hist] drop adjusted rows error = {:?}", err)
+                });
+        }
+        Err(err) => ftlog::error!("[stock_zh_a_hist] count adjusted rows error = {:?}", err),
+    }
 }
 
 /// 收集东方财富网-沪深京 A 股-实时行情数据
-pub(super) struct RealTimeStockMonitor {
-    data_url: String,
-    data_table: String,
-    ext_res: ExternalResource,
-}
+#[derive(Clone)]
+pub(super) struct RealTimeStockMonitor;
+
+impl Collector for RealTimeStockMonitor {
+    type Param = ();
+    type Api = schema::akshare::RealtimeStockMarketRecord;
+    type Row = akshare::RealtimeStockMarketRecord;
+
+    fn meta(&self) -> TaskMeta {
+        TaskMeta {
+            name: "stock_zh_a_spot_em".to_owned(),
+            desc: "东方财富网-沪深京 A 股-实时行情数据".to_owned(),
+            cron_expr: TRADE_TIME_CRON.to_owned(),
+            tag: Some(ScheduleTaskType::AStock),
+            overlap: OverlapPolicy::Skip,
+            timeout: Some(Duration::from_secs(30)),
+            trading_days_only: true,
+            misfire: MisfirePolicy::Ignore,
+        }
+    }
 
-impl RealTimeStockMonitor {
-    pub async fn collect_data(&self, ts: chrono::DateTime<Utc>) -> anyhow::Result<()> {
-        let backoff_s = config_backoff(5, 20);
-
-        let result: Vec<schema::akshare::RealtimeStockMarketRecord> =
-            backoff::future::retry(backoff_s, || async {
-                let api_data: Vec<schema::akshare::RealtimeStockMarketRecord> = self
-                    .ext_res
-                    .http_client
-                    .get(&self.data_url)
-                    .send()
-                    .await?
-                    .error_for_status()?
-                    .json()
-                    .await
-                    .map_err(backoff::Error::Permanent)?;
-
-                Ok(api_data)
-            })
-            .await?;
+    fn path(&self) -> &'static str {
+        "/stock_zh_a_spot_em"
+    }
 
-        let astock_realtime_data_row = result
-            .into_iter()
-            .map(|record| repository::akshare::RealtimeStockMarketRecord::from_with_ts(record, ts))
-            .collect::<Vec<_>>();
+    fn table(&self) -> &'static str {
+        "astock_realtime_data"
+    }
 
-        let mut inserter = self.ext_res.ch_client.inserter(&self.data_table)?;
-        for row in astock_realtime_data_row {
-            inserter.write(&row)?;
+    async fn params(&self, _ctx: &CollectContext<'_>) -> anyhow::Result<Vec<()>> {
+        Ok(collector::single())
+    }
+
+    fn transform(&self, _param: &(), value: Self::Api, ts: DateTime<Utc>) -> Option<Self::Row> {
+        Some(akshare::RealtimeStockMarketRecord::from_with_ts(value, ts))
+    }
+
+    fn settings(&self) -> CollectSettings {
+        CollectSettings {
+            retry_max_elapsed: 20,
+            ..Default::default()
         }
-        inserter.end().await?;
+    }
 
-        Ok(())
+    fn should_collect(&self, now: DateTime<Utc>) -> bool {
+        in_trade_time(&now)
     }
 }
 
@@ -134,341 +124,483 @@ impl RealTimeStockMonitor {
 
 /// 收集东方财富-沪深京 A 股日频率数据;
 /// 历史数据按日频率更新, 当日收盘价请在收盘后获取
-pub(super) struct StockZhAHistMonitor {
-    data_url: String,
-    data_table: String,
-    ext_res: ExternalResource,
+#[derive(Clone)]
+pub(super) struct StockZhAHistMonitor;
+
+/// 单次请求的参数，日期为 yyyymmdd 格式的时间字符串
+#[derive(Serialize, Deserialize)]
+pub(super) struct StockZhAHistParam {
+    pub(super) code: String,
+    pub(super) adj_type: StockAdjustmentType,
+    pub(super) start_date: String,
+    pub(super) end_date: String,
 }
 
-impl StockZhAHistMonitor {
-    async fn req_data_with_type(
-        &self,
-        code: &str,
-        start_date: &str,
-        end_date: &str,
-        adj_type: StockAdjustmentType,
-    ) -> anyhow::Result<Vec<StockZhAHistInsert>> {
-        let backoff_s = config_backoff(60, 480);
-        let ch_data: Vec<StockZhAHistInsert> = backoff::future::retry(backoff_s, || async {
-            let api_data: Vec<AkStockZhAHist> = self
-                .ext_res
-                .http_client
-                .get(&self.data_url)
-                .query(&[
-                    ("symbol", code),
-                    ("period", "daily"),
-                    ("start_date", start_date),
-                    ("end_date", end_date),
-                    ("adjust", adj_type.to_str()),
-                ])
-                .send()
-                .await?
-                .error_for_status()?
-                .json()
-                .await
-                .map_err(backoff::Error::Permanent)?;
+impl Collector for StockZhAHistMonitor {
+    type Param = StockZhAHistParam;
+    type Api = AkStockZhAHist;
+    type Row = StockZhAHistInsert;
+
+    fn meta(&self) -> TaskMeta {
+        TaskMeta {
+            name: "stock_zh_a_hist".to_owned(),
+            desc:
+                "收集东方财富-沪深京 A 股日频率数据; 历史数据按日频率更新, 当日收盘价请在收盘后获取"
+                    .to_owned(),
+            cron_expr: "0 0 16 * * MON-FRI".to_owned(),
+            tag: Some(ScheduleTaskType::AStock),
+            overlap: OverlapPolicy::Skip,
+            timeout: Some(Duration::from_secs(2 * 60 * 60)),
+            trading_days_only: true,
+            misfire: MisfirePolicy::RunOnce,
+        }
+    }
 
-            let now = Utc::now();
-            let ch_data = api_data
-                .into_iter()
-                .map(|value| StockZhAHistInsert::from_with_type(value, adj_type, now))
-                .collect::<Vec<StockZhAHistInsert>>();
-
-            Ok(ch_data)
-        })
-        .await?;
-
-        // use std::io::Write;
-        // let mut file = std::fs::OpenOptions::new()
-        //     .create(true)
-        //     .append(true)
-        //     .open("../tmp/output.log")?;
-        // let now = Utc::now();
-        // writeln!(
-        //     file,
-        //     "time = {:?}, code = {}, adj_type = {} processed",
-        //     now,
-        //     code,
-        //     adj_type.to_str()
-        // );
-
-        Ok(ch_data)
-    }
-
-    /// 从akshare请求数据，返回clickhouse schema格式的Vec，包括对应code对应日期之中：`不复权 | 前复权 | 后复权`的整体数据
-    /// 注意date_str是一个 yyyymmdd 格式的时间字符串
-    async fn req_data(
-        &self,
-        code: String,
-        start_date: u32,
-        end_date: u32,
-    ) -> anyhow::Result<Vec<StockZhAHistInsert>> {
-        let start_date = start_date.to_string();
-        let end_date = end_date.to_string();
-
-        let hist_data: Vec<Vec<StockZhAHistInsert>> = stream::iter(StockAdjustmentType::iter())
-            .then(|adj_type| {
-                self.req_data_with_type(
-                    code.as_str(),
-                    start_date.as_str(),
-                    end_date.as_str(),
+    fn path(&self) -> &'static str {
+        "/stock_zh_a_hist"
+    }
+
+    fn table(&self) -> &'static str {
+        "stock_zh_a_hist"
+    }
+
+    /// 所有股票的不复权数据，前复权以及后复权价格在查询时由`stock_adjust_factor`之中的复权因子计算，
+    /// 从每只股票已有数据的最新日期(往前重叠若干天)开始增量收集，还没有任何数据的股票收集过去90天的数据
+    async fn params(&self, ctx: &CollectContext<'_>) -> anyhow::Result<Vec<StockZhAHistParam>> {
+        let codes = get_distinct_code(&ctx.ext_res.ch_client).await?;
+        let today = Utc::now().with_timezone(&CST).date_naive();
+        let window_start = today - chrono::Duration::days(90);
+        let end_date = today.format("%Y%m%d").to_string();
+        let adj_type = StockAdjustmentType::None;
+        let filter = format!("adj_type = '{:?}'", adj_type);
+        let latest_dates = ctx.latest_dates(Some(&filter)).await?;
+
+        Ok(codes
+            .into_iter()
+            .map(|code| {
+                let start_date = latest_dates
+                    .get(&code)
+                    .map(|latest| ctx.incremental_start(*latest).min(today))
+                    .unwrap_or(window_start);
+                StockZhAHistParam {
+                    code,
                     adj_type,
-                )
+                    start_date: start_date.format("%Y%m%d").to_string(),
+                    end_date: end_date.clone(),
+                }
             })
-            .try_collect()
-            .await?;
-
-        Ok(hist_data.into_iter().flatten().collect())
+            .collect())
     }
 
-    /// 收集东方财富-沪深京 A 股日频率数据
-    pub async fn collect_data(&self) -> anyhow::Result<()> {
-        let mut codes = get_distinct_code(&self.ext_res.ch_client).await?;
-        // println!("codes length = {}", codes.len());
-        let now_date = Utc::now().with_timezone(&CST);
-        let start_date = now_date - chrono::Duration::days(90);
+    fn query(&self, param: &StockZhAHistParam) -> Vec<(&'static str, String)> {
+        vec![
+            ("symbol", param.code.clone()),
+            ("period", "daily".to_owned()),
+            ("start_date", param.start_date.clone()),
+            ("end_date", param.end_date.clone()),
+            ("adjust", param.adj_type.to_str().to_owned()),
+        ]
+    }
 
-        let end = now_date
-            .format("%Y%m%d")
-            .to_string()
-            .parse::<u32>()
-            .unwrap();
-        let start = start_date
-            .format("%Y%m%d")
-            .to_string()
-            .parse::<u32>()
-            .unwrap();
+    fn transform(
+        &self,
+        param: &StockZhAHistParam,
+        value: AkStockZhAHist,
+        ts: DateTime<Utc>,
+    ) -> Option<StockZhAHistInsert> {
+        Some(StockZhAHistInsert::from_with_type(
+            value,
+            param.adj_type,
+            ts,
+        ))
+    }
 
-        while !codes.is_empty() {
-            // 每次拿出前 512 个
-            let chunk: Vec<String> = if codes.len() > 512 {
-                codes.drain(..512).collect()
-            } else {
-                codes.drain(..).collect()
-            };
-
-            let hist_data: Vec<Vec<StockZhAHistInsert>> = stream::iter(chunk)
-                .map(|code| self.req_data(code, start, end))
-                .buffer_unordered(32)
-                .try_collect()
-                .await?;
-
-            let hist_data = hist_data.into_iter().flatten().collect::<Vec<_>>();
-
-            let mut inserter = self.ext_res.ch_client.inserter(&self.data_table)?;
-            for row in hist_data {
-                inserter.write(&row)?;
-            }
-            inserter.end().await?;
-
-            ftlog::info!(
-                "[stock_zh_a_hist collect] batch complete, remain len = {}",
-                codes.len()
-            );
-            tokio::time::sleep(std::time::Duration::from_secs(30)).await;
+    /// 每次请求512只股票的数据，之后暂停30秒，避免被上游接口限流
+    fn settings(&self) -> CollectSettings {
+        CollectSettings {
+            retry_max_interval: 60,
+            retry_max_elapsed: 480,
+            concurrency: 32,
+            batch_size: Some(512),
+            batch_pause: Duration::from_secs(30),
+            ..Default::default()
         }
-
-        Ok(())
     }
 }
 
 // -----------------------------------------------------------------------------------------
 
-pub struct StockHsgtHistEmMonitor {
-    data_url: String,
-    data_table: String,
-    ext_res: ExternalResource,
+/// 收集新浪财经-A股后复权因子，`stock_zh_a_hist`只保存不复权数据，复权价格在查询时计算
+#[derive(Clone)]
+pub(super) struct StockAdjustFactorMonitor;
+
+/// 单次请求的参数
+#[derive(Serialize, Deserialize)]
+pub(super) struct StockAdjustFactorParam {
+    /// 6位股票代码
+    code: String,
+    /// 新浪接口使用的带交易所前缀的代码，例如`sh600000`
+    symbol: String,
 }
 
-impl StockHsgtHistEmMonitor {
-    async fn get_dir_data(
-        &self,
-        flow_dir: FlowDirection,
-    ) -> anyhow::Result<Vec<StockHsgtHistEmInsert>> {
-        let backoff_s = config_backoff(5, 30);
-        let api_data = backoff::future::retry(backoff_s, || async {
-            let api_data: Vec<AkStockHsgtHistEm> = self
-                .ext_res
-                .http_client
-                .get(&self.data_url)
-                .query(&[("symbol", flow_dir.as_str())])
-                .send()
-                .await?
-                .error_for_status()?
-                .json()
-                .await
-                .map_err(backoff::Error::Permanent)?;
+impl Collector for StockAdjustFactorMonitor {
+    type Param = StockAdjustFactorParam;
+    type Api = AkStockZhADailyHfqFactor;
+    type Row = StockAdjustFactorInsert;
+
+    fn meta(&self) -> TaskMeta {
+        TaskMeta {
+            name: "stock_adjust_factor".to_owned(),
+            desc: "新浪财经-A股后复权因子, 用于在查询时计算前复权以及后复权价格".to_owned(),
+            cron_expr: "0 30 17 * * MON-FRI".to_owned(),
+            tag: Some(ScheduleTaskType::AStock),
+            overlap: OverlapPolicy::Skip,
+            timeout: Some(Duration::from_secs(2 * 60 * 60)),
+            trading_days_only: true,
+            misfire: MisfirePolicy::RunOnce,
+        }
+    }
 
-            Ok(api_data)
-        })
-        .await?;
+    fn path(&self) -> &'static str {
+        "/stock_zh_a_daily"
+    }
 
-        let now = Utc::now();
+    fn table(&self) -> &'static str {
+        "stock_adjust_factor"
+    }
 
-        Ok(api_data
+    /// 所有上市股票，接口每次返回该股票全部的复权因子，由ReplacingMergeTree去重
+    async fn params(
+        &self,
+        ctx: &CollectContext<'_>,
+    ) -> anyhow::Result<Vec<StockAdjustFactorParam>> {
+        let codes = listed_security_codes(&ctx.ext_res.ch_client, SecurityKind::Stock).await?;
+        Ok(codes
             .into_iter()
-            .map(|value| StockHsgtHistEmInsert::from_with_dir_ts(value, flow_dir, now))
+            .map(|code| StockAdjustFactorParam {
+                code: code.symbol().to_owned(),
+                symbol: code.prefixed(),
+            })
             .collect())
     }
 
-    async fn get_api_data(&self) -> anyhow::Result<Vec<StockHsgtHistEmInsert>> {
-        let api_data: Vec<Vec<StockHsgtHistEmInsert>> = stream::iter(FlowDirection::iter())
-            .map(|flow_dir| self.get_dir_data(flow_dir))
-            .buffer_unordered(2)
-            .try_collect()
-            .await?;
-
-        Ok(api_data.into_iter().flatten().collect())
+    fn query(&self, param: &StockAdjustFactorParam) -> Vec<(&'static str, String)> {
+        vec![
+            ("symbol", param.symbol.clone()),
+            ("adjust", "hfq-factor".to_owned()),
+        ]
     }
 
-    pub async fn collect_data(&self) -> anyhow::Result<()> {
-        let rows = self.get_api_data().await?;
+    fn transform(
+        &self,
+        param: &StockAdjustFactorParam,
+        value: AkStockZhADailyHfqFactor,
+        ts: DateTime<Utc>,
+    ) -> Option<StockAdjustFactorInsert> {
+        StockAdjustFactorInsert::from_with_code(value, &param.code, ts)
+    }
 
-        let mut inserter = self.ext_res.ch_client.inserter(&self.data_table)?;
-        for row in rows {
-            inserter.write(&row)?;
+    /// 新浪的接口容易被限流，每次请求512只股票的数据之后暂停30秒
+    fn settings(&self) -> CollectSettings {
+        CollectSettings {
+            retry_max_interval: 60,
+            retry_max_elapsed: 300,
+            concurrency: 16,
+            batch_size: Some(512),
+            batch_pause: Duration::from_secs(30),
+            ..Default::default()
         }
-        inserter.end().await?;
-
-        Ok(())
     }
 }
 
 // -----------------------------------------------------------------------------------------
 
-pub struct StockZtPoolEmMonitor {
-    data_url: String,
-    data_table: String,
-    ext_res: ExternalResource,
+/// 东方财富网-数据中心-资金流向-沪深港通资金流向-沪深港通历史数据
+#[derive(Clone)]
+pub struct StockHsgtHistEmMonitor;
+
+impl Collector for StockHsgtHistEmMonitor {
+    type Param = FlowDirection;
+    type Api = AkStockHsgtHistEm;
+    type Row = StockHsgtHistEmInsert;
+
+    fn meta(&self) -> TaskMeta {
+        TaskMeta {
+            name: "stock_hsgt_hist_em".to_owned(),
+            desc: "东方财富网-数据中心-资金流向-沪深港通资金流向-沪深港通历史数据".to_owned(),
+            cron_expr: "0 30 15 * * MON-FRI".to_owned(),
+            tag: Some(ScheduleTaskType::AStock),
+            overlap: OverlapPolicy::Skip,
+            timeout: Some(Duration::from_secs(5 * 60)),
+            trading_days_only: true,
+            misfire: MisfirePolicy::RunOnce,
+        }
+    }
+
+    fn path(&self) -> &'static str {
+        "/stock_hsgt_hist_em"
+    }
+
+    fn table(&self) -> &'static str {
+        "stock_hsgt_hist_em"
+    }
+
+    async fn params(&self, _ctx: &CollectContext<'_>) -> anyhow::Result<Vec<FlowDirection>> {
+        Ok(collector::variants())
+    }
+
+    fn query(&self, flow_dir: &FlowDirection) -> Vec<(&'static str, String)> {
+        vec![("symbol", flow_dir.as_str().to_owned())]
+    }
+
+    fn transform(
+        &self,
+        flow_dir: &FlowDirection,
+        value: AkStockHsgtHistEm,
+        ts: DateTime<Utc>,
+    ) -> Option<StockHsgtHistEmInsert> {
+        Some(StockHsgtHistEmInsert::from_with_dir_ts(
+            value, *flow_dir, ts,
+        ))
+    }
+
+    fn settings(&self) -> CollectSettings {
+        CollectSettings {
+            concurrency: 2,
+            ..Default::default()
+        }
+    }
 }
 
-impl StockZtPoolEmMonitor {
-    /// 获取某一天的所有数据
-    async fn get_date_data(&self, date: NaiveDate) -> anyhow::Result<Vec<StockZtPoolEmInsert>> {
-        let formatted = date.format("%Y%m%d").to_string();
-        let backoff_s = config_backoff(5, 30);
-
-        let api_data = backoff::future::retry(backoff_s, || async {
-            let api_data: Vec<AkStockZtPoolEm> = self
-                .ext_res
-                .http_client
-                .get(&self.data_url)
-                .query(&[("date", formatted.as_str())])
-                .send()
-                .await?
-                .error_for_status()?
-                .json()
-                .await
-                .map_err(backoff::Error::Permanent)?;
+// -----------------------------------------------------------------------------------------
 
-            Ok(api_data)
-        })
-        .await?;
+/// 东方财富网-行情中心-涨停板行情-涨停股池
+#[derive(Clone)]
+pub struct StockZtPoolEmMonitor;
+
+impl Collector for StockZtPoolEmMonitor {
+    type Param = NaiveDate;
+    type Api = AkStockZtPoolEm;
+    type Row = StockZtPoolEmInsert;
+
+    fn meta(&self) -> TaskMeta {
+        TaskMeta {
+            name: "stock_zt_pool_em".to_owned(),
+            desc: "东方财富网-行情中心-涨停板行情-涨停股池".to_owned(),
+            cron_expr: "0 30 15 * * MON-FRI".to_owned(),
+            tag: Some(ScheduleTaskType::AStock),
+            overlap: OverlapPolicy::Skip,
+            timeout: Some(Duration::from_secs(10 * 60)),
+            trading_days_only: true,
+            misfire: MisfirePolicy::RunOnce,
+        }
+    }
 
-        let now = Utc::now();
+    fn path(&self) -> &'static str {
+        "/stock_zt_pool_em"
+    }
 
-        Ok(api_data
-            .into_iter()
-            .map(|value| StockZtPoolEmInsert::from_with_time(value, date, now))
-            .collect())
+    fn table(&self) -> &'static str {
+        "stock_zt_pool_em"
     }
 
     /// 获取过去两周的所有涨跌停板数据
-    async fn get_api_data(&self) -> anyhow::Result<Vec<StockZtPoolEmInsert>> {
-        // 获取当前本地时间
+    async fn params(&self, _ctx: &CollectContext<'_>) -> anyhow::Result<Vec<NaiveDate>> {
         let now = Local::now().date_naive();
-        // 计算14天前的日期
-        let start_date = now - Duration::weeks(2);
-        let dates = (0..=14)
-            .map(|diff| start_date + Duration::days(diff))
-            .collect::<Vec<_>>();
+        Ok(collector::dates(now - chrono::Duration::weeks(2), now))
+    }
 
-        let api_data: Vec<Vec<StockZtPoolEmInsert>> = stream::iter(dates)
-            .map(|date| self.get_date_data(date))
-            .buffer_unordered(8)
-            .try_collect()
-            .await?;
+    fn query(&self, date: &NaiveDate) -> Vec<(&'static str, String)> {
+        vec![("date", date.format("%Y%m%d").to_string())]
+    }
 
-        Ok(api_data.into_iter().flatten().collect())
+    fn transform(
+        &self,
+        date: &NaiveDate,
+        value: AkStockZtPoolEm,
+        ts: DateTime<Utc>,
+    ) -> Option<StockZtPoolEmInsert> {
+        Some(StockZtPoolEmInsert::from_with_time(value, *date, ts))
     }
+}
 
-    /// 收集数据到clickhouse
-    pub async fn collect_data(&self) -> anyhow::Result<()> {
-        let rows = self.get_api_data().await?;
+// ------------------------------------------------------------------------------------
 
-        let mut inserter = self.ext_res.ch_client.inserter(&self.data_table)?;
-        for row in rows {
-            inserter.write(&row)?;
+/// 财新网-财新数据通-内容精选
+#[derive(Clone)]
+pub struct StockNewsMainCxMonitor;
+
+impl Collector for StockNewsMainCxMonitor {
+    type Param = ();
+    type Api = AkStockNewsMainCx;
+    type Row = StockNewsMainCxInsert;
+
+    fn meta(&self) -> TaskMeta {
+        TaskMeta {
+            name: "stock_news_main_cx".to_owned(),
+            desc: "财新网-财新数据通-内容精选".to_owned(),
+            cron_expr: "0 52 21 * * *".to_owned(),
+            tag: Some(ScheduleTaskType::AStock),
+            overlap: OverlapPolicy::Skip,
+            timeout: Some(Duration::from_secs(5 * 60)),
+            trading_days_only: false,
+            misfire: MisfirePolicy::RunOnce,
         }
-        inserter.end().await?;
+    }
 
-        Ok(())
+    fn path(&self) -> &'static str {
+        "/stock_news_main_cx"
+    }
+
+    fn table(&self) -> &'static str {
+        "stock_news_main_cx"
+    }
+
+    async fn params(&self, _ctx: &CollectContext<'_>) -> anyhow::Result<Vec<()>> {
+        Ok(collector::single())
+    }
+
+    fn transform(
+        &self,
+        _param: &(),
+        value: AkStockNewsMainCx,
+        ts: DateTime<Utc>,
+    ) -> Option<StockNewsMainCxInsert> {
+        Some(StockNewsMainCxInsert::from_with_ts(value, ts))
     }
 }
 
 // ------------------------------------------------------------------------------------
 
-pub struct StockNewsMainCxMonitor {
-    data_url: String,
-    data_table: String,
-    ext_res: ExternalResource,
-}
+/// 同花顺-数据中心-技术选股-连续上涨，接口每次返回全量数据，每次收集替换整个表格
+#[derive(Clone)]
+pub struct StockRankLxszThsMonitor;
+
+impl Collector for StockRankLxszThsMonitor {
+    type Param = ();
+    type Api = StockRankLxszThsInsert;
+    type Row = StockRankLxszThsInsert;
+
+    fn meta(&self) -> TaskMeta {
+        TaskMeta {
+            name: "stock_rank_lxsz_ths".to_owned(),
+            desc: "同花顺-数据中心-技术选股-连续上涨".to_owned(),
+            cron_expr: "0 3 17 * * *".to_owned(),
+            tag: Some(ScheduleTaskType::AStock),
+            overlap: OverlapPolicy::Skip,
+            timeout: Some(Duration::from_secs(5 * 60)),
+            trading_days_only: false,
+            misfire: MisfirePolicy::RunOnce,
+        }
+    }
 
-impl StockNewsMainCxMonitor {
-    async fn get_api_data(&self) -> anyhow::Result<Vec<StockNewsMainCxInsert>> {
-        let res: Vec<AkStockNewsMainCx> = self
-            .ext_res
-            .http_client
-            .get(with_base_url("/stock_news_main_cx"))
-            .send()
-            .await?
-            .error_for_status()?
-            .json()
-            .await?;
-
-        let now = Utc::now();
-
-        Ok(res
-            .into_iter()
-            .map(|value| StockNewsMainCxInsert::from_with_ts(value, now))
-            .collect())
+    fn path(&self) -> &'static str {
+        "/stock_rank_lxsz_ths"
     }
 
-    pub async fn collect_data(&self) -> anyhow::Result<()> {
-        let rows = self.get_api_data().await?;
+    fn table(&self) -> &'static str {
+        "stock_rank_lxsz_ths"
+    }
 
-        let mut inserter = self.ext_res.ch_client.inserter(&self.data_table)?;
-        for row in rows {
-            inserter.write(&row)?;
-        }
-        inserter.end().await?;
+    async fn params(&self, _ctx: &CollectContext<'_>) -> anyhow::Result<Vec<()>> {
+        Ok(collector::single())
+    }
+
+    fn transform(
+        &self,
+        _param: &(),
+        value: StockRankLxszThsInsert,
+        _ts: DateTime<Utc>,
+    ) -> Option<StockRankLxszThsInsert> {
+        Some(value)
+    }
 
-        Ok(())
+    fn sink(&self) -> Sink {
+        Sink::Replace
     }
 }
 
-// ------------------------------------------------------------------------------------
+// ------------------------------------------------------------------------------------------
+
+/// 收集同花顺-财务指标-主要指标，每个季报披露截止日之后收集一次所有股票按报告期、年度以及单季度的数据
+#[derive(Clone)]
+pub(super) struct StockFinancialAbstractThsMonitor;
 
-pub struct StockRankLxszThsMonitor {
-    data_table: String,
-    ext_res: ExternalResource,
+/// 单次请求的参数
+#[derive(Serialize, Deserialize)]
+pub(super) struct StockFinancialAbstractThsParam {
+    code: String,
+    period_type: FinancialPeriodType,
 }
 
-impl StockRankLxszThsMonitor {
-    pub async fn collect_data(&self) -> anyhow::Result<()> {
-        let rows = AkStockRankLxszThs::from_astock_api(&self.ext_res.http_client).await?;
+impl Collector for StockFinancialAbstractThsMonitor {
+    type Param = StockFinancialAbstractThsParam;
+    type Api = AkStockFinancialAbstractThs;
+    type Row = StockFinancialAbstractThsInsert;
+
+    /// 年报以及一季报、半年报、三季报的披露截止日分别为4月30日、8月31日以及10月31日，
+    /// 1月初再收集一次业绩快报之后提前披露的年报
+    fn meta(&self) -> TaskMeta {
+        TaskMeta {
+            name: "stock_financial_abstract_ths".to_owned(),
+            desc: "同花顺-财务指标-主要指标, 每个季报披露截止日之后收集所有股票的数据".to_owned(),
+            cron_expr: "0 0 20 1 1,5,9,11 *".to_owned(),
+            tag: Some(ScheduleTaskType::AStock),
+            overlap: OverlapPolicy::Skip,
+            timeout: Some(Duration::from_secs(4 * 60 * 60)),
+            trading_days_only: false,
+            misfire: MisfirePolicy::RunOnce,
+        }
+    }
 
-        // 首先删除当前表格之中的所有数据
-        let sql = format!("TRUNCATE TABLE {}", self.data_table);
-        self.ext_res.ch_client.query(&sql).execute().await?;
+    fn path(&self) -> &'static str {
+        "/stock_financial_abstract_ths"
+    }
 
-        let mut inserter = self.ext_res.ch_client.inserter(&self.data_table)?;
-        for row in rows {
-            inserter.write(&row)?;
-        }
-        inserter.end().await?;
+    fn table(&self) -> &'static str {
+        "stock_financial_abstract_ths"
+    }
+
+    /// 所有股票 x 所有报告期类型，接口每次返回该股票全部历史报告期的数据，由ReplacingMergeTree去重
+    async fn params(
+        &self,
+        ctx: &CollectContext<'_>,
+    ) -> anyhow::Result<Vec<StockFinancialAbstractThsParam>> {
+        let codes = get_distinct_code(&ctx.ext_res.ch_client).await?;
+        Ok(
+            collector::product(codes, collector::variants::<FinancialPeriodType>())
+                .into_iter()
+                .map(|(code, period_type)| StockFinancialAbstractThsParam { code, period_type })
+                .collect(),
+        )
+    }
+
+    fn query(&self, param: &StockFinancialAbstractThsParam) -> Vec<(&'static str, String)> {
+        vec![
+            ("symbol", param.code.clone()),
+            ("indicator", param.period_type.to_str().to_owned()),
+        ]
+    }
 
-        Ok(())
+    fn transform(
+        &self,
+        param: &StockFinancialAbstractThsParam,
+        value: AkStockFinancialAbstractThs,
+        ts: DateTime<Utc>,
+    ) -> Option<StockFinancialAbstractThsInsert> {
+        StockFinancialAbstractThsInsert::from_with_type(value, &param.code, param.period_type, ts)
+    }
+
+    /// 同花顺的接口容易被限流，每次请求256个参数之后暂停30秒
+    fn settings(&self) -> CollectSettings {
+        CollectSettings {
+            retry_max_interval: 60,
+            retry_max_elapsed: 300,
+            concurrency: 16,
+            batch_size: Some(256),
+            batch_pause: Duration::from_secs(30),
+            ..Default::default()
+        }
     }
 }
 
@@ -486,9 +618,11 @@ mod test {
     use crate::{
         init::ExternalResource,
         scheduler::CST,
-        tasks::{TEST_CH_CLIENT, TEST_HTTP_CLIENT},
+        tasks::{TEST_CH_CLIENT, TEST_HTTP_CLIENT, collector::CollectorTask},
     };
 
+    use tokio_util::sync::CancellationToken;
+
     use super::*;
 
     #[test]
@@ -509,14 +643,10 @@ mod test {
             ch_client: TEST_CH_CLIENT.clone(),
             http_client: TEST_HTTP_CLIENT.clone(),
         };
-        let reatime_stock_monitor = RealTimeStockMonitor {
-            data_url: with_base_url("/stock_zh_a_spot_em"),
-            data_table: "astock_realtime_data".to_owned(),
-            ext_res,
-        };
+        let reatime_stock_monitor = CollectorTask::new(RealTimeStockMonitor, ext_res);
 
         reatime_stock_monitor
-            .collect_data(Utc::now())
+            .collect_data(&CancellationToken::new())
             .await
             .unwrap();
     }
@@ -527,13 +657,12 @@ mod test {
             ch_client: TEST_CH_CLIENT.clone(),
             http_client: TEST_HTTP_CLIENT.clone(),
         };
-        let stock_zh_a_hist_monitor = StockZhAHistMonitor {
-            data_url: with_base_url("/stock_zh_a_hist"),
-            data_table: "stock_zh_a_hist".to_owned(),
-            ext_res,
-        };
+        let stock_zh_a_hist_monitor = CollectorTask::new(StockZhAHistMonitor, ext_res);
 
-        stock_zh_a_hist_monitor.collect_data().await.unwrap();
+        stock_zh_a_hist_monitor
+            .collect_data(&CancellationToken::new())
+            .await
+            .unwrap();
     }
 
     #[tokio::test]
@@ -543,13 +672,12 @@ mod test {
             http_client: TEST_HTTP_CLIENT.clone(),
         };
 
-        let stock_hsgt_hist_em_monitor = StockHsgtHistEmMonitor {
-            data_url: with_base_url("/stock_hsgt_hist_em"),
-            data_table: "stock_hsgt_hist_em".to_owned(),
-            ext_res,
-        };
+        let stock_hsgt_hist_em_monitor = CollectorTask::new(StockHsgtHistEmMonitor, ext_res);
 
-        stock_hsgt_hist_em_monitor.collect_data().await.unwrap();
+        stock_hsgt_hist_em_monitor
+            .collect_data(&CancellationToken::new())
+            .await
+            .unwrap();
     }
 
     #[tokio::test]
@@ -559,13 +687,12 @@ mod test {
             http_client: TEST_HTTP_CLIENT.clone(),
         };
 
-        let stock_zt_pool_em_monitor = StockZtPoolEmMonitor {
-            data_url: with_base_url("/stock_zt_pool_em"),
-            data_table: "stock_zt_pool_em".to_owned(),
-            ext_res,
-        };
+        let stock_zt_pool_em_monitor = CollectorTask::new(StockZtPoolEmMonitor, ext_res);
 
-        stock_zt_pool_em_monitor.collect_data().await.unwrap();
+        stock_zt_pool_em_monitor
+            .collect_data(&CancellationToken::new())
+            .await
+            .unwrap();
     }
 
     #[tokio::test]
@@ -575,13 +702,12 @@ mod test {
             http_client: TEST_HTTP_CLIENT.clone(),
         };
 
-        let stock_news_main_cx_monitor = StockNewsMainCxMonitor {
-            data_url: with_base_url("/stock_news_main_cx"),
-            data_table: "stock_news_main_cx".to_owned(),
-            ext_res,
-        };
+        let stock_news_main_cx_monitor = CollectorTask::new(StockNewsMainCxMonitor, ext_res);
 
-        stock_news_main_cx_monitor.collect_data().await.unwrap();
+        stock_news_main_cx_monitor
+            .collect_data(&CancellationToken::new())
+            .await
+            .unwrap();
     }
 
     #[tokio::test]
@@ -591,12 +717,12 @@ mod test {
             http_client: TEST_HTTP_CLIENT.clone(),
         };
 
-        let stock_rank_lxsz_ths_monitor = StockRankLxszThsMonitor {
-            data_table: "stock_rank_lxsz_ths".to_owned(),
-            ext_res: ext_res.clone(),
-        };
+        let stock_rank_lxsz_ths_monitor = CollectorTask::new(StockRankLxszThsMonitor, ext_res);
 
-        stock_rank_lxsz_ths_monitor.collect_data().await.unwrap();
+        stock_rank_lxsz_ths_monitor
+            .collect_data(&CancellationToken::new())
+            .await
+            .unwrap();
     }
 
     #[test]
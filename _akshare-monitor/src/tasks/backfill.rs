@@ -0,0 +1,631 @@
+//! 历史数据回填，按照日期区间、代码列表以及复权方式一次性拉取多年的历史数据，
+//! 每个代码回填完成之后都会在clickhouse之中记录一个检查点，
+//! 中断(monitor重启、执行失败或者被取消)的回填任务恢复之后会跳过已经完成的代码
+use std::{
+    cmp::Reverse,
+    collections::{HashMap, HashSet},
+    sync::{
+        Arc, LazyLock, Mutex, OnceLock,
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+    },
+};
+
+use chrono::{DateTime, FixedOffset, NaiveDate, Utc};
+use clickhouse::Row;
+use data_mind::repository::akshare::StockAdjustmentType;
+use futures::{StreamExt, TryStreamExt, stream};
+use poem::{error::ResponseError, http::StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_repr::{Deserialize_repr, Serialize_repr};
+use snafu::{ResultExt, Snafu};
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+
+use super::{
+    a_index::{StockZhIndexDailyMonitor, StockZhIndexDailyParam},
+    a_stock::{StockZhAHistMonitor, StockZhAHistParam},
+    collector::{self, CollectorTask},
+    utils::{get_distinct_code, get_index_codes},
+};
+use crate::{init::ExternalResource, scheduler::CST};
+
+const BACKFILL_JOB_TABLE: &str = "backfill_job";
+const BACKFILL_CHECKPOINT_TABLE: &str = "backfill_checkpoint";
+/// 同时回填的代码数量
+const BACKFILL_CONCURRENCY: usize = 8;
+
+pub static BACKFILL_MANAGER: LazyLock<BackfillManager> = LazyLock::new(BackfillManager::default);
+
+#[derive(Debug, Snafu)]
+pub enum BackfillError {
+    /// 开始日期晚于结束日期
+    #[snafu(display("Invalid date range: {} is after {}", start_date, end_date))]
+    InvalidDateRange {
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+    },
+    /// 代码列表既不是"all"也不是一个非空的代码列表
+    #[snafu(display(
+        "Invalid codes `{}`, expect \"all\" or a non-empty list of codes",
+        codes
+    ))]
+    InvalidCodes { codes: String },
+    /// 复权方式之中存在不复权以外的方式
+    #[snafu(display(
+        "Invalid adj_types `{}`, only 0 (no adjustment) is supported",
+        adj_types
+    ))]
+    InvalidAdjTypes { adj_types: String },
+    /// 不存在该id对应的回填任务
+    #[snafu(display("Backfill job `{}` not found", job_id))]
+    JobNotFound { job_id: String },
+    /// 回填任务正在执行之中
+    #[snafu(display("Backfill job `{}` is running", job_id))]
+    JobRunning { job_id: String },
+    /// monitor启动时还没有完成初始化
+    #[snafu(display("Backfill manager has not been initialized"))]
+    NotInitialized,
+    /// 回填任务持久化失败
+    #[snafu(display("Persist backfill job error: {}", source))]
+    Storage { source: clickhouse::error::Error },
+}
+
+impl ResponseError for BackfillError {
+    fn status(&self) -> StatusCode {
+        match self {
+            BackfillError::InvalidDateRange { .. }
+            | BackfillError::InvalidCodes { .. }
+            | BackfillError::InvalidAdjTypes { .. } => StatusCode::BAD_REQUEST,
+            BackfillError::JobNotFound { .. } => StatusCode::NOT_FOUND,
+            BackfillError::JobRunning { .. } => StatusCode::CONFLICT,
+            BackfillError::NotInitialized => StatusCode::SERVICE_UNAVAILABLE,
+            BackfillError::Storage { .. } => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+/// 支持回填的数据
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackfillTarget {
+    /// 东方财富-沪深京 A 股日频率数据
+    StockZhAHist,
+    /// 指数历史行情数据-东方财富，股票指数日频率数据
+    StockZhIndexDaily,
+}
+
+impl BackfillTarget {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::StockZhAHist => "stock_zh_a_hist",
+            Self::StockZhIndexDaily => "stock_zh_index_daily",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "stock_zh_a_hist" => Some(Self::StockZhAHist),
+            "stock_zh_index_daily" => Some(Self::StockZhIndexDaily),
+            _ => None,
+        }
+    }
+
+    /// 获取该数据之中的所有代码
+    async fn all_codes(&self, ext_res: &ExternalResource) -> anyhow::Result<Vec<String>> {
+        match self {
+            Self::StockZhAHist => get_distinct_code(&ext_res.ch_client).await,
+            Self::StockZhIndexDaily => get_index_codes(&ext_res.ch_client).await,
+        }
+    }
+
+    /// 回填一个代码在日期区间之中的数据，返回写入的行数
+    async fn backfill_code(
+        &self,
+        ext_res: &ExternalResource,
+        record: &BackfillJobRecord,
+        code: &str,
+    ) -> anyhow::Result<u64> {
+        let ts = Utc::now();
+        match self {
+            Self::StockZhAHist => {
+                let task = CollectorTask::configured(StockZhAHistMonitor, ext_res.clone());
+                let mut rows = Vec::new();
+                for adj_type in &record.adj_types {
+                    let param = StockZhAHistParam {
+                        code: code.to_owned(),
+                        adj_type: *adj_type,
+                        start_date: record.start_date.format("%Y%m%d").to_string(),
+                        end_date: record.end_date.format("%Y%m%d").to_string(),
+                    };
+                    rows.extend(task.fetch(param, ts).await?);
+                }
+                task.write(rows).await
+            }
+            Self::StockZhIndexDaily => {
+                let task = CollectorTask::configured(StockZhIndexDailyMonitor, ext_res.clone());
+                let param = StockZhIndexDailyParam {
+                    code: code.to_owned(),
+                    start_date: record.start_date.format("%Y%m%d").to_string(),
+                    end_date: record.end_date.format("%Y%m%d").to_string(),
+                };
+                let rows = task.fetch(param, ts).await?;
+                task.write(rows).await
+            }
+        }
+    }
+}
+
+/// 需要回填的代码，`"all"`代表所有代码
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum CodeSelection {
+    Keyword(String),
+    List(Vec<String>),
+}
+
+/// 创建回填任务的请求
+#[derive(Debug, Deserialize)]
+pub struct BackfillRequest {
+    pub target: BackfillTarget,
+    /// 开始日期(包含)，格式为`yyyy-mm-dd`
+    pub start_date: NaiveDate,
+    /// 结束日期(包含)，格式为`yyyy-mm-dd`
+    pub end_date: NaiveDate,
+    pub codes: CodeSelection,
+    /// 复权方式，只为兼容保留，`stock_zh_a_hist`只保存不复权数据，复权价格在查询时由复权因子计算，
+    /// 所以只接受不复权(0)，传入其他复权方式时返回错误
+    pub adj_types: Option<Vec<StockAdjustmentType>>,
+}
+
+impl BackfillRequest {
+    /// 检查请求之中的复权方式是否都是不复权
+    fn check_adj_types(&self) -> Result<(), BackfillError> {
+        match &self.adj_types {
+            Some(adj_types) if adj_types.iter().any(|t| *t != StockAdjustmentType::None) => {
+                InvalidAdjTypesSnafu {
+                    adj_types: format!("{:?}", adj_types),
+                }
+                .fail()
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// 回填任务状态
+#[derive(Debug, Clone, Copy, PartialEq, Serialize_repr, Deserialize_repr)]
+#[repr(u8)]
+pub enum BackfillStatus {
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+    /// 执行过程之中monitor被重启，需要手动恢复
+    Interrupted,
+}
+
+impl BackfillStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Running => "Running",
+            Self::Completed => "Completed",
+            Self::Failed => "Failed",
+            Self::Cancelled => "Cancelled",
+            Self::Interrupted => "Interrupted",
+        }
+    }
+}
+
+/// 回填任务持久化记录，对应clickhouse之中的`backfill_job`表
+#[derive(Debug, Clone, Row, Serialize, Deserialize)]
+struct BackfillJobRecord {
+    job_id: String,
+    target: String,
+    #[serde(with = "clickhouse::serde::chrono::date")]
+    start_date: NaiveDate,
+    #[serde(with = "clickhouse::serde::chrono::date")]
+    end_date: NaiveDate,
+    /// 为空代表所有代码
+    codes: Vec<String>,
+    adj_types: Vec<StockAdjustmentType>,
+    status: BackfillStatus,
+    /// 执行失败时的错误链
+    error: String,
+    #[serde(with = "clickhouse::serde::chrono::datetime64::millis")]
+    created_at: DateTime<Utc>,
+    /// 记录更新时间
+    #[serde(with = "clickhouse::serde::chrono::datetime64::millis")]
+    ts: DateTime<Utc>,
+}
+
+/// 回填检查点，对应clickhouse之中的`backfill_checkpoint`表，每个代码回填完成之后记录一行
+#[derive(Debug, Row, Serialize, Deserialize)]
+struct BackfillCheckpoint {
+    job_id: String,
+    code: String,
+    rows_written: u64,
+    #[serde(with = "clickhouse::serde::chrono::datetime64::millis")]
+    ts: DateTime<Utc>,
+}
+
+/// 回填任务展示信息
+#[derive(Debug, Serialize)]
+pub struct BackfillJobShow {
+    pub job_id: String,
+    pub target: String,
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    /// 为空代表所有代码
+    pub codes: Vec<String>,
+    pub adj_types: Vec<StockAdjustmentType>,
+    pub status: &'static str,
+    pub error: String,
+    /// 需要回填的代码总数，回填所有代码时在开始执行之后才能确定，之前为0
+    pub total: usize,
+    /// 已经完成的代码数量
+    pub done: usize,
+    pub rows_written: u64,
+    pub created_at: DateTime<FixedOffset>,
+    pub updated_at: DateTime<FixedOffset>,
+}
+
+/// 内存之中的回填任务
+struct BackfillJob {
+    record: Mutex<BackfillJobRecord>,
+    total: AtomicUsize,
+    done: AtomicUsize,
+    rows_written: AtomicU64,
+    /// 每一次执行都会使用一个新的cancel token
+    cancel: Mutex<CancellationToken>,
+}
+
+impl BackfillJob {
+    fn new(record: BackfillJobRecord, done: usize, rows_written: u64) -> Arc<Self> {
+        Arc::new(Self {
+            total: AtomicUsize::new(record.codes.len()),
+            done: AtomicUsize::new(done),
+            rows_written: AtomicU64::new(rows_written),
+            record: Mutex::new(record),
+            cancel: Mutex::new(CancellationToken::new()),
+        })
+    }
+
+    fn show(&self) -> BackfillJobShow {
+        let record = self.record.lock().unwrap().clone();
+        BackfillJobShow {
+            job_id: record.job_id,
+            target: record.target,
+            start_date: record.start_date,
+            end_date: record.end_date,
+            codes: record.codes,
+            adj_types: record.adj_types,
+            status: record.status.as_str(),
+            error: record.error,
+            total: self.total.load(Ordering::SeqCst),
+            done: self.done.load(Ordering::SeqCst),
+            rows_written: self.rows_written.load(Ordering::SeqCst),
+            created_at: record.created_at.with_timezone(&CST),
+            updated_at: record.ts.with_timezone(&CST),
+        }
+    }
+}
+
+/// 回填任务管理器，回填任务只会在接收到请求(或者恢复请求)的monitor实例上执行
+#[derive(Default)]
+pub struct BackfillManager {
+    ext_res: OnceLock<ExternalResource>,
+    /// HashMap<job_id, 回填任务>
+    jobs: Mutex<HashMap<String, Arc<BackfillJob>>>,
+}
+
+impl BackfillManager {
+    /// 挂载外部资源并加载所有已经持久化的回填任务，
+    /// 上次monitor停止时依旧处于执行状态的任务会被标记为`Interrupted`，需要手动恢复
+    pub async fn init(&self, ext_res: ExternalResource) -> anyhow::Result<()> {
+        let records: Vec<BackfillJobRecord> = ext_res
+            .ch_client
+            .query(
+                "SELECT job_id, target, start_date, end_date, codes, adj_types, \
+                status, error, created_at, ts \
+                FROM backfill_job FINAL",
+            )
+            .fetch_all()
+            .await?;
+        let progress: Vec<(String, u64, u64)> = ext_res
+            .ch_client
+            .query(
+                "SELECT job_id, count(), sum(rows_written) \
+                FROM backfill_checkpoint FINAL \
+                GROUP BY job_id",
+            )
+            .fetch_all()
+            .await?;
+        let progress: HashMap<String, (u64, u64)> = progress
+            .into_iter()
+            .map(|(job_id, done, rows_written)| (job_id, (done, rows_written)))
+            .collect();
+
+        let mut jobs = self.jobs.lock().unwrap();
+        for mut record in records {
+            if record.status == BackfillStatus::Running {
+                record.status = BackfillStatus::Interrupted;
+            }
+            let (done, rows_written) = progress.get(&record.job_id).copied().unwrap_or_default();
+            jobs.insert(
+                record.job_id.clone(),
+                BackfillJob::new(record, done as usize, rows_written),
+            );
+        }
+        drop(jobs);
+
+        if self.ext_res.set(ext_res).is_err() {
+            ftlog::warn!(
+                "[in BackfillManager::init] backfill manager has been initialized already"
+            );
+        }
+        Ok(())
+    }
+
+    /// 创建并开始执行一个回填任务，返回回填任务的id
+    pub async fn submit(&self, request: BackfillRequest) -> Result<String, BackfillError> {
+        let ext_res = self.ext_res.get().ok_or(BackfillError::NotInitialized)?;
+        if request.start_date > request.end_date {
+            return InvalidDateRangeSnafu {
+                start_date: request.start_date,
+                end_date: request.end_date,
+            }
+            .fail();
+        }
+        request.check_adj_types()?;
+        let codes = match request.codes {
+            CodeSelection::Keyword(keyword) if keyword == "all" => Vec::new(),
+            CodeSelection::List(codes) if !codes.is_empty() => codes,
+            CodeSelection::Keyword(codes) => return InvalidCodesSnafu { codes }.fail(),
+            CodeSelection::List(_) => {
+                return InvalidCodesSnafu { codes: "[]" }.fail();
+            }
+        };
+        let adj_types = vec![StockAdjustmentType::None];
+
+        let now = Utc::now();
+        let record = BackfillJobRecord {
+            job_id: Uuid::new_v4().to_string(),
+            target: request.target.as_str().to_owned(),
+            start_date: request.start_date,
+            end_date: request.end_date,
+            codes,
+            adj_types,
+            status: BackfillStatus::Running,
+            error: String::new(),
+            created_at: now,
+            ts: now,
+        };
+        save_job(&ext_res.ch_client, &record)
+            .await
+            .context(StorageSnafu)?;
+
+        let job_id = record.job_id.clone();
+        let job = BackfillJob::new(record, 0, 0);
+        self.jobs
+            .lock()
+            .unwrap()
+            .insert(job_id.clone(), Arc::clone(&job));
+        ftlog::info!("[backfill] job {} submitted", job_id);
+        tokio::spawn(run_job(ext_res.clone(), job));
+
+        Ok(job_id)
+    }
+
+    /// 恢复一个没有完成的回填任务，已经完成的代码会被跳过
+    pub async fn resume(&self, job_id: &str) -> Result<(), BackfillError> {
+        let ext_res = self.ext_res.get().ok_or(BackfillError::NotInitialized)?;
+        let job = self.get(job_id)?;
+        let record = {
+            let mut record = job.record.lock().unwrap();
+            if record.status == BackfillStatus::Running {
+                return JobRunningSnafu { job_id }.fail();
+            }
+            record.status = BackfillStatus::Running;
+            record.error = String::new();
+            record.ts = Utc::now();
+            record.clone()
+        };
+        *job.cancel.lock().unwrap() = CancellationToken::new();
+        save_job(&ext_res.ch_client, &record)
+            .await
+            .context(StorageSnafu)?;
+
+        ftlog::info!("[backfill] job {} resumed", job_id);
+        tokio::spawn(run_job(ext_res.clone(), job));
+        Ok(())
+    }
+
+    /// 取消一个正在执行的回填任务，之后可以通过[`BackfillManager::resume`]恢复
+    pub fn cancel(&self, job_id: &str) -> Result<(), BackfillError> {
+        self.get(job_id)?.cancel.lock().unwrap().cancel();
+        Ok(())
+    }
+
+    /// 获取一个回填任务的进度
+    pub fn job(&self, job_id: &str) -> Result<BackfillJobShow, BackfillError> {
+        Ok(self.get(job_id)?.show())
+    }
+
+    /// 按照创建时间倒序获取所有回填任务的进度
+    pub fn jobs(&self) -> Vec<BackfillJobShow> {
+        let mut jobs: Vec<_> = self
+            .jobs
+            .lock()
+            .unwrap()
+            .values()
+            .map(|job| job.show())
+            .collect();
+        jobs.sort_by_key(|job| Reverse(job.created_at));
+        jobs
+    }
+
+    fn get(&self, job_id: &str) -> Result<Arc<BackfillJob>, BackfillError> {
+        self.jobs
+            .lock()
+            .unwrap()
+            .get(job_id)
+            .cloned()
+            .ok_or_else(|| BackfillError::JobNotFound {
+                job_id: job_id.to_owned(),
+            })
+    }
+}
+
+/// 执行一个回填任务直到完成、失败或者被取消，并持久化最终状态
+async fn run_job(ext_res: ExternalResource, job: Arc<BackfillJob>) {
+    let cancel = job.cancel.lock().unwrap().clone();
+    let result = tokio::select! {
+        result = backfill(&ext_res, &job) => result,
+        _ = cancel.cancelled() => Err(anyhow::anyhow!("backfill job has been cancelled")),
+    };
+
+    let record = {
+        let mut record = job.record.lock().unwrap();
+        match result {
+            Ok(()) => record.status = BackfillStatus::Completed,
+            Err(err) => {
+                ftlog::error!(
+                    "[backfill] job {} stopped, error = {:?}",
+                    record.job_id,
+                    err
+                );
+                record.status = if cancel.is_cancelled() {
+                    BackfillStatus::Cancelled
+                } else {
+                    BackfillStatus::Failed
+                };
+                record.error = format!("{:#}", err);
+            }
+        }
+        record.ts = Utc::now();
+        record.clone()
+    };
+    ftlog::info!(
+        "[backfill] job {} finished with status {:?}, rows_written = {}",
+        record.job_id,
+        record.status,
+        job.rows_written.load(Ordering::SeqCst)
+    );
+    let _ = save_job(&ext_res.ch_client, &record)
+        .await
+        .inspect_err(|err| {
+            ftlog::error!("[backfill] persist job {} error = {:?}", record.job_id, err)
+        });
+}
+
+/// 跳过检查点之中已经完成的代码，并发回填剩余的代码
+async fn backfill(ext_res: &ExternalResource, job: &BackfillJob) -> anyhow::Result<()> {
+    let record = job.record.lock().unwrap().clone();
+    let target = BackfillTarget::from_name(&record.target)
+        .ok_or_else(|| anyhow::anyhow!("unknown backfill target `{}`", record.target))?;
+    let codes = if record.codes.is_empty() {
+        target.all_codes(ext_res).await?
+    } else {
+        record.codes.clone()
+    };
+
+    let checkpoints: Vec<BackfillCheckpoint> = ext_res
+        .ch_client
+        .query(
+            "SELECT job_id, code, rows_written, ts \
+            FROM backfill_checkpoint FINAL \
+            WHERE job_id = ?",
+        )
+        .bind(&record.job_id)
+        .fetch_all()
+        .await?;
+    let finished: HashSet<&str> = checkpoints.iter().map(|cp| cp.code.as_str()).collect();
+    let pending: Vec<String> = codes
+        .iter()
+        .filter(|code| !finished.contains(code.as_str()))
+        .cloned()
+        .collect();
+
+    job.total.store(codes.len(), Ordering::SeqCst);
+    job.done
+        .store(codes.len() - pending.len(), Ordering::SeqCst);
+    job.rows_written.store(
+        checkpoints.iter().map(|cp| cp.rows_written).sum(),
+        Ordering::SeqCst,
+    );
+
+    stream::iter(pending)
+        .map(|code| {
+            let record = &record;
+            async move {
+                let rows_written = target.backfill_code(ext_res, record, &code).await?;
+                let checkpoint = BackfillCheckpoint {
+                    job_id: record.job_id.clone(),
+                    code,
+                    rows_written,
+                    ts: Utc::now(),
+                };
+                let mut inserter = ext_res.ch_client.inserter(BACKFILL_CHECKPOINT_TABLE)?;
+                inserter.write(&checkpoint)?;
+                inserter.end().await?;
+
+                job.done.fetch_add(1, Ordering::SeqCst);
+                job.rows_written.fetch_add(rows_written, Ordering::SeqCst);
+                anyhow::Ok(())
+            }
+        })
+        .buffer_unordered(BACKFILL_CONCURRENCY)
+        .try_collect::<Vec<()>>()
+        .await?;
+
+    Ok(())
+}
+
+/// 保存(覆盖)一个回填任务的记录
+async fn save_job(
+    ch_client: &clickhouse::Client,
+    record: &BackfillJobRecord,
+) -> clickhouse::error::Result<()> {
+    let mut inserter = ch_client.inserter(BACKFILL_JOB_TABLE)?;
+    inserter.write(record)?;
+    inserter.end().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_backfill_request() {
+        let request: BackfillRequest = serde_json::from_str(
+            r#"{"target": "stock_zh_a_hist", "start_date": "2015-01-01", "end_date": "2024-12-31", "codes": "all"}"#,
+        )
+        .unwrap();
+        assert_eq!(request.target, BackfillTarget::StockZhAHist);
+        assert!(matches!(request.codes, CodeSelection::Keyword(ref kw) if kw == "all"));
+        assert!(request.adj_types.is_none());
+        assert!(request.check_adj_types().is_ok());
+
+        let request: BackfillRequest = serde_json::from_str(
+            r#"{"target": "stock_zh_index_daily", "start_date": "2020-01-01", "end_date": "2020-12-31", "codes": ["sh000001", "sz399001"], "adj_types": [1]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            BackfillTarget::from_name(request.target.as_str()),
+            Some(BackfillTarget::StockZhIndexDaily)
+        );
+        assert!(matches!(request.codes, CodeSelection::List(ref codes) if codes.len() == 2));
+        assert_eq!(request.adj_types, Some(vec![StockAdjustmentType::Forward]));
+        assert!(matches!(
+            request.check_adj_types(),
+            Err(BackfillError::InvalidAdjTypes { .. })
+        ));
+
+        let request: BackfillRequest = serde_json::from_str(
+            r#"{"target": "stock_zh_a_hist", "start_date": "2020-01-01", "end_date": "2020-12-31", "codes": ["000001"], "adj_types": [0]}"#,
+        )
+        .unwrap();
+        assert!(request.check_adj_types().is_ok());
+    }
+}
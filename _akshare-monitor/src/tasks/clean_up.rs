@@ -1,13 +1,73 @@
-use crate::scheduler::{Schedulable, ScheduleTaskType, TaskMeta};
+use std::{sync::Arc, time::Duration};
+
+use chrono::{DateTime, Days, Months, NaiveDate, Utc};
+use clickhouse::Row;
+use serde::{Deserialize, Serialize};
+use serde_repr::Serialize_repr;
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    config::{RetentionConfig, RetentionUnit},
+    scheduler::{CST, MisfirePolicy, OverlapPolicy, Schedulable, ScheduleTaskType, TaskMeta},
+};
+
+const RETENTION_REPORT_TABLE: &str = "retention_report";
 
 /// 每天定时清理数据库相关表格，避免磁盘爆炸
+///
+/// 按照配置文件之中的`[[retention]]`规则清理过期数据：
+///
+/// - 按日期分区的表格直接删除所有数据都已经过期的分区
+/// - 没有分区的表格设置clickhouse的TTL，由clickhouse在合并时删除过期数据
+///
+/// 每个规则的清理结果写入`retention_report`表之中
 pub(super) struct CleanUp {
     ch_client: clickhouse::Client,
+    rules: Vec<RetentionConfig>,
 }
 
 impl CleanUp {
-    pub fn new(ch_client: clickhouse::Client) -> Self {
-        Self { ch_client }
+    pub fn new(ch_client: clickhouse::Client, rules: Vec<RetentionConfig>) -> Self {
+        Self { ch_client, rules }
+    }
+
+    /// 应用所有保留规则，返回删除(或者等待TTL删除)的行数，单个规则失败不影响其余规则
+    async fn apply_rules(&self) -> anyhow::Result<u64> {
+        let today = Utc::now().with_timezone(&CST).date_naive();
+        let mut reports = Vec::with_capacity(self.rules.len());
+        let mut failed = Vec::new();
+        for rule in &self.rules {
+            match apply_rule(&self.ch_client, rule, today).await {
+                Ok(report) => {
+                    ftlog::info!(
+                        "[clean up] {} {:?} before {}, partitions = {:?}, rows = {}, bytes = {}",
+                        report.table,
+                        report.action,
+                        report.cutoff,
+                        report.partitions,
+                        report.rows,
+                        report.bytes
+                    );
+                    reports.push(report);
+                }
+                Err(err) => {
+                    ftlog::error!("[clean up] {} error = {:?}", rule.table, err);
+                    failed.push(rule.table.as_str());
+                }
+            }
+        }
+
+        if !reports.is_empty() {
+            let mut inserter = self.ch_client.inserter(RETENTION_REPORT_TABLE)?;
+            for report in &reports {
+                inserter.write(report)?;
+            }
+            inserter.end().await?;
+        }
+        if !failed.is_empty() {
+            anyhow::bail!("retention rules failed for tables {:?}", failed);
+        }
+        Ok(reports.iter().map(|report| report.rows).sum())
     }
 }
 
@@ -18,25 +78,209 @@ impl Schedulable for CleanUp {
             desc: "每天定时清理数据库相关表格，避免磁盘超出容量".to_owned(),
             cron_expr: "0 0 6 * * *".to_owned(),
             tag: Some(ScheduleTaskType::System),
+            overlap: OverlapPolicy::Skip,
+            timeout: Some(Duration::from_secs(30 * 60)),
+            trading_days_only: false,
+            misfire: MisfirePolicy::RunOnce,
         }
     }
 
     fn execute(
-        self: std::sync::Arc<Self>,
-    ) -> Box<dyn Future<Output = anyhow::Result<()>> + Send + 'static> {
-        Box::new(async move {
-            cleanup_astock_realtime_data(&self.ch_client).await?;
-            Ok(())
-        })
+        self: Arc<Self>,
+        _cancel: CancellationToken,
+    ) -> Box<dyn Future<Output = anyhow::Result<u64>> + Send + 'static> {
+        Box::new(async move { self.apply_rules().await })
+    }
+}
+
+/// 清理方式
+#[derive(Debug, Clone, Copy, PartialEq, Serialize_repr)]
+#[repr(u8)]
+enum RetentionAction {
+    /// 删除过期分区
+    DropPartition,
+    /// 设置TTL，由clickhouse在合并时删除
+    Ttl,
+}
+
+/// 一个保留规则的清理结果，对应clickhouse之中的`retention_report`表
+#[derive(Debug, Row, Serialize)]
+struct RetentionReport {
+    table: String,
+    action: RetentionAction,
+    /// 早于该日期的数据被清理
+    #[serde(with = "clickhouse::serde::chrono::date")]
+    cutoff: NaiveDate,
+    /// 被删除的分区
+    partitions: Vec<String>,
+    /// 删除分区时为被删除的行数，设置TTL时为等待TTL删除的行数
+    rows: u64,
+    /// 删除分区时为释放的磁盘字节数，设置TTL时为按照行数比例估算的字节数
+    bytes: u64,
+    #[serde(with = "clickhouse::serde::chrono::datetime64::millis")]
+    ts: DateTime<Utc>,
+}
+
+/// 一个分区的统计信息
+#[derive(Debug, Row, Deserialize)]
+struct PartitionStat {
+    partition: String,
+    partition_id: String,
+    rows: u64,
+    bytes: u64,
+    /// 分区之中数据的最大日期，分区键不是日期时为1970-01-01
+    #[serde(with = "clickhouse::serde::chrono::date")]
+    max_date: NaiveDate,
+}
+
+/// 表格的分区键以及包括TTL在内的完整引擎定义
+#[derive(Debug, Row, Deserialize)]
+struct TableInfo {
+    partition_key: String,
+    engine_full: String,
+}
+
+#[derive(Debug, Row, Deserialize)]
+struct TableSize {
+    rows: u64,
+    bytes: u64,
+    expired: u64,
+}
+
+/// 应用一个保留规则
+async fn apply_rule(
+    ch_client: &clickhouse::Client,
+    rule: &RetentionConfig,
+    today: NaiveDate,
+) -> anyhow::Result<RetentionReport> {
+    let cutoff = retention_cutoff(rule, today)?;
+    let info: Option<TableInfo> = ch_client
+        .query(
+            "SELECT partition_key, engine_full FROM system.tables \
+            WHERE database = currentDatabase() AND name = ?",
+        )
+        .bind(&rule.table)
+        .fetch_optional()
+        .await?;
+    let Some(info) = info else {
+        anyhow::bail!("table {} not exist", rule.table);
+    };
+
+    let mut report = RetentionReport {
+        table: rule.table.clone(),
+        action: RetentionAction::DropPartition,
+        cutoff,
+        partitions: Vec::new(),
+        rows: 0,
+        bytes: 0,
+        ts: Utc::now(),
+    };
+    if info.partition_key.is_empty() {
+        report.action = RetentionAction::Ttl;
+        let ttl = ttl_expr(rule);
+        if !info.engine_full.contains(&format!("TTL {}", ttl)) {
+            let sql = format!("ALTER TABLE {} MODIFY TTL {}", rule.table, ttl);
+            ch_client.query(&sql).execute().await?;
+        }
+
+        let sql = format!(
+            "SELECT count() AS rows, \
+            (SELECT sum(bytes_on_disk) FROM system.parts \
+            WHERE database = currentDatabase() AND table = ? AND active) AS bytes, \
+            countIf(toDate({}) < ?) AS expired \
+            FROM {}",
+            rule.column, rule.table
+        );
+        let size: TableSize = ch_client
+            .query(&sql)
+            .bind(&rule.table)
+            .bind(cutoff.to_string())
+            .fetch_one()
+            .await?;
+        report.rows = size.expired;
+        if size.rows > 0 {
+            report.bytes = (size.bytes as f64 * size.expired as f64 / size.rows as f64) as u64;
+        }
+        return Ok(report);
+    }
+
+    let partitions: Vec<PartitionStat> = ch_client
+        .query(
+            "SELECT partition, partition_id, sum(rows) AS rows, sum(bytes_on_disk) AS bytes, \
+            greatest(max(max_date), toDate(max(max_time))) AS max_date \
+            FROM system.parts \
+            WHERE database = currentDatabase() AND table = ? AND active \
+            GROUP BY partition, partition_id",
+        )
+        .bind(&rule.table)
+        .fetch_all()
+        .await?;
+    for partition in partitions {
+        // 分区键不是日期时无法判断分区是否过期
+        if partition.max_date == NaiveDate::default() || partition.max_date >= cutoff {
+            continue;
+        }
+        let sql = format!(
+            "ALTER TABLE {} DROP PARTITION ID '{}'",
+            rule.table, partition.partition_id
+        );
+        ch_client.query(&sql).execute().await?;
+        report.partitions.push(partition.partition);
+        report.rows += partition.rows;
+        report.bytes += partition.bytes;
     }
+    Ok(report)
 }
 
-/// 定时清除a股实时行情数据
-async fn cleanup_astock_realtime_data(_ch_client: &clickhouse::Client) -> anyhow::Result<()> {
-    // let sql = r#"
-    //     ALTER TABLE akshare.astock_realtime_data
-    //     DELETE WHERE date < toDate(now() - INTERVAL 2 DAY)
-    // "#;
-    // ch_client.query(sql).execute().await?;
-    Ok(())
+/// 早于返回日期的数据已经过期
+fn retention_cutoff(rule: &RetentionConfig, today: NaiveDate) -> anyhow::Result<NaiveDate> {
+    let cutoff = match rule.unit {
+        RetentionUnit::Day => today.checked_sub_days(Days::new(rule.keep as u64)),
+        RetentionUnit::Month => today.checked_sub_months(Months::new(rule.keep)),
+        RetentionUnit::Year => today.checked_sub_months(Months::new(rule.keep * 12)),
+    };
+    cutoff.ok_or_else(|| anyhow::anyhow!("invalid retention keep = {}", rule.keep))
+}
+
+/// 规则对应的TTL表达式，与clickhouse格式化之后的写法保持一致，用于判断TTL是否需要更新
+fn ttl_expr(rule: &RetentionConfig) -> String {
+    let interval = match rule.unit {
+        RetentionUnit::Day => "toIntervalDay",
+        RetentionUnit::Month => "toIntervalMonth",
+        RetentionUnit::Year => "toIntervalYear",
+    };
+    format!("toDateTime({}) + {}({})", rule.column, interval, rule.keep)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_retention_cutoff() {
+        let rule = |keep, unit| RetentionConfig {
+            table: "alarm_hist".to_owned(),
+            column: "event_time".to_owned(),
+            keep,
+            unit,
+        };
+        let today = NaiveDate::from_ymd_opt(2025, 3, 31).unwrap();
+
+        assert_eq!(
+            retention_cutoff(&rule(2, RetentionUnit::Day), today).unwrap(),
+            NaiveDate::from_ymd_opt(2025, 3, 29).unwrap()
+        );
+        assert_eq!(
+            retention_cutoff(&rule(1, RetentionUnit::Month), today).unwrap(),
+            NaiveDate::from_ymd_opt(2025, 2, 28).unwrap()
+        );
+        assert_eq!(
+            retention_cutoff(&rule(3, RetentionUnit::Year), today).unwrap(),
+            NaiveDate::from_ymd_opt(2022, 3, 31).unwrap()
+        );
+        assert_eq!(
+            ttl_expr(&rule(6, RetentionUnit::Month)),
+            "toDateTime(event_time) + toIntervalMonth(6)"
+        );
+    }
 }
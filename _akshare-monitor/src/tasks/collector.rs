@@ -0,0 +1,529 @@
+//! 声明式的数据收集框架，一次数据收集由下面三个阶段组成：
+//!
+//! - fetch: 对[`Collector::params`]展开得到的每一个参数请求一次aktools接口，共享重试以及并发设置，
+//!   所有请求都会经过全局的aktools限流器，
+//!   按日期更新的数据可以通过[`CollectContext::latest_dates`]只请求缺失的日期区间
+//! - transform: 将接口返回的每一条数据转换为clickhouse之中的一行
+//! - sink: 按照[`Sink`]将数据写入clickhouse
+//!
+//! 追加写入的任务之中单个参数请求失败时会被记录到死信队列(见[`super::dead_letter`])，其余数据照常写入，
+//! 接口返回的数据逐条反序列化，无法反序列化的数据会被隔离到`schema_drift`表之中(见[`super::schema_drift`])
+//!
+//! 新增一个aktools接口只需要实现[`Collector`]，之后通过[`CollectorTask`]加入调度器即可
+use std::{collections::HashMap, future::Future, pin::Pin, sync::Arc, time::Duration};
+
+use chrono::{DateTime, Days, NaiveDate, Utc};
+use clickhouse::Row;
+use data_mind::{limiter::aktools_get, utils::config_backoff};
+use futures::{StreamExt, TryStreamExt, stream};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_json::Value;
+use strum::IntoEnumIterator;
+use tokio_util::sync::CancellationToken;
+
+use super::{
+    dead_letter::{self, DeadLetter, DeadLetterRetry},
+    schema_drift,
+};
+use crate::{
+    config::{INIT_CONFIG, MonitorConfig},
+    init::ExternalResource,
+    scheduler::{SCHEDULE_TASK_MANAGER, Schedulable, TaskMeta},
+};
+
+/// 一个数据收集任务的声明式定义
+pub trait Collector: Send + Sync + 'static {
+    /// 每一次接口请求携带的参数，例如股票代码、日期、资金流向等，
+    /// 请求失败时会被序列化之后写入死信队列，重试时再反序列化
+    type Param: Serialize + DeserializeOwned + Send + Sync + 'static;
+    /// aktools接口返回的单条数据
+    type Api: DeserializeOwned + Send + 'static;
+    /// 写入clickhouse的单行数据
+    type Row: clickhouse::Row + Serialize + Send + Sync + 'static;
+
+    /// 调度任务相关描述性字段
+    fn meta(&self) -> TaskMeta;
+    /// aktools接口路径，例如`/stock_hsgt_hist_em`
+    fn path(&self) -> &'static str;
+    /// 写入的clickhouse表名
+    fn table(&self) -> &'static str;
+    /// 本次收集需要请求的所有参数，每一个参数对应一次接口请求
+    fn params(
+        &self,
+        ctx: &CollectContext<'_>,
+    ) -> impl Future<Output = anyhow::Result<Vec<Self::Param>>> + Send;
+    /// 将接口返回的一条数据转换为clickhouse之中的一行，返回`None`代表丢弃该条数据，
+    /// `ts`为本次收集开始的时间
+    fn transform(
+        &self,
+        param: &Self::Param,
+        value: Self::Api,
+        ts: DateTime<Utc>,
+    ) -> Option<Self::Row>;
+
+    /// 将参数转换为接口请求的query，默认不携带任何query
+    fn query(&self, _param: &Self::Param) -> Vec<(&'static str, String)> {
+        Vec::new()
+    }
+
+    /// 数据写入方式
+    fn sink(&self) -> Sink {
+        Sink::Append
+    }
+
+    /// 重试、并发以及分批设置
+    fn settings(&self) -> CollectSettings {
+        CollectSettings::default()
+    }
+
+    /// 本次调度是否需要收集数据，例如实时行情只在交易时间之内收集，只在调度执行时检查
+    fn should_collect(&self, _now: DateTime<Utc>) -> bool {
+        true
+    }
+}
+
+/// 数据写入clickhouse的方式
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Sink {
+    /// 直接追加写入
+    Append,
+    /// 所有批次的数据都请求成功之后写入临时表格再原子地替换原表格，用于每次返回全量数据的接口
+    Replace,
+}
+
+/// 一次数据收集共享的重试、并发以及分批设置
+#[derive(Debug, Clone)]
+pub struct CollectSettings {
+    /// 单次重试的最大间隔(秒)
+    pub retry_max_interval: u64,
+    /// 单个参数总的最大重试时间(秒)
+    pub retry_max_elapsed: u64,
+    /// 同时进行的接口请求数量
+    pub concurrency: usize,
+    /// 每一批请求的参数数量，每一批请求完成之后写入一次clickhouse，`None`代表所有参数作为一批
+    pub batch_size: Option<usize>,
+    /// 两批请求之间的间隔，避免触发上游接口的限流
+    pub batch_pause: Duration,
+    /// 增量收集时从已有数据的最新日期往前重新收集的天数，只对按日期增量收集的任务生效
+    pub overlap_days: u32,
+}
+
+impl Default for CollectSettings {
+    fn default() -> Self {
+        Self {
+            retry_max_interval: 5,
+            retry_max_elapsed: 30,
+            concurrency: 8,
+            batch_size: None,
+            batch_pause: Duration::ZERO,
+            overlap_days: 3,
+        }
+    }
+}
+
+/// 展开本次收集的参数时可以使用的上下文
+pub struct CollectContext<'a> {
+    pub ext_res: &'a ExternalResource,
+    /// 实际写入的clickhouse表名，可能被配置文件覆盖
+    pub table: &'a str,
+    pub settings: &'a CollectSettings,
+}
+
+/// 一个代码已有数据的最新日期
+#[derive(Debug, Row, Deserialize)]
+struct LatestDate {
+    code: String,
+    #[serde(with = "clickhouse::serde::chrono::date")]
+    date: NaiveDate,
+}
+
+impl CollectContext<'_> {
+    /// 查询写入表之中每个代码已有数据的最新日期，`filter`为额外的WHERE条件，例如只查询某一种复权方式
+    pub async fn latest_dates(
+        &self,
+        filter: Option<&str>,
+    ) -> anyhow::Result<HashMap<String, NaiveDate>> {
+        let sql = format!(
+            "SELECT code, max(date) AS date FROM {} WHERE {} GROUP BY code",
+            self.table,
+            filter.unwrap_or("1")
+        );
+        let latest: Vec<LatestDate> = self.ext_res.ch_client.query(&sql).fetch_all().await?;
+
+        Ok(latest
+            .into_iter()
+            .map(|latest| (latest.code, latest.date))
+            .collect())
+    }
+
+    /// 增量收集的开始日期，从已有数据的最新日期往前`overlap_days`天
+    pub fn incremental_start(&self, latest: NaiveDate) -> NaiveDate {
+        latest - Days::new(self.settings.overlap_days as u64)
+    }
+}
+
+/// 只请求一次，不携带任何参数
+pub fn single() -> Vec<()> {
+    vec![()]
+}
+
+/// 枚举类型的所有取值，例如资金流向、复权方式
+pub fn variants<E: IntoEnumIterator>() -> Vec<E> {
+    E::iter().collect()
+}
+
+/// `[start, end]`之间的每一天
+pub fn dates(start: NaiveDate, end: NaiveDate) -> Vec<NaiveDate> {
+    start.iter_days().take_while(|date| *date <= end).collect()
+}
+
+/// 两组参数的笛卡尔积，例如股票代码 x 复权方式
+pub fn product<A: Clone, B: Clone>(left: Vec<A>, right: Vec<B>) -> Vec<(A, B)> {
+    left.into_iter()
+        .flat_map(|a| right.iter().map(move |b| (a.clone(), b.clone())))
+        .collect()
+}
+
+/// 按照配置文件之中同名的`[[monitors]]`配置将一个数据收集任务加入调度器，
+/// 没有对应配置时使用代码之中的默认设置，被配置停用的任务直接跳过
+pub async fn register<C: Collector + Clone>(
+    collector: C,
+    ext_res: &ExternalResource,
+) -> anyhow::Result<()> {
+    let name = collector.meta().name;
+    if INIT_CONFIG
+        .monitor(&name)
+        .is_some_and(|config| !config.enabled)
+    {
+        ftlog::info!("[register] monitor {} is disabled by config", name);
+        return Ok(());
+    }
+
+    dead_letter::register_retry(
+        &name,
+        CollectorTask::configured(collector.clone(), ext_res.clone()),
+    );
+    SCHEDULE_TASK_MANAGER
+        .add_task(CollectorTask::configured(collector, ext_res.clone()))
+        .await?;
+    Ok(())
+}
+
+/// 将一个[`Collector`]包装为可以加入调度器的调度任务
+pub struct CollectorTask<C> {
+    collector: C,
+    ext_res: ExternalResource,
+    /// 写入的clickhouse表名，默认为[`Collector::table`]
+    table: String,
+    settings: CollectSettings,
+    /// 配置文件之中指定的cron表达式
+    cron_expr: Option<String>,
+}
+
+impl<C: Collector> CollectorTask<C> {
+    pub fn new(collector: C, ext_res: ExternalResource) -> Self {
+        Self {
+            table: collector.table().to_owned(),
+            settings: collector.settings(),
+            cron_expr: None,
+            collector,
+            ext_res,
+        }
+    }
+
+    /// 使用配置文件之中同名的`[[monitors]]`配置创建，没有对应配置时使用默认设置
+    pub fn configured(collector: C, ext_res: ExternalResource) -> Self {
+        let name = collector.meta().name;
+        let task = Self::new(collector, ext_res);
+        match INIT_CONFIG.monitor(&name) {
+            Some(config) => task.with_config(config),
+            None => task,
+        }
+    }
+
+    /// 使用配置文件之中的设置覆盖默认设置
+    pub fn with_config(mut self, config: &MonitorConfig) -> Self {
+        if let Some(cron_expr) = &config.cron_expr {
+            self.cron_expr = Some(cron_expr.clone());
+        }
+        if let Some(concurrency) = config.concurrency {
+            self.settings.concurrency = concurrency.max(1);
+        }
+        if let Some(retry_max_interval) = config.retry_max_interval {
+            self.settings.retry_max_interval = retry_max_interval;
+        }
+        if let Some(retry_max_elapsed) = config.retry_max_elapsed {
+            self.settings.retry_max_elapsed = retry_max_elapsed;
+        }
+        if let Some(overlap_days) = config.overlap_days {
+            self.settings.overlap_days = overlap_days;
+        }
+        if let Some(table) = &config.table {
+            self.table = table.clone();
+        }
+        self
+    }
+
+    /// 请求一个参数对应的接口数据并转换为clickhouse之中的行
+    pub(super) async fn fetch(
+        &self,
+        param: C::Param,
+        ts: DateTime<Utc>,
+    ) -> anyhow::Result<Vec<C::Row>> {
+        let query = self.collector.query(&param);
+        let backoff_s = config_backoff(
+            self.settings.retry_max_interval,
+            self.settings.retry_max_elapsed,
+        );
+        let values = backoff::future::retry(backoff_s, || async {
+            let values: Vec<Value> = aktools_get(&self.ext_res.http_client, self.collector.path())
+                .query(&query)
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await
+                .map_err(backoff::Error::Permanent)?;
+
+            Ok(values)
+        })
+        .await?;
+
+        // 逐条反序列化，无法反序列化的数据记录到schema_drift表之中，不影响其余数据
+        let params = serde_json::to_string(&param).unwrap_or_default();
+        let api_data = schema_drift::check_rows::<C::Api>(
+            &self.ext_res.ch_client,
+            &self.collector.meta().name,
+            self.collector.path(),
+            &params,
+            values,
+        )
+        .await?;
+
+        Ok(api_data
+            .into_iter()
+            .filter_map(|value| self.collector.transform(&param, value, ts))
+            .collect())
+    }
+
+    /// 将数据追加写入clickhouse，返回写入的行数
+    pub(super) async fn write(
+        &self,
+        rows: impl IntoIterator<Item = C::Row>,
+    ) -> anyhow::Result<u64> {
+        let mut inserter = self.ext_res.ch_client.inserter(&self.table)?;
+        for row in rows {
+            inserter.write(&row)?;
+        }
+        Ok(inserter.end().await?.rows)
+    }
+
+    /// 请求一批参数，单个参数失败时记录到死信队列，只有整批参数全部失败时才返回错误
+    async fn fetch_batch(
+        &self,
+        name: &str,
+        batch: Vec<C::Param>,
+        ts: DateTime<Utc>,
+    ) -> anyhow::Result<Vec<Vec<C::Row>>> {
+        let results: Vec<Result<Vec<C::Row>, DeadLetter>> = stream::iter(batch)
+            .map(|param| async move {
+                let code = self
+                    .collector
+                    .query(&param)
+                    .into_iter()
+                    .find(|(key, _)| *key == "symbol")
+                    .map(|(_, value)| value)
+                    .unwrap_or_default();
+                let params = serde_json::to_string(&param).unwrap_or_default();
+                self.fetch(param, ts)
+                    .await
+                    .map_err(|err| DeadLetter::new(name, self.collector.path(), code, params, &err))
+            })
+            .buffer_unordered(self.settings.concurrency)
+            .collect()
+            .await;
+
+        let total = results.len();
+        let mut rows = Vec::with_capacity(total);
+        let mut dead_letters = Vec::new();
+        for result in results {
+            match result {
+                Ok(value) => rows.push(value),
+                Err(dead_letter) => dead_letters.push(dead_letter),
+            }
+        }
+        if dead_letters.is_empty() {
+            return Ok(rows);
+        }
+        // 整批参数全部失败一般意味着aktools或者上游数据源整体不可用，此时直接让本次收集失败
+        if rows.is_empty() {
+            anyhow::bail!(
+                "all {} requests failed, error = {}",
+                total,
+                dead_letters[0].error()
+            );
+        }
+
+        ftlog::warn!(
+            "[{} collect] {}/{} requests failed, recorded to dead letter queue",
+            name,
+            dead_letters.len(),
+            total
+        );
+        // 死信写入失败时只记录日志，已经请求成功的数据依旧需要写入
+        let _ = dead_letter::record(&self.ext_res.ch_client, dead_letters)
+            .await
+            .inspect_err(|err| {
+                ftlog::error!("[{} collect] record dead letters error = {:?}", name, err)
+            });
+        Ok(rows)
+    }
+
+    /// 使用死信之中记录的参数重新请求一次并追加写入数据，返回写入的行数
+    async fn retry_dead_letter(&self, params: &str) -> anyhow::Result<u64> {
+        let param: C::Param = serde_json::from_str(params)?;
+        let rows = self.fetch(param, Utc::now()).await?;
+        self.write(rows).await
+    }
+
+    /// 执行一次完整的数据收集，返回写入的行数，每一批数据写入之后检查一次`cancel`，被取消时提前返回
+    pub async fn collect_data(&self, cancel: &CancellationToken) -> anyhow::Result<u64> {
+        let ts = Utc::now();
+        let name = self.collector.meta().name;
+        let settings = &self.settings;
+        let ctx = CollectContext {
+            ext_res: &self.ext_res,
+            table: &self.table,
+            settings,
+        };
+        let mut params = self.collector.params(&ctx).await?;
+        let batch_size = settings.batch_size.unwrap_or(params.len()).max(1);
+
+        // 全量替换的数据在所有批次都请求成功之后才替换表格，任何一批失败时表格保持原样
+        let mut replacement = Vec::new();
+        let mut rows_written = 0;
+        while !params.is_empty() {
+            let batch: Vec<C::Param> = params.drain(..batch_size.min(params.len())).collect();
+            match self.collector.sink() {
+                Sink::Append => {
+                    let rows = self.fetch_batch(&name, batch, ts).await?;
+                    rows_written += self.write(rows.into_iter().flatten()).await?;
+                }
+                // 全量替换的数据缺少任何一部分都不应该写入
+                Sink::Replace => {
+                    let rows: Vec<Vec<C::Row>> = stream::iter(batch)
+                        .map(|param| self.fetch(param, ts))
+                        .buffer_unordered(settings.concurrency)
+                        .try_collect()
+                        .await?;
+                    replacement.extend(rows.into_iter().flatten());
+                }
+            }
+
+            if params.is_empty() {
+                break;
+            }
+            ftlog::info!(
+                "[{} collect] batch complete, remain len = {}",
+                name,
+                params.len()
+            );
+            tokio::select! {
+                _ = tokio::time::sleep(settings.batch_pause) => {}
+                _ = cancel.cancelled() => {
+                    anyhow::bail!("{} collect cancelled, remain len = {}", name, params.len())
+                }
+            }
+        }
+
+        if self.collector.sink() == Sink::Replace {
+            rows_written += self.replace(replacement).await?;
+        }
+
+        Ok(rows_written)
+    }
+
+    /// 全量替换表格之中的数据：先写入`{table}_tmp`，成功之后通过`EXCHANGE TABLES`原子地替换，
+    /// 写入失败或者进程中途退出时读取方依旧能看到替换之前的数据
+    async fn replace(&self, rows: Vec<C::Row>) -> anyhow::Result<u64> {
+        let ch_client = &self.ext_res.ch_client;
+        let tmp_table = format!("{}_tmp", self.table);
+        ch_client
+            .query(&format!("DROP TABLE IF EXISTS {}", tmp_table))
+            .execute()
+            .await?;
+        ch_client
+            .query(&format!("CREATE TABLE {} AS {}", tmp_table, self.table))
+            .execute()
+            .await?;
+
+        let mut inserter = ch_client.inserter(&tmp_table)?;
+        for row in rows {
+            inserter.write(&row)?;
+        }
+        let rows_written = inserter.end().await?.rows;
+
+        ch_client
+            .query(&format!("EXCHANGE TABLES {} AND {}", tmp_table, self.table))
+            .execute()
+            .await?;
+        // 交换之后`{table}_tmp`之中是替换之前的数据
+        ch_client
+            .query(&format!("DROP TABLE IF EXISTS {}", tmp_table))
+            .execute()
+            .await?;
+        Ok(rows_written)
+    }
+}
+
+impl<C: Collector> DeadLetterRetry for CollectorTask<C> {
+    fn retry<'a>(
+        &'a self,
+        params: &'a str,
+    ) -> Pin<Box<dyn Future<Output = anyhow::Result<u64>> + Send + 'a>> {
+        Box::pin(self.retry_dead_letter(params))
+    }
+}
+
+impl<C: Collector> Schedulable for CollectorTask<C> {
+    fn gen_meta(&self) -> TaskMeta {
+        let mut meta = self.collector.meta();
+        if let Some(cron_expr) = &self.cron_expr {
+            meta.cron_expr = cron_expr.clone();
+        }
+        meta
+    }
+
+    fn pinned_cron(&self) -> bool {
+        self.cron_expr.is_some()
+    }
+
+    fn execute(
+        self: Arc<Self>,
+        cancel: CancellationToken,
+    ) -> Box<dyn Future<Output = anyhow::Result<u64>> + Send + 'static> {
+        Box::new(async move {
+            if !self.collector.should_collect(Utc::now()) {
+                return Ok(0);
+            }
+            self.collect_data(&cancel).await
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_fan_out() {
+        let start = NaiveDate::from_ymd_opt(2025, 1, 30).unwrap();
+        let end = NaiveDate::from_ymd_opt(2025, 2, 2).unwrap();
+        assert_eq!(dates(start, end).len(), 4);
+        assert!(dates(end, start).is_empty());
+
+        let pairs = product(vec!["000001", "600000"], vec![1, 2, 3]);
+        assert_eq!(pairs.len(), 6);
+        assert_eq!(pairs[3], ("600000", 1));
+    }
+}
@@ -0,0 +1,342 @@
+//! 数据收集的死信队列
+//!
+//! 一次数据收集之中单个参数(例如某一只股票)在重试之后依旧请求失败时，
+//! 不会让整批数据被丢弃，而是将该参数记录到clickhouse的`dead_letter`表之中，
+//! 之后由[`DeadLetterSweeper`]定期重试，重试`MAX_ATTEMPTS`次之后依旧失败的参数不再重试
+use std::{
+    collections::HashMap,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, LazyLock, Mutex},
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use clickhouse::Row;
+use futures::{StreamExt, stream};
+use serde::{Deserialize, Serialize};
+use serde_repr::{Deserialize_repr, Serialize_repr};
+use tokio_util::sync::CancellationToken;
+
+use crate::scheduler::{MisfirePolicy, OverlapPolicy, Schedulable, TaskMeta};
+
+const DEAD_LETTER_TABLE: &str = "dead_letter";
+/// 包括第一次失败在内的最大尝试次数
+const MAX_ATTEMPTS: u32 = 5;
+/// 每一次清扫最多重试的死信数量
+const SWEEP_LIMIT: u64 = 2000;
+/// 同时重试的死信数量
+const SWEEP_CONCURRENCY: usize = 4;
+
+/// HashMap<调度任务名称, 重试方式>
+static RETRY_HANDLERS: LazyLock<Mutex<HashMap<String, Arc<dyn DeadLetterRetry>>>> =
+    LazyLock::new(Default::default);
+
+/// 死信状态
+#[derive(Debug, Clone, Copy, PartialEq, Serialize_repr, Deserialize_repr)]
+#[repr(u8)]
+pub(super) enum DeadLetterStatus {
+    /// 等待重试
+    Pending,
+    /// 重试成功
+    Resolved,
+    /// 超过最大尝试次数，放弃重试
+    GaveUp,
+}
+
+/// 一个请求失败的参数，对应clickhouse之中的`dead_letter`表
+#[derive(Debug, Clone, Row, Serialize, Deserialize)]
+pub(super) struct DeadLetter {
+    /// 调度任务名称加上序列化之后的参数，同一个参数再次失败时与之前的记录合并
+    id: String,
+    task_name: String,
+    /// aktools接口路径
+    endpoint: String,
+    /// 参数之中的代码(`symbol`)，没有时为空字符串
+    code: String,
+    /// json序列化之后的参数
+    params: String,
+    /// 最近一次失败的错误链
+    error: String,
+    attempts: u32,
+    status: DeadLetterStatus,
+    #[serde(with = "clickhouse::serde::chrono::datetime64::millis")]
+    created_at: DateTime<Utc>,
+    #[serde(with = "clickhouse::serde::chrono::datetime64::millis")]
+    ts: DateTime<Utc>,
+}
+
+impl DeadLetter {
+    pub(super) fn new(
+        task_name: &str,
+        endpoint: &str,
+        code: String,
+        params: String,
+        error: &anyhow::Error,
+    ) -> Self {
+        let now = Utc::now();
+        Self {
+            id: format!("{}:{}", task_name, params),
+            task_name: task_name.to_owned(),
+            endpoint: endpoint.to_owned(),
+            code,
+            params,
+            error: format!("{:#}", error),
+            attempts: 1,
+            status: DeadLetterStatus::Pending,
+            created_at: now,
+            ts: now,
+        }
+    }
+
+    pub(super) fn error(&self) -> &str {
+        &self.error
+    }
+
+    /// 同一个参数在之后的数据收集之中再次失败时与之前的记录合并，
+    /// 尝试次数累加，保留第一次失败的时间，已经放弃重试的死信不会重新进入等待重试的状态
+    fn merge(mut self, existing: &DeadLetter) -> Self {
+        self.attempts = existing.attempts + 1;
+        self.created_at = existing.created_at;
+        if existing.status == DeadLetterStatus::GaveUp || self.attempts >= MAX_ATTEMPTS {
+            self.status = DeadLetterStatus::GaveUp;
+        }
+        self
+    }
+}
+
+/// 记录数据收集之中新产生的一组死信，已经存在的死信与之前的记录合并之后再写入
+pub(super) async fn record(
+    ch_client: &clickhouse::Client,
+    dead_letters: Vec<DeadLetter>,
+) -> anyhow::Result<()> {
+    if dead_letters.is_empty() {
+        return Ok(());
+    }
+    let ids: Vec<&str> = dead_letters
+        .iter()
+        .map(|dead_letter| dead_letter.id.as_str())
+        .collect();
+    let existing: HashMap<String, DeadLetter> = ch_client
+        .query(
+            "SELECT id, task_name, endpoint, code, params, error, attempts, status, created_at, ts \
+            FROM dead_letter FINAL \
+            WHERE id IN ?",
+        )
+        .bind(ids)
+        .fetch_all::<DeadLetter>()
+        .await?
+        .into_iter()
+        .map(|dead_letter| (dead_letter.id.clone(), dead_letter))
+        .collect();
+
+    let merged: Vec<DeadLetter> = dead_letters
+        .into_iter()
+        .map(|dead_letter| match existing.get(&dead_letter.id) {
+            Some(existing) => dead_letter.merge(existing),
+            None => dead_letter,
+        })
+        .collect();
+    save(ch_client, &merged).await
+}
+
+/// 写入一组死信
+pub(super) async fn save(
+    ch_client: &clickhouse::Client,
+    dead_letters: &[DeadLetter],
+) -> anyhow::Result<()> {
+    if dead_letters.is_empty() {
+        return Ok(());
+    }
+    let mut inserter = ch_client.inserter(DEAD_LETTER_TABLE)?;
+    for dead_letter in dead_letters {
+        inserter.write(dead_letter)?;
+    }
+    inserter.end().await?;
+    Ok(())
+}
+
+/// 使用死信之中记录的参数重新请求一次并写入数据
+pub(super) trait DeadLetterRetry: Send + Sync + 'static {
+    /// `params`为json序列化之后的参数，返回写入的行数
+    fn retry<'a>(
+        &'a self,
+        params: &'a str,
+    ) -> Pin<Box<dyn Future<Output = anyhow::Result<u64>> + Send + 'a>>;
+}
+
+/// 注册一个调度任务的重试方式，没有注册重试方式的死信不会被重试
+pub(super) fn register_retry(task_name: &str, handler: impl DeadLetterRetry) {
+    RETRY_HANDLERS
+        .lock()
+        .unwrap()
+        .insert(task_name.to_owned(), Arc::new(handler));
+}
+
+/// 定期重试死信队列之中等待重试的参数
+pub(super) struct DeadLetterSweeper {
+    ch_client: clickhouse::Client,
+}
+
+impl DeadLetterSweeper {
+    pub fn new(ch_client: clickhouse::Client) -> Self {
+        Self { ch_client }
+    }
+
+    /// 重试所有等待重试的死信，返回写入的行数，每一批死信重试完成之后检查一次`cancel`
+    async fn sweep(&self, cancel: &CancellationToken) -> anyhow::Result<u64> {
+        let pending: Vec<DeadLetter> = self
+            .ch_client
+            .query(
+                "SELECT id, task_name, endpoint, code, params, error, attempts, status, created_at, ts \
+                FROM dead_letter FINAL \
+                WHERE status = 'Pending' \
+                ORDER BY created_at \
+                LIMIT ?",
+            )
+            .bind(SWEEP_LIMIT)
+            .fetch_all()
+            .await?;
+        if pending.is_empty() {
+            return Ok(0);
+        }
+        ftlog::info!(
+            "[dead letter sweeper] {} dead letters pending",
+            pending.len()
+        );
+
+        let mut rows_written = 0;
+        for batch in pending.chunks(SWEEP_CONCURRENCY * 8) {
+            if cancel.is_cancelled() {
+                anyhow::bail!("dead letter sweep cancelled");
+            }
+            // 对应的任务被配置停用之后没有重试方式，保留该死信
+            let retryable: Vec<_> = {
+                let handlers = RETRY_HANDLERS.lock().unwrap();
+                batch
+                    .iter()
+                    .filter_map(|dead_letter| {
+                        let handler = handlers.get(&dead_letter.task_name)?.clone();
+                        Some(retry(handler, dead_letter.clone()))
+                    })
+                    .collect()
+            };
+            let retried: Vec<(DeadLetter, u64)> = stream::iter(retryable)
+                .buffer_unordered(SWEEP_CONCURRENCY)
+                .collect()
+                .await;
+
+            rows_written += retried.iter().map(|(_, rows)| rows).sum::<u64>();
+            let updated: Vec<DeadLetter> = retried.into_iter().map(|(letter, _)| letter).collect();
+            save(&self.ch_client, &updated).await?;
+        }
+
+        Ok(rows_written)
+    }
+}
+
+/// 重试一个死信，返回更新之后的死信以及写入的行数
+async fn retry(
+    handler: Arc<dyn DeadLetterRetry>,
+    mut dead_letter: DeadLetter,
+) -> (DeadLetter, u64) {
+    let result = handler.retry(&dead_letter.params).await;
+    dead_letter.ts = Utc::now();
+    match result {
+        Ok(rows) => {
+            dead_letter.status = DeadLetterStatus::Resolved;
+            (dead_letter, rows)
+        }
+        Err(err) => {
+            dead_letter.attempts += 1;
+            dead_letter.error = format!("{:#}", err);
+            if dead_letter.attempts >= MAX_ATTEMPTS {
+                dead_letter.status = DeadLetterStatus::GaveUp;
+                ftlog::error!(
+                    "[dead letter sweeper] give up {} after {} attempts, error = {}",
+                    dead_letter.id,
+                    dead_letter.attempts,
+                    dead_letter.error
+                );
+            }
+            (dead_letter, 0)
+        }
+    }
+}
+
+impl Schedulable for DeadLetterSweeper {
+    fn gen_meta(&self) -> TaskMeta {
+        TaskMeta {
+            name: "dead_letter_sweeper".to_owned(),
+            desc: "定期重试数据收集之中请求失败的参数，超过最大尝试次数之后放弃".to_owned(),
+            cron_expr: "0 20 * * * *".to_owned(),
+            // 多个实例同时部署时通过调度锁避免重复重试
+            tag: None,
+            overlap: OverlapPolicy::Skip,
+            timeout: Some(Duration::from_secs(50 * 60)),
+            trading_days_only: false,
+            misfire: MisfirePolicy::Ignore,
+        }
+    }
+
+    fn execute(
+        self: Arc<Self>,
+        cancel: CancellationToken,
+    ) -> Box<dyn Future<Output = anyhow::Result<u64>> + Send + 'static> {
+        Box::new(async move { self.sweep(&cancel).await })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_merge_dead_letter() {
+        let error = anyhow::anyhow!("request failed");
+        let first = DeadLetter::new(
+            "stock_zh_a_hist",
+            "/stock_zh_a_hist",
+            "603777".to_owned(),
+            "{}".to_owned(),
+            &error,
+        );
+
+        let again = DeadLetter::new(
+            "stock_zh_a_hist",
+            "/stock_zh_a_hist",
+            "603777".to_owned(),
+            "{}".to_owned(),
+            &error,
+        )
+        .merge(&first);
+        assert_eq!(again.attempts, 2);
+        assert_eq!(again.status, DeadLetterStatus::Pending);
+        assert_eq!(again.created_at, first.created_at);
+
+        let mut gave_up = again.clone();
+        gave_up.status = DeadLetterStatus::GaveUp;
+        let merged = DeadLetter::new(
+            "stock_zh_a_hist",
+            "/stock_zh_a_hist",
+            "603777".to_owned(),
+            "{}".to_owned(),
+            &error,
+        )
+        .merge(&gave_up);
+        assert_eq!(merged.attempts, 3);
+        assert_eq!(merged.status, DeadLetterStatus::GaveUp);
+
+        let mut nearly = again;
+        nearly.attempts = MAX_ATTEMPTS - 1;
+        let merged = DeadLetter::new(
+            "stock_zh_a_hist",
+            "/stock_zh_a_hist",
+            "603777".to_owned(),
+            "{}".to_owned(),
+            &error,
+        )
+        .merge(&nearly);
+        assert_eq!(merged.status, DeadLetterStatus::GaveUp);
+    }
+}
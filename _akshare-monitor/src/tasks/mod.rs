@@ -8,16 +8,28 @@ use chrono::{DateTime, Timelike, Utc};
 use reqwest::ClientBuilder;
 
 use crate::{
+    config::INIT_CONFIG,
     init::ExternalResource,
     scheduler::{CST, SCHEDULE_TASK_MANAGER},
+    trade_calendar::TRADE_CALENDAR,
 };
 
 mod a_index;
 mod a_stock;
+mod backfill;
 mod clean_up;
+mod collector;
+mod dead_letter;
 mod s_impls;
+mod schema_drift;
+mod sector;
+mod security_master;
+mod tool;
 mod utils;
 
+pub use backfill::{BACKFILL_MANAGER, BackfillJobShow, BackfillRequest};
+pub use schema_drift::{SchemaDriftSummary, drift_summary};
+
 #[cfg(test)]
 pub static TEST_CH_CLIENT: LazyLock<clickhouse::Client> = LazyLock::new(|| {
     clickhouse::Client::default()
@@ -45,10 +57,13 @@ const MORNING_END: u32 = 11 * 60 + 30; // 11:30
 const AFTERNOON_START: u32 = 13 * 60; // 13:00
 const AFTERNOON_END: u32 = 15 * 60; // 15:00
 
-/// 判断是否当前处于交易时间内
+/// 判断是否当前处于交易时间内，非交易日的任何时间都不属于交易时间
 fn in_trade_time(now: &DateTime<Utc>) -> bool {
     // 使用提供的时间或获取当前CST时间
     let dt = now.with_timezone(&CST);
+    if !TRADE_CALENDAR.is_trading_day(dt.date_naive()) {
+        return false;
+    }
     let hour = dt.hour();
     let minute = dt.minute();
     let current_minutes = hour * 60 + minute;
@@ -57,11 +72,43 @@ fn in_trade_time(now: &DateTime<Utc>) -> bool {
         || (current_minutes >= AFTERNOON_START && current_minutes < AFTERNOON_END)
 }
 
-pub async fn start_up_monitor_tasks(ext_res: ExternalResource) {
+pub async fn start_up_monitor_tasks(ext_res: ExternalResource) -> anyhow::Result<()> {
+    // 交易日历需要在其他数据收集任务之前完成加载
+    let trade_calendar_monitor = tool::ToolTradeDateHistSinaMonitor {
+        data_table: "tool_trade_date_hist_sina".to_owned(),
+        ext_res: ext_res.clone(),
+    };
+    trade_calendar_monitor.init_calendar().await;
+    SCHEDULE_TASK_MANAGER
+        .add_task(trade_calendar_monitor)
+        .await?;
+
+    // 证券主表是其他数据收集任务的代码范围，同样需要提前完成初始化
+    let security_master_monitor = security_master::SecurityMasterMonitor {
+        data_table: "security_master".to_owned(),
+        ext_res: ext_res.clone(),
+    };
+    security_master_monitor.init_master().await;
     SCHEDULE_TASK_MANAGER
-        .add_task(clean_up::CleanUp::new(ext_res.ch_client.clone()))
-        .await;
+        .add_task(security_master_monitor)
+        .await?;
+
+    SCHEDULE_TASK_MANAGER
+        .add_task(clean_up::CleanUp::new(
+            ext_res.ch_client.clone(),
+            INIT_CONFIG.retention.clone(),
+        ))
+        .await?;
+    SCHEDULE_TASK_MANAGER
+        .add_task(dead_letter::DeadLetterSweeper::new(
+            ext_res.ch_client.clone(),
+        ))
+        .await?;
+
+    a_stock::start_a_stock_tasks(ext_res.clone()).await?;
+    a_index::start_a_index_tasks(ext_res.clone()).await?;
+    sector::start_sector_tasks(ext_res.clone()).await?;
 
-    a_stock::start_a_stock_tasks(ext_res.clone()).await;
-    a_index::start_a_index_tasks(ext_res.clone()).await;
+    BACKFILL_MANAGER.init(ext_res).await?;
+    Ok(())
 }
@@ -0,0 +1,354 @@
+//! aktools返回数据的结构漂移检测
+//!
+//! `src/schema/akshare`之中的结构体按照中文字段名反序列化，aktools修改字段名之后整个接口的数据都无法反序列化。
+//! 数据收集时逐条反序列化接口返回的数据，无法反序列化的原始数据连同serde错误、
+//! 期望字段与实际字段的差异一起记录到clickhouse的`schema_drift`表之中，其余数据照常写入，
+//! 同时输出error日志，grafana通过[`drift_summary`]对应的查询告警。
+//! 每一个(任务, 接口)每天最多通过kafka发送一次告警，避免逐个代码请求的任务以及死信重试重复告警
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::{LazyLock, Mutex},
+};
+
+use chrono::{DateTime, NaiveDate, Utc};
+use clickhouse::Row;
+use serde::{
+    Deserialize, Deserializer, Serialize,
+    de::{self, DeserializeOwned, Visitor},
+    forward_to_deserialize_any,
+};
+use serde_json::Value;
+
+use data_mind::limiter::aktools_get;
+
+use crate::{alarm, init::ExternalResource, scheduler::CST};
+
+const SCHEMA_DRIFT_TABLE: &str = "schema_drift";
+/// 每一次接口请求最多记录的原始数据条数，字段改名时接口返回的所有数据都会失败，只需要保留少量样本
+const SAMPLES_PER_REQUEST: usize = 5;
+
+/// 每一个(任务名称, 接口路径)最近一次发送告警的日期
+static ALARMED: LazyLock<Mutex<HashMap<(String, String), NaiveDate>>> =
+    LazyLock::new(Default::default);
+
+/// 一条无法反序列化的接口数据，对应clickhouse之中的`schema_drift`表
+#[derive(Debug, Row, Serialize, Deserialize)]
+pub(super) struct SchemaDrift {
+    task_name: String,
+    /// aktools接口路径
+    endpoint: String,
+    /// json序列化之后的请求参数
+    params: String,
+    /// 原始数据
+    payload: String,
+    /// serde错误
+    error: String,
+    /// 期望存在但是没有出现的字段
+    missing_keys: Vec<String>,
+    /// 出现但是不在期望之中的字段
+    unexpected_keys: Vec<String>,
+    /// 本次请求之中无法反序列化的数据条数
+    failed_rows: u64,
+    /// 本次请求返回的数据条数
+    total_rows: u64,
+    #[serde(with = "clickhouse::serde::chrono::datetime64::millis")]
+    ts: DateTime<Utc>,
+}
+
+/// 逐条反序列化一次接口请求返回的数据，返回成功的数据以及无法反序列化的数据样本
+pub(super) fn deserialize_rows<T: DeserializeOwned>(
+    task_name: &str,
+    endpoint: &str,
+    params: &str,
+    values: Vec<Value>,
+) -> (Vec<T>, Vec<SchemaDrift>) {
+    let total_rows = values.len() as u64;
+    let expected = expected_keys::<T>();
+    let mut rows = Vec::with_capacity(values.len());
+    let mut drifts = Vec::new();
+    let mut failed_rows = 0;
+    for value in values {
+        match T::deserialize(&value) {
+            Ok(row) => rows.push(row),
+            Err(err) => {
+                failed_rows += 1;
+                if drifts.len() >= SAMPLES_PER_REQUEST {
+                    continue;
+                }
+                let (missing_keys, unexpected_keys) = diff_keys(&expected, &value);
+                drifts.push(SchemaDrift {
+                    task_name: task_name.to_owned(),
+                    endpoint: endpoint.to_owned(),
+                    params: params.to_owned(),
+                    payload: value.to_string(),
+                    error: err.to_string(),
+                    missing_keys,
+                    unexpected_keys,
+                    failed_rows: 0,
+                    total_rows,
+                    ts: Utc::now(),
+                });
+            }
+        }
+    }
+    for drift in drifts.iter_mut() {
+        drift.failed_rows = failed_rows;
+    }
+
+    (rows, drifts)
+}
+
+/// 请求一次aktools接口并逐条反序列化，用于不通过[`Collector`](super::collector::Collector)收集的任务，
+/// 例如证券主表以及交易日历
+pub(super) async fn fetch_rows<T: DeserializeOwned>(
+    ext_res: &ExternalResource,
+    task_name: &str,
+    path: &str,
+    query: &[(&str, &str)],
+) -> anyhow::Result<Vec<T>> {
+    let values: Vec<Value> = aktools_get(&ext_res.http_client, path)
+        .query(query)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    let params = serde_json::to_string(query).unwrap_or_default();
+    check_rows(&ext_res.ch_client, task_name, path, &params, values).await
+}
+
+/// 逐条反序列化一次接口请求返回的数据，无法反序列化的数据写入schema_drift表，
+/// 只有所有数据都无法反序列化时才返回错误
+pub(super) async fn check_rows<T: DeserializeOwned>(
+    ch_client: &clickhouse::Client,
+    task_name: &str,
+    endpoint: &str,
+    params: &str,
+    values: Vec<Value>,
+) -> anyhow::Result<Vec<T>> {
+    let total = values.len();
+    let (rows, drifts) = deserialize_rows::<T>(task_name, endpoint, params, values);
+    if drifts.is_empty() {
+        return Ok(rows);
+    }
+    let _ = quarantine(ch_client, &drifts).await.inspect_err(|err| {
+        ftlog::error!(
+            "[{} collect] quarantine schema drift error = {:?}",
+            task_name,
+            err
+        )
+    });
+    if rows.is_empty() {
+        anyhow::bail!(
+            "all {} rows of {} failed to deserialize, see schema_drift table",
+            total,
+            endpoint
+        );
+    }
+    Ok(rows)
+}
+
+/// 写入一组无法反序列化的数据样本，输出error日志并通过kafka发送告警
+pub(super) async fn quarantine(
+    ch_client: &clickhouse::Client,
+    drifts: &[SchemaDrift],
+) -> anyhow::Result<()> {
+    let Some(first) = drifts.first() else {
+        return Ok(());
+    };
+    ftlog::error!(
+        "[schema drift] {} {}/{} rows of {} failed to deserialize, missing = {:?}, unexpected = {:?}, error = {}",
+        first.task_name,
+        first.failed_rows,
+        first.total_rows,
+        first.endpoint,
+        first.missing_keys,
+        first.unexpected_keys,
+        first.error
+    );
+    let today = Utc::now().with_timezone(&CST).date_naive();
+    if first_alarm_today(&first.task_name, &first.endpoint, today) {
+        let _ = alarm::send_alarm(
+            drift_fingerprint(first),
+            format!("aktools接口结构漂移({})", first.endpoint),
+            vec![format!(
+                "task = {}, failed rows = {}/{}, missing = {:?}, unexpected = {:?}",
+                first.task_name,
+                first.failed_rows,
+                first.total_rows,
+                first.missing_keys,
+                first.unexpected_keys
+            )],
+            format!("{}返回的数据之中有{}条无法反序列化", first.endpoint, first.failed_rows),
+            format!(
+                "数据收集任务{}请求{}时返回的数据结构与期望不一致，无法反序列化的样本已经写入schema_drift表，serde错误: {}",
+                first.task_name, first.endpoint, first.error
+            ),
+        )
+        .await
+        .inspect_err(|err| ftlog::error!("[schema drift] send alarm error = {:?}", err));
+    }
+
+    let mut inserter = ch_client.inserter(SCHEMA_DRIFT_TABLE)?;
+    for drift in drifts {
+        inserter.write(drift)?;
+    }
+    inserter.end().await?;
+    Ok(())
+}
+
+/// 该(任务, 接口)在`today`是否还没有发送过告警，返回`true`时同时记录为已经告警
+fn first_alarm_today(task_name: &str, endpoint: &str, today: NaiveDate) -> bool {
+    let mut alarmed = ALARMED.lock().unwrap();
+    alarmed.insert((task_name.to_owned(), endpoint.to_owned()), today) != Some(today)
+}
+
+/// 告警的fingerprint，由接口路径以及排序之后的缺少字段、多出字段组成，同一种结构漂移始终相同
+fn drift_fingerprint(drift: &SchemaDrift) -> String {
+    let mut missing = drift.missing_keys.clone();
+    missing.sort();
+    let mut unexpected = drift.unexpected_keys.clone();
+    unexpected.sort();
+    format!(
+        "schema_drift:{}:missing={}:unexpected={}",
+        drift.endpoint,
+        missing.join(","),
+        unexpected.join(",")
+    )
+}
+
+/// 最近一段时间之内每个接口的结构漂移情况
+#[derive(Debug, Row, Serialize, Deserialize)]
+pub struct SchemaDriftSummary {
+    pub task_name: String,
+    pub endpoint: String,
+    /// 记录下来的数据样本条数
+    pub samples: u64,
+    pub missing_keys: Vec<String>,
+    pub unexpected_keys: Vec<String>,
+    /// 最近一次的serde错误
+    pub last_error: String,
+    #[serde(with = "clickhouse::serde::chrono::datetime64::millis")]
+    pub last_seen: DateTime<Utc>,
+}
+
+/// 查询最近`hours`小时之内每个接口的结构漂移情况，没有结构漂移时返回空列表
+pub async fn drift_summary(
+    ch_client: &clickhouse::Client,
+    hours: u32,
+) -> anyhow::Result<Vec<SchemaDriftSummary>> {
+    let summary = ch_client
+        .query(
+            "SELECT task_name, endpoint, count() AS samples, \
+            groupUniqArrayArray(missing_keys) AS missing_keys, \
+            groupUniqArrayArray(unexpected_keys) AS unexpected_keys, \
+            argMax(error, ts) AS last_error, max(ts) AS last_seen \
+            FROM schema_drift \
+            WHERE ts >= now() - toIntervalHour(?) \
+            GROUP BY task_name, endpoint \
+            ORDER BY last_seen DESC",
+        )
+        .bind(hours)
+        .fetch_all()
+        .await?;
+    Ok(summary)
+}
+
+/// 结构体反序列化时期望的字段名(包括rename以及alias)，不是结构体时返回空集合
+fn expected_keys<T: DeserializeOwned>() -> BTreeSet<&'static str> {
+    let mut fields = None;
+    let _ = T::deserialize(FieldsDeserializer {
+        fields: &mut fields,
+    });
+    fields.unwrap_or_default().iter().copied().collect()
+}
+
+/// 对比期望字段与一条原始数据之中实际出现的字段，返回(缺少的字段, 多出的字段)
+fn diff_keys(expected: &BTreeSet<&'static str>, value: &Value) -> (Vec<String>, Vec<String>) {
+    let Some(object) = value.as_object() else {
+        return (Vec::new(), Vec::new());
+    };
+    let missing = expected
+        .iter()
+        .filter(|key| !object.contains_key(**key))
+        .map(|key| key.to_string())
+        .collect();
+    let unexpected = object
+        .keys()
+        .filter(|key| !expected.contains(key.as_str()))
+        .cloned()
+        .collect();
+    (missing, unexpected)
+}
+
+/// 只用于获取结构体字段名的Deserializer，serde在反序列化结构体时会把所有字段名传给`deserialize_struct`
+struct FieldsDeserializer<'a> {
+    fields: &'a mut Option<&'static [&'static str]>,
+}
+
+impl<'de> Deserializer<'de> for FieldsDeserializer<'_> {
+    type Error = de::value::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Self::Error> {
+        Err(de::Error::custom("not a struct"))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        *self.fields = Some(fields);
+        Err(de::Error::custom("only collect fields"))
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map enum identifier ignored_any
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use data_mind::schema::akshare::AkStockZhIndexDaily;
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_deserialize_rows() {
+        let values = vec![
+            json!({"date": "2025-01-02", "open": 1.0, "high": 2.0, "low": 0.5, "close": 1.5, "volume": 100.0}),
+            json!({"日期": "2025-01-03", "open": 1.0, "high": 2.0, "low": 0.5, "close": 1.5, "volume": 100.0}),
+        ];
+        let (rows, drifts) = deserialize_rows::<AkStockZhIndexDaily>(
+            "stock_zh_index_daily",
+            "/stock_zh_index_daily_em",
+            "{}",
+            values,
+        );
+
+        assert_eq!(rows.len(), 1);
+        assert_eq!(drifts.len(), 1);
+        assert_eq!(drifts[0].failed_rows, 1);
+        assert_eq!(drifts[0].total_rows, 2);
+        assert_eq!(drifts[0].missing_keys, vec!["date".to_owned()]);
+        assert_eq!(drifts[0].unexpected_keys, vec!["日期".to_owned()]);
+        assert_eq!(
+            drift_fingerprint(&drifts[0]),
+            "schema_drift:/stock_zh_index_daily_em:missing=date:unexpected=日期"
+        );
+    }
+
+    #[test]
+    fn test_first_alarm_today() {
+        let today = NaiveDate::from_ymd_opt(2025, 1, 2).unwrap();
+        let tomorrow = NaiveDate::from_ymd_opt(2025, 1, 3).unwrap();
+        assert!(first_alarm_today("test_alarm", "/a", today));
+        assert!(!first_alarm_today("test_alarm", "/a", today));
+        assert!(first_alarm_today("test_alarm", "/b", today));
+        assert!(first_alarm_today("test_alarm", "/a", tomorrow));
+    }
+}
@@ -0,0 +1,294 @@
+//! 东方财富行业板块以及概念板块的数据收集任务，每一种任务对于行业板块以及概念板块各注册一个调度任务
+
+use std::time::Duration;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use clickhouse::Row;
+use data_mind::{
+    repository::akshare::{
+        BoardKind, StockBoardConsEmInsert, StockBoardEmInsert, StockBoardHistEmInsert,
+    },
+    schema::akshare::{AkStockBoardConsEm, AkStockBoardHistEm, AkStockBoardNameEm},
+};
+use serde::{Deserialize, Serialize};
+
+use super::collector::{self, CollectContext, CollectSettings, Collector};
+use crate::{
+    init::ExternalResource,
+    scheduler::{CST, MisfirePolicy, OverlapPolicy, ScheduleTaskType, TaskMeta},
+};
+
+pub(super) async fn start_sector_tasks(ext_res: ExternalResource) -> anyhow::Result<()> {
+    for kind in collector::variants::<BoardKind>() {
+        collector::register(StockBoardNameEmMonitor(kind), &ext_res).await?;
+        collector::register(StockBoardHistEmMonitor(kind), &ext_res).await?;
+        collector::register(StockBoardConsEmMonitor(kind), &ext_res).await?;
+    }
+    Ok(())
+}
+
+/// 一个板块的代码以及名称，板块相关的接口使用板块名称作为参数
+#[derive(Debug, Clone, Row, Serialize, Deserialize)]
+pub(super) struct Board {
+    code: String,
+    name: String,
+}
+
+/// 最近一次收集到的板块列表
+async fn latest_boards(
+    ch_client: &clickhouse::Client,
+    kind: BoardKind,
+) -> anyhow::Result<Vec<Board>> {
+    let kind = format!("{:?}", kind);
+    let boards: Vec<Board> = ch_client
+        .query(
+            "SELECT code, name FROM stock_board_em FINAL \
+            WHERE kind = ? AND date = (SELECT max(date) FROM stock_board_em WHERE kind = ?)",
+        )
+        .bind(&kind)
+        .bind(&kind)
+        .fetch_all()
+        .await?;
+    if boards.is_empty() {
+        anyhow::bail!("no {} board in stock_board_em", kind);
+    }
+    Ok(boards)
+}
+
+/// 收集东方财富-沪深京板块-行业板块/概念板块的板块列表以及收盘之后的行情
+#[derive(Clone)]
+pub(super) struct StockBoardNameEmMonitor(BoardKind);
+
+impl Collector for StockBoardNameEmMonitor {
+    type Param = ();
+    type Api = AkStockBoardNameEm;
+    type Row = StockBoardEmInsert;
+
+    fn meta(&self) -> TaskMeta {
+        TaskMeta {
+            name: format!("stock_board_{}_name_em", self.0.to_str()),
+            desc: format!(
+                "东方财富-沪深京板块-{}板块列表以及行情, 每个交易日收盘之后收集一次",
+                board_kind_desc(self.0)
+            ),
+            cron_expr: "0 40 15 * * MON-FRI".to_owned(),
+            tag: Some(ScheduleTaskType::AStock),
+            overlap: OverlapPolicy::Skip,
+            timeout: Some(Duration::from_secs(5 * 60)),
+            trading_days_only: true,
+            misfire: MisfirePolicy::RunOnce,
+        }
+    }
+
+    fn path(&self) -> &'static str {
+        match self.0 {
+            BoardKind::Industry => "/stock_board_industry_name_em",
+            BoardKind::Concept => "/stock_board_concept_name_em",
+        }
+    }
+
+    fn table(&self) -> &'static str {
+        "stock_board_em"
+    }
+
+    async fn params(&self, _ctx: &CollectContext<'_>) -> anyhow::Result<Vec<()>> {
+        Ok(collector::single())
+    }
+
+    fn transform(
+        &self,
+        _param: &(),
+        value: AkStockBoardNameEm,
+        ts: DateTime<Utc>,
+    ) -> Option<StockBoardEmInsert> {
+        let date = ts.with_timezone(&CST).date_naive();
+        Some(StockBoardEmInsert::from_with_kind(value, self.0, date, ts))
+    }
+}
+
+// ------------------------------------------------------------------------------------------
+
+/// 收集东方财富-沪深京板块-行业板块/概念板块的日频历史行情
+#[derive(Clone)]
+pub(super) struct StockBoardHistEmMonitor(BoardKind);
+
+/// 单次请求的参数，日期为 yyyymmdd 格式的时间字符串
+#[derive(Serialize, Deserialize)]
+pub(super) struct StockBoardHistEmParam {
+    board: Board,
+    start_date: String,
+    end_date: String,
+}
+
+impl Collector for StockBoardHistEmMonitor {
+    type Param = StockBoardHistEmParam;
+    type Api = AkStockBoardHistEm;
+    type Row = StockBoardHistEmInsert;
+
+    fn meta(&self) -> TaskMeta {
+        TaskMeta {
+            name: format!("stock_board_{}_hist_em", self.0.to_str()),
+            desc: format!(
+                "东方财富-沪深京板块-{}板块日频历史行情",
+                board_kind_desc(self.0)
+            ),
+            cron_expr: "0 10 17 * * MON-FRI".to_owned(),
+            tag: Some(ScheduleTaskType::AStock),
+            overlap: OverlapPolicy::Skip,
+            timeout: Some(Duration::from_secs(60 * 60)),
+            trading_days_only: true,
+            misfire: MisfirePolicy::RunOnce,
+        }
+    }
+
+    fn path(&self) -> &'static str {
+        match self.0 {
+            BoardKind::Industry => "/stock_board_industry_hist_em",
+            BoardKind::Concept => "/stock_board_concept_hist_em",
+        }
+    }
+
+    fn table(&self) -> &'static str {
+        "stock_board_hist_em"
+    }
+
+    /// 最近一次收集到的所有板块，从每个板块已有数据的最新日期(往前重叠若干天)开始增量收集，
+    /// 还没有任何数据的板块收集过去一年的数据
+    async fn params(&self, ctx: &CollectContext<'_>) -> anyhow::Result<Vec<StockBoardHistEmParam>> {
+        let boards = latest_boards(&ctx.ext_res.ch_client, self.0).await?;
+        let filter = format!("kind = '{:?}'", self.0);
+        let latest_dates = ctx.latest_dates(Some(&filter)).await?;
+        let today = Utc::now().with_timezone(&CST).date_naive();
+        let window_start = today - chrono::Duration::days(365);
+        let end_date = today.format("%Y%m%d").to_string();
+
+        Ok(boards
+            .into_iter()
+            .map(|board| {
+                let start_date: NaiveDate = latest_dates
+                    .get(&board.code)
+                    .map(|latest| ctx.incremental_start(*latest).min(today))
+                    .unwrap_or(window_start);
+                StockBoardHistEmParam {
+                    board,
+                    start_date: start_date.format("%Y%m%d").to_string(),
+                    end_date: end_date.clone(),
+                }
+            })
+            .collect())
+    }
+
+    fn query(&self, param: &StockBoardHistEmParam) -> Vec<(&'static str, String)> {
+        vec![
+            ("symbol", param.board.name.clone()),
+            ("period", self.0.daily_period().to_owned()),
+            ("start_date", param.start_date.clone()),
+            ("end_date", param.end_date.clone()),
+            ("adjust", String::new()),
+        ]
+    }
+
+    fn transform(
+        &self,
+        param: &StockBoardHistEmParam,
+        value: AkStockBoardHistEm,
+        ts: DateTime<Utc>,
+    ) -> Option<StockBoardHistEmInsert> {
+        StockBoardHistEmInsert::from_with_board(
+            value,
+            self.0,
+            &param.board.code,
+            &param.board.name,
+            ts,
+        )
+    }
+
+    fn settings(&self) -> CollectSettings {
+        CollectSettings {
+            retry_max_interval: 30,
+            retry_max_elapsed: 120,
+            concurrency: 8,
+            ..Default::default()
+        }
+    }
+}
+
+// ------------------------------------------------------------------------------------------
+
+/// 收集东方财富-沪深京板块-行业板块/概念板块的成份股，用于记录成份股的调入以及调出
+#[derive(Clone)]
+pub(super) struct StockBoardConsEmMonitor(BoardKind);
+
+impl Collector for StockBoardConsEmMonitor {
+    type Param = Board;
+    type Api = AkStockBoardConsEm;
+    type Row = StockBoardConsEmInsert;
+
+    fn meta(&self) -> TaskMeta {
+        TaskMeta {
+            name: format!("stock_board_{}_cons_em", self.0.to_str()),
+            desc: format!(
+                "东方财富-沪深京板块-{}板块成份股, 记录成份股的调入以及调出",
+                board_kind_desc(self.0)
+            ),
+            cron_expr: "0 30 17 * * MON-FRI".to_owned(),
+            tag: Some(ScheduleTaskType::AStock),
+            overlap: OverlapPolicy::Skip,
+            timeout: Some(Duration::from_secs(60 * 60)),
+            trading_days_only: true,
+            misfire: MisfirePolicy::RunOnce,
+        }
+    }
+
+    fn path(&self) -> &'static str {
+        match self.0 {
+            BoardKind::Industry => "/stock_board_industry_cons_em",
+            BoardKind::Concept => "/stock_board_concept_cons_em",
+        }
+    }
+
+    fn table(&self) -> &'static str {
+        "stock_board_cons_em"
+    }
+
+    async fn params(&self, ctx: &CollectContext<'_>) -> anyhow::Result<Vec<Board>> {
+        latest_boards(&ctx.ext_res.ch_client, self.0).await
+    }
+
+    fn query(&self, param: &Board) -> Vec<(&'static str, String)> {
+        vec![("symbol", param.name.clone())]
+    }
+
+    fn transform(
+        &self,
+        param: &Board,
+        value: AkStockBoardConsEm,
+        ts: DateTime<Utc>,
+    ) -> Option<StockBoardConsEmInsert> {
+        let date = ts.with_timezone(&CST).date_naive();
+        Some(StockBoardConsEmInsert::from_with_board(
+            value,
+            self.0,
+            &param.code,
+            &param.name,
+            date,
+            ts,
+        ))
+    }
+
+    fn settings(&self) -> CollectSettings {
+        CollectSettings {
+            retry_max_interval: 30,
+            retry_max_elapsed: 120,
+            concurrency: 8,
+            ..Default::default()
+        }
+    }
+}
+
+fn board_kind_desc(kind: BoardKind) -> &'static str {
+    match kind {
+        BoardKind::Industry => "行业",
+        BoardKind::Concept => "概念",
+    }
+}
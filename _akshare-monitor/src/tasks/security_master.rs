@@ -0,0 +1,229 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use data_mind::{
+    repository::akshare::{ListingStatus, SecurityKind, SecurityMasterInsert},
+    schema::akshare::{
+        AkStockInfoBjNameCode, AkStockInfoShDelist, AkStockInfoShNameCode, AkStockInfoSzDelist,
+        AkStockInfoSzNameCode, AkStockZhIndexSpotSina,
+    },
+    security::{Exchange, SecurityCode},
+};
+
+use super::schema_drift::fetch_rows;
+use crate::init::ExternalResource;
+
+const TASK_NAME: &str = "security_master";
+
+/// 收集沪深京三个交易所的股票列表(包括终止上市的股票)以及新浪的指数列表，写入证券主表`security_master`，
+/// 所有数据收集任务的代码范围都来自这张表
+pub(super) struct SecurityMasterMonitor {
+    pub(super) data_table: String,
+    pub(super) ext_res: ExternalResource,
+}
+
+impl SecurityMasterMonitor {
+    pub async fn collect_data(&self) -> anyhow::Result<u64> {
+        let ts = Utc::now();
+        let ext_res = &self.ext_res;
+        let (sh_main, sh_star, sz, bj, sh_delist, sz_delist, indexes) = tokio::try_join!(
+            fetch_rows::<AkStockInfoShNameCode>(
+                ext_res,
+                TASK_NAME,
+                "/stock_info_sh_name_code",
+                &[("symbol", "主板A股")],
+            ),
+            fetch_rows::<AkStockInfoShNameCode>(
+                ext_res,
+                TASK_NAME,
+                "/stock_info_sh_name_code",
+                &[("symbol", "科创板")],
+            ),
+            fetch_rows::<AkStockInfoSzNameCode>(
+                ext_res,
+                TASK_NAME,
+                "/stock_info_sz_name_code",
+                &[("symbol", "A股列表")],
+            ),
+            fetch_rows::<AkStockInfoBjNameCode>(
+                ext_res,
+                TASK_NAME,
+                "/stock_info_bj_name_code",
+                &[],
+            ),
+            fetch_rows::<AkStockInfoShDelist>(
+                ext_res,
+                TASK_NAME,
+                "/stock_info_sh_delist",
+                &[("symbol", "全部")],
+            ),
+            fetch_rows::<AkStockInfoSzDelist>(
+                ext_res,
+                TASK_NAME,
+                "/stock_info_sz_delist",
+                &[("symbol", "终止上市公司")],
+            ),
+            fetch_rows::<AkStockZhIndexSpotSina>(
+                ext_res,
+                TASK_NAME,
+                "/stock_zh_index_spot_sina",
+                &[],
+            ),
+        )?;
+
+        // 任意一个交易所的上市列表为空时大概率是上游异常，此时不根据本次结果推断终止上市
+        let complete = [
+            sh_main.len(),
+            sh_star.len(),
+            sz.len(),
+            bj.len(),
+            indexes.len(),
+        ]
+        .iter()
+        .all(|len| *len > 0);
+
+        // 终止上市的股票先写入，同一个代码同时出现在上市列表之中时以上市列表为准
+        let mut rows: HashMap<String, SecurityMasterInsert> = HashMap::new();
+        let delisted = sh_delist
+            .into_iter()
+            .map(|value| (Exchange::Sh, value.code, value.name, value.list_date))
+            .chain(
+                sz_delist
+                    .into_iter()
+                    .map(|value| (Exchange::Sz, value.code, value.name, value.list_date)),
+            );
+        let listed = sh_main
+            .into_iter()
+            .chain(sh_star)
+            .map(|value| (Exchange::Sh, value.code, value.name, value.list_date))
+            .chain(
+                sz.into_iter()
+                    .map(|value| (Exchange::Sz, value.code, value.name, value.list_date)),
+            )
+            .chain(
+                bj.into_iter()
+                    .map(|value| (Exchange::Bj, value.code, value.name, value.list_date)),
+            );
+        for (status, (exchange, code, name, list_date)) in delisted
+            .map(|value| (ListingStatus::Delisted, value))
+            .chain(listed.map(|value| (ListingStatus::Listed, value)))
+        {
+            let Some(row) = stock_row(exchange, &code, name, &list_date, status, ts) else {
+                continue;
+            };
+            rows.insert(row.code.clone(), row);
+        }
+        for index in indexes {
+            let Ok(code) = index.code.parse::<SecurityCode>() else {
+                ftlog::warn!("[security master] skip index code {}", index.code);
+                continue;
+            };
+            let row = SecurityMasterInsert::new(
+                &code,
+                index.name,
+                SecurityKind::Index,
+                "",
+                ListingStatus::Listed,
+                ts,
+            );
+            rows.insert(row.code.clone(), row);
+        }
+
+        let mut inserter = self.ext_res.ch_client.inserter(&self.data_table)?;
+        for row in rows.values() {
+            inserter.write(row)?;
+        }
+        let written = inserter.end().await?.rows;
+        if complete {
+            self.delist_missing(ts).await?;
+        } else {
+            ftlog::warn!(
+                "[security master] some listed feeds are empty, skip delisting missing codes"
+            );
+        }
+        Ok(written)
+    }
+
+    /// 北交所没有终止上市列表，本次完整刷新之中没有出现的上市证券(`ts`早于本次刷新)一律标记为终止上市
+    async fn delist_missing(&self, ts: DateTime<Utc>) -> anyhow::Result<()> {
+        let sql = format!(
+            "INSERT INTO {table} (code, symbol, name, kind, exchange, board, list_date, status, ts) \
+            SELECT code, symbol, name, kind, exchange, board, list_date, 'Delisted', \
+            fromUnixTimestamp64Milli(toInt64(?)) \
+            FROM {table} FINAL \
+            WHERE status = 'Listed' AND ts < fromUnixTimestamp64Milli(toInt64(?))",
+            table = self.data_table
+        );
+        self.ext_res
+            .ch_client
+            .query(&sql)
+            .bind(ts.timestamp_millis())
+            .bind(ts.timestamp_millis())
+            .execute()
+            .await?;
+        Ok(())
+    }
+
+    /// 启动时证券主表之中还没有数据时收集一次，失败时只记录日志
+    pub async fn init_master(&self) {
+        let sql = format!("SELECT count() FROM {}", self.data_table);
+        let count = self
+            .ext_res
+            .ch_client
+            .query(&sql)
+            .fetch_one::<u64>()
+            .await
+            .inspect_err(|err| {
+                ftlog::error!("[init_master] count security master error = {:?}", err)
+            })
+            .unwrap_or_default();
+        if count > 0 {
+            return;
+        }
+
+        match self.collect_data().await {
+            Ok(rows) => ftlog::info!("[init_master] {} securities loaded", rows),
+            Err(err) => ftlog::error!("[init_master] seed security master error = {:?}", err),
+        }
+    }
+}
+
+/// 交易所股票列表之中的一条数据，代码无法解析时跳过
+fn stock_row(
+    exchange: Exchange,
+    code: &str,
+    name: String,
+    list_date: &str,
+    status: ListingStatus,
+    ts: DateTime<Utc>,
+) -> Option<SecurityMasterInsert> {
+    let code = SecurityCode::new(exchange, code.trim())
+        .inspect_err(|err| ftlog::warn!("[security master] skip stock code, error = {:?}", err))
+        .ok()?;
+    Some(SecurityMasterInsert::new(
+        &code,
+        name,
+        SecurityKind::Stock,
+        list_date,
+        status,
+        ts,
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::tasks::{TEST_CH_CLIENT, TEST_HTTP_CLIENT};
+
+    #[tokio::test]
+    async fn test_security_master_monitor() {
+        let monitor = SecurityMasterMonitor {
+            data_table: "security_master".to_owned(),
+            ext_res: ExternalResource {
+                ch_client: TEST_CH_CLIENT.clone(),
+                http_client: TEST_HTTP_CLIENT.clone(),
+            },
+        };
+        monitor.collect_data().await.unwrap();
+    }
+}
@@ -0,0 +1,77 @@
+use chrono::Utc;
+use data_mind::{
+    repository::akshare::ToolTradeDateHistSinaInsert, schema::akshare::AkToolTradeDateHistSina,
+};
+
+use super::schema_drift::fetch_rows;
+use crate::{init::ExternalResource, trade_calendar::TRADE_CALENDAR};
+
+/// 收集交易日历-新浪，写入clickhouse之后重新加载全局交易日历
+pub(super) struct ToolTradeDateHistSinaMonitor {
+    pub(super) data_table: String,
+    pub(super) ext_res: ExternalResource,
+}
+
+impl ToolTradeDateHistSinaMonitor {
+    pub async fn collect_data(&self) -> anyhow::Result<u64> {
+        let now = Utc::now();
+        let rows = fetch_rows::<AkToolTradeDateHistSina>(
+            &self.ext_res,
+            "tool_trade_date_hist_sina",
+            "/tool_trade_date_hist_sina",
+            &[],
+        )
+        .await?
+        .into_iter()
+        .map(|value| ToolTradeDateHistSinaInsert::from_with_ts(value, now))
+        .collect::<Result<Vec<_>, _>>()?;
+
+        let mut inserter = self.ext_res.ch_client.inserter(&self.data_table)?;
+        for row in rows {
+            inserter.write(&row)?;
+        }
+        let stats = inserter.end().await?;
+
+        TRADE_CALENDAR.load(&self.ext_res.ch_client).await?;
+        Ok(stats.rows)
+    }
+
+    /// 启动时加载交易日历，clickhouse之中还没有交易日数据时从aktools拉取一次，
+    /// 失败时只记录日志，此时交易日历会退化为按照工作日判断
+    pub async fn init_calendar(&self) {
+        let loaded = TRADE_CALENDAR
+            .load(&self.ext_res.ch_client)
+            .await
+            .inspect_err(|err| {
+                ftlog::error!("[init_calendar] load trade calendar error = {:?}", err)
+            })
+            .unwrap_or_default();
+        if loaded > 0 {
+            ftlog::info!("[init_calendar] {} trade days loaded", loaded);
+            return;
+        }
+
+        let _ = self.collect_data().await.inspect_err(|err| {
+            ftlog::error!("[init_calendar] seed trade calendar error = {:?}", err)
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::tasks::{TEST_CH_CLIENT, TEST_HTTP_CLIENT};
+
+    #[tokio::test]
+    async fn test_tool_trade_date_hist_sina_monitor() {
+        let monitor = ToolTradeDateHistSinaMonitor {
+            data_table: "tool_trade_date_hist_sina".to_owned(),
+            ext_res: ExternalResource {
+                ch_client: TEST_CH_CLIENT.clone(),
+                http_client: TEST_HTTP_CLIENT.clone(),
+            },
+        };
+        monitor.collect_data().await.unwrap();
+        assert!(!TRADE_CALENDAR.is_empty());
+    }
+}
@@ -1,15 +1,26 @@
-/// 从A股实时数据表之中获取所有的股票id
+use data_mind::repository::{akshare::SecurityKind, service::listed_security_codes};
+
+/// 从证券主表之中获取所有上市股票的6位代码
 pub async fn get_distinct_code(ch_client: &clickhouse::Client) -> anyhow::Result<Vec<String>> {
-    let res = ch_client
-        .query(
-            "SELECT DISTINCT code \
-            FROM astock_realtime_data \
-            WHERE is_suspended = false",
-        )
-        .fetch_all::<String>()
-        .await?;
+    let codes = listed_security_codes(ch_client, SecurityKind::Stock).await?;
+    if codes.is_empty() {
+        anyhow::bail!("no listed stock in security_master");
+    }
 
-    Ok(res)
+    Ok(codes
+        .into_iter()
+        .map(|code| code.symbol().to_owned())
+        .collect())
+}
+
+/// 从证券主表之中获取所有指数代码，使用新浪接口的写法，例如`sh000001`
+pub async fn get_index_codes(ch_client: &clickhouse::Client) -> anyhow::Result<Vec<String>> {
+    let codes = listed_security_codes(ch_client, SecurityKind::Index).await?;
+    if codes.is_empty() {
+        anyhow::bail!("no index in security_master");
+    }
+
+    Ok(codes.iter().map(|code| code.prefixed()).collect())
 }
 
 #[cfg(test)]
@@ -22,5 +33,7 @@ mod test {
     async fn test_distinct_code() {
         let res = get_distinct_code(&TEST_CH_CLIENT).await.unwrap();
         println!("{:?}", res);
+        let res = get_index_codes(&TEST_CH_CLIENT).await.unwrap();
+        println!("{:?}", res);
     }
 }
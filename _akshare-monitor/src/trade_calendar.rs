@@ -0,0 +1,115 @@
+//! A股交易日历，数据来源于aktools的`tool_trade_date_hist_sina`接口并持久化在clickhouse之中，
+//! 调度器以及数据收集任务通过它判断某一天是否为交易日
+#![allow(unused)]
+use std::{
+    collections::BTreeSet,
+    ops::Bound::{Excluded, Unbounded},
+    sync::{LazyLock, RwLock},
+};
+
+use chrono::{Datelike, NaiveDate};
+use data_mind::repository::akshare::ToolTradeDateHistSinaInsert;
+
+pub static TRADE_CALENDAR: LazyLock<TradeCalendar> = LazyLock::new(TradeCalendar::default);
+
+/// 交易日历，启动时以及每次拉取到新的交易日数据之后从clickhouse之中重新加载
+#[derive(Debug, Default)]
+pub struct TradeCalendar {
+    trade_days: RwLock<BTreeSet<NaiveDate>>,
+}
+
+impl TradeCalendar {
+    /// 从clickhouse之中重新加载所有交易日，返回加载的交易日数量
+    pub async fn load(&self, ch_client: &clickhouse::Client) -> anyhow::Result<usize> {
+        let rows: Vec<ToolTradeDateHistSinaInsert> = ch_client
+            .query("SELECT trade_date, ts FROM tool_trade_date_hist_sina FINAL")
+            .fetch_all()
+            .await?;
+        let trade_days = rows.into_iter().map(|row| row.trade_date).collect();
+        Ok(self.replace(trade_days))
+    }
+
+    /// 使用一组新的交易日替换当前日历，返回交易日数量
+    pub fn replace(&self, trade_days: BTreeSet<NaiveDate>) -> usize {
+        let len = trade_days.len();
+        *self.trade_days.write().unwrap() = trade_days;
+        len
+    }
+
+    /// 当前日历之中是否还没有任何交易日
+    pub fn is_empty(&self) -> bool {
+        self.trade_days.read().unwrap().is_empty()
+    }
+
+    /// 判断一个日期是否为交易日，日历没有覆盖到的日期(包括日历为空时)退化为按照周一到周五判断
+    pub fn is_trading_day(&self, date: NaiveDate) -> bool {
+        let trade_days = self.trade_days.read().unwrap();
+        match (trade_days.first(), trade_days.last()) {
+            (Some(first), Some(last)) if *first <= date && date <= *last => {
+                trade_days.contains(&date)
+            }
+            _ => date.weekday().number_from_monday() <= 5,
+        }
+    }
+
+    /// 获取某一日期之后(不包含该日期)的下一个交易日，超出日历覆盖范围时返回`None`
+    pub fn next_trading_day(&self, date: NaiveDate) -> Option<NaiveDate> {
+        self.trade_days
+            .read()
+            .unwrap()
+            .range((Excluded(date), Unbounded))
+            .next()
+            .copied()
+    }
+
+    /// 获取某一日期之前(不包含该日期)的上一个交易日，超出日历覆盖范围时返回`None`
+    pub fn prev_trading_day(&self, date: NaiveDate) -> Option<NaiveDate> {
+        self.trade_days
+            .read()
+            .unwrap()
+            .range(..date)
+            .next_back()
+            .copied()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn test_trade_calendar() {
+        let calendar = TradeCalendar::default();
+        // 日历为空时按照工作日判断
+        assert!(calendar.is_trading_day(date("2025-10-01")));
+        assert!(!calendar.is_trading_day(date("2025-10-04")));
+
+        // 2025年国庆节前后的交易日
+        calendar.replace(
+            ["2025-09-29", "2025-09-30", "2025-10-09", "2025-10-10"]
+                .into_iter()
+                .map(date)
+                .collect(),
+        );
+        assert!(calendar.is_trading_day(date("2025-09-30")));
+        assert!(!calendar.is_trading_day(date("2025-10-01")));
+        assert!(!calendar.is_trading_day(date("2025-10-08")));
+        // 超出日历覆盖范围的日期依旧按照工作日判断
+        assert!(calendar.is_trading_day(date("2025-10-13")));
+
+        assert_eq!(
+            calendar.next_trading_day(date("2025-09-30")),
+            Some(date("2025-10-09"))
+        );
+        assert_eq!(
+            calendar.prev_trading_day(date("2025-10-09")),
+            Some(date("2025-09-30"))
+        );
+        assert_eq!(calendar.next_trading_day(date("2025-10-10")), None);
+        assert_eq!(calendar.prev_trading_day(date("2025-09-29")), None);
+    }
+}
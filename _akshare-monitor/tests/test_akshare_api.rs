@@ -134,8 +134,12 @@ async fn test_stock_zh_index_spot_sina() {
 #[tokio::test]
 async fn test_stock_zh_index_daily() {
     let res = TEST_HTTP_CLIENT
-        .get(with_base_url("/stock_zh_index_daily"))
-        .query(&[("symbol", "sz399552")])
+        .get(with_base_url("/stock_zh_index_daily_em"))
+        .query(&[
+            ("symbol", "sz399552"),
+            ("start_date", "20250101"),
+            ("end_date", "20250430"),
+        ])
         .send()
         .await
         .unwrap()
@@ -146,7 +150,7 @@ async fn test_stock_zh_index_daily() {
     let values: Vec<Value> = serde_json::from_str(&res).unwrap();
     println!("res len = {}", values.len());
 
-    let mut file = File::create("../tmp/历史行情数据-新浪.json").unwrap();
+    let mut file = File::create("../tmp/指数历史行情数据-东方财富.json").unwrap();
     file.write_all(serde_json::to_string_pretty(&values).unwrap().as_bytes())
         .unwrap();
 }
@@ -1,5 +1,6 @@
 use clap::Parser;
 use config::{Environment, File};
+use data_mind::{limiter::LimiterConfig, upstream::UpstreamConfig};
 use serde::Deserialize;
 
 /// data-mind 网页服务器
@@ -30,6 +31,12 @@ pub struct ClickhouseConfig {
 pub struct InitConfig {
     pub server: ServerConfig,
     pub clickhouse: ClickhouseConfig,
+    /// aktools上游配置，没有配置时使用本地的aktools
+    #[serde(default)]
+    pub aktools: UpstreamConfig,
+    /// aktools请求限流器配置，没有配置时使用默认设置
+    #[serde(default)]
+    pub aktools_limiter: LimiterConfig,
 }
 
 impl InitConfig {
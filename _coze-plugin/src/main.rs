@@ -1,7 +1,11 @@
 use std::{net::Ipv4Addr, sync::Arc};
 
 use actix_web::{App, HttpServer, web::Data};
-use data_mind::handler::{a_stock, indicator, news};
+use data_mind::{
+    handler::{a_stock, aktools, indicator, news, sector},
+    limiter::AKTOOLS_LIMITER,
+    upstream::AKTOOLS_UPSTREAMS,
+};
 use ftlog::appender::{FileAppender, Period};
 use init::{init_ch_client, init_reqwest_client};
 use init_config::InitConfig;
@@ -41,6 +45,9 @@ async fn main() -> anyhow::Result<()> {
     // 初始化一些外部资源(init.rs) ------
     let ch_client = init_ch_client(&init_config);
     let reqwest_client = init_reqwest_client();
+    AKTOOLS_LIMITER.configure(init_config.aktools_limiter.clone());
+    AKTOOLS_UPSTREAMS.configure(init_config.aktools.clone())?;
+    AKTOOLS_UPSTREAMS.spawn_health_check();
 
     // openapi 挂载点 -----
     #[derive(OpenApi)]
@@ -48,7 +55,9 @@ async fn main() -> anyhow::Result<()> {
         tags(
             (name = indicator::API_TAG, description = indicator::API_DESC),
             (name = news::API_TAG, description = news::API_DESC),
-            (name = a_stock::API_TAG, description = a_stock::API_DESC)
+            (name = a_stock::API_TAG, description = a_stock::API_DESC),
+            (name = aktools::API_TAG, description = aktools::API_DESC),
+            (name = sector::API_TAG, description = sector::API_DESC)
         ),
         servers(
             (url = "http://localhost:9090", description = "本地测试环境"),
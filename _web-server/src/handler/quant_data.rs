@@ -1,6 +1,6 @@
 use data_mind::handler::{
-    a_index::mount_aindex_scope, a_stock::mount_astock_scope,
-    indicator::mount_tech_indicator_scope, news::mount_news_scope,
+    a_index::mount_aindex_scope, a_stock::mount_astock_scope, aktools::mount_aktools_scope,
+    indicator::mount_tech_indicator_scope, news::mount_news_scope, sector::mount_sector_scope,
 };
 use utoipa_actix_web::service_config::ServiceConfig;
 
@@ -11,5 +11,7 @@ pub fn config() -> impl FnOnce(&mut ServiceConfig) {
         mount_news_scope(config);
         mount_astock_scope(config);
         mount_aindex_scope(config);
+        mount_aktools_scope(config);
+        mount_sector_scope(config);
     }
 }
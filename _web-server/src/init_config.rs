@@ -1,5 +1,6 @@
 use clap::{command, Parser};
 use config::{Environment, File};
+use data_mind::{limiter::LimiterConfig, upstream::UpstreamConfig};
 use serde::Deserialize;
 
 /// data-mind 网页服务器
@@ -18,6 +19,12 @@ pub struct InitConfig {
     pub clickhouse: ClickhouseConfig,
     pub jwt_secret_key: String,
     pub github: GithubConfig,
+    /// aktools上游配置，没有配置时使用本地的aktools
+    #[serde(default)]
+    pub aktools: UpstreamConfig,
+    /// aktools请求限流器配置，没有配置时使用默认设置
+    #[serde(default)]
+    pub aktools_limiter: LimiterConfig,
 }
 
 #[derive(Debug, Deserialize)]
@@ -4,7 +4,11 @@ use actix_web::{
     web::Data,
     App, HttpServer,
 };
-use data_mind::handler::{a_stock, indicator, news};
+use data_mind::{
+    handler::{a_stock, aktools, indicator, news, sector},
+    limiter::AKTOOLS_LIMITER,
+    upstream::AKTOOLS_UPSTREAMS,
+};
 use ftlog::appender::{FileAppender, Period};
 use handler::{auth::jwt_mw::JwtAuthGuard, *};
 use init::DbClients;
@@ -55,6 +59,11 @@ async fn main() {
     let github_state = background::github_state::GithubStateCache::begin_processing();
     // init reqwest client
     let reqwest_client = init::init_reqwest_client();
+    AKTOOLS_LIMITER.configure(app_config.aktools_limiter.clone());
+    AKTOOLS_UPSTREAMS
+        .configure(app_config.aktools.clone())
+        .expect("invalid aktools upstream config");
+    AKTOOLS_UPSTREAMS.spawn_health_check();
     // ----------------------------------------- done
 
     #[derive(OpenApi)]
@@ -63,7 +72,9 @@ async fn main() {
             (name = auth::API_TAG, description = auth::API_DESC),
             (name = indicator::API_TAG, description = indicator::API_DESC),
             (name = news::API_TAG, description = news::API_DESC),
-            (name = a_stock::API_TAG, description = a_stock::API_DESC)
+            (name = a_stock::API_TAG, description = a_stock::API_DESC),
+            (name = aktools::API_TAG, description = aktools::API_DESC),
+            (name = sector::API_TAG, description = sector::API_DESC)
         ),
         servers(
             (url = "http://localhost:8800", description = "本地测试环境"),
@@ -2,14 +2,16 @@ use crate::{
     repository::service::is_index_code_exists,
     schema::{
         common::OkRes,
-        error::{InternalServerSnafu, NotFoundSnafu, OrdinError},
+        error::{BadReqSnafu, InternalServerSnafu, NotFoundSnafu, OrdinError},
         service::serv_aindex,
     },
+    security::SecurityCode,
 };
 use actix_web::{
     get,
     web::{self, Json},
 };
+use chrono::{FixedOffset, NaiveDate, Utc};
 use serde::Deserialize;
 use snafu::ResultExt;
 use utoipa::IntoParams;
@@ -26,7 +28,8 @@ pub fn mount_aindex_scope(config: &mut ServiceConfig) {
             .service(stock_zh_index_daily_kline)
             .service(stock_zh_index_daily_mas)
             .service(stock_zh_index_daily_volume)
-            .service(stock_zh_index_daily_pagin),
+            .service(stock_zh_index_daily_pagin)
+            .service(constituents),
     );
 }
 
@@ -255,3 +258,61 @@ async fn stock_zh_index_daily_pagin(
     let res = OkRes::from_with_msg("成功分页获取对应交易日的交易信息".to_owned(), data);
     Ok(Json(res))
 }
+
+/// 指数成份股查询请求体
+#[derive(Debug, Deserialize, IntoParams)]
+struct ConstituentQuery {
+    /// 带有交易所编号的指数代码，目前收集上证50、沪深300、科创50、中证100、中证500、中证800、中证1000以及中证2000
+    #[param(example = "sh000300")]
+    index_code: String,
+    /// 查询日期，格式为YYYY-MM-DD，不填写时为今天
+    #[param(example = "2025-05-08")]
+    date: Option<NaiveDate>,
+}
+
+/// 获取指数在某一天的成份股以及权重，附带每只成份股在该日期之前(含)最近一个交易日的日频行情
+#[utoipa::path(
+    tag = API_TAG,
+    params(
+        ConstituentQuery
+    ),
+    responses(
+        (status = 200, description = "成功获取指数成份股以及权重", body = OkRes<Vec<serv_aindex::ServIndexConstituent>>),
+        (status = 400, description = "指数代码格式错误", body = OrdinError),
+        (status = 404, description = "指定的指数不存在或者在该日期之前没有成份股数据", body = OrdinError),
+        (status = 401, description = "没有访问权限", body = OrdinError),
+        (status = 500, description = "发生服务器内部错误", body = OrdinError),
+    )
+)]
+#[get("/constituents")]
+async fn constituents(
+    query: web::Query<ConstituentQuery>,
+    ch_client: web::Data<clickhouse::Client>,
+) -> Result<Json<OkRes<Vec<serv_aindex::ServIndexConstituent>>>, OrdinError> {
+    let index_code = query.index_code.parse::<SecurityCode>().map_err(|_| {
+        BadReqSnafu {
+            desc: format!("无法解析指数代码{}，例如sh000300", query.index_code),
+        }
+        .build()
+    })?;
+    is_index_code_exists(&ch_client, &query.index_code)
+        .await
+        .context(InternalServerSnafu)?
+        .then_some(())
+        .ok_or(NotFoundSnafu.build())?;
+
+    let date = query.date.unwrap_or_else(|| {
+        let cst = FixedOffset::east_opt(8 * 3600).unwrap();
+        Utc::now().with_timezone(&cst).date_naive()
+    });
+    let data =
+        serv_aindex::ServIndexConstituent::fetch_with_date(&ch_client, index_code.symbol(), date)
+            .await
+            .context(InternalServerSnafu)?;
+    (!data.is_empty())
+        .then_some(())
+        .ok_or(NotFoundSnafu.build())?;
+
+    let res = OkRes::from_with_msg("成功获取指数成份股以及权重".to_owned(), data);
+    Ok(Json(res))
+}
@@ -4,6 +4,7 @@ use actix_web::{
     get,
     web::{self, Data, Json},
 };
+use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
 use snafu::ResultExt;
 use utoipa::IntoParams;
@@ -15,8 +16,8 @@ use crate::{
         common::OkRes,
         error::{InternalServerSnafu, NotFoundSnafu, OrdinError},
         service::serv_astock::{
-            ServDailyIndicator, ServDailyKline, ServDailyTradingVolume, ServMALines,
-            ServStockIndividualInfoEm, ServStockZhAStEm,
+            IntradayPeriod, ServDailyIndicator, ServDailyKline, ServDailyTradingVolume,
+            ServIntradayBar, ServMALines, ServStockIndividualInfoEm, ServStockZhAStEm,
         },
     },
 };
@@ -31,7 +32,8 @@ pub fn mount_astock_scope(config: &mut ServiceConfig) {
             .service(fetch_mas_with_limit)
             .service(fetch_daily_kline)
             .service(fetch_daily_trading_volume)
-            .service(fetch_daily_indicator),
+            .service(fetch_daily_indicator)
+            .service(fetch_intraday_kline),
     );
 }
 
@@ -239,6 +241,52 @@ async fn fetch_daily_indicator(
     Ok(Json(res))
 }
 
+#[derive(Debug, Deserialize, IntoParams)]
+struct IntradayKlineQuery {
+    /// 需要获取分时K线的股票代码
+    #[param(example = "603777")]
+    stock_id: String,
+    /// K线周期(1分钟 = 1m、5分钟 = 5m)
+    #[param(example = "5m")]
+    period: IntradayPeriod,
+    /// 交易日期，格式为YYYY-MM-DD，不填写时返回该股票有数据的最近一个交易日
+    #[param(example = "2025-05-08")]
+    date: Option<NaiveDate>,
+}
+
+/// 获取对应`stock_id`的A股股票某一个交易日的分时K线数据，由交易时间之内每30秒一次的实时行情快照聚合得到
+#[utoipa::path(
+    tag = API_TAG,
+    params(
+        IntradayKlineQuery
+    ),
+    responses(
+        (status = 200, description = "获取分时K线数据成功", body = OkRes<Vec<ServIntradayBar>>),
+        (status = 404, description = "对应个股信息不存在", body = OrdinError),
+        (status = 401, description = "没有访问权限", body = OrdinError),
+        (status = 500, description = "发生服务器内部错误", body = OrdinError),
+    )
+)]
+#[get("/intraday_kline")]
+async fn fetch_intraday_kline(
+    query: web::Query<IntradayKlineQuery>,
+    ch_client: web::Data<clickhouse::Client>,
+) -> Result<Json<OkRes<Vec<ServIntradayBar>>>, OrdinError> {
+    is_stock_code_exists(&ch_client, &query.stock_id)
+        .await
+        .context(InternalServerSnafu)?
+        .then_some(())
+        .ok_or(NotFoundSnafu.build())?;
+
+    let data: Vec<ServIntradayBar> =
+        ServIntradayBar::fetch_with_date(&ch_client, query.period, &query.stock_id, query.date)
+            .await
+            .context(InternalServerSnafu)?;
+
+    let res = OkRes::from_with_msg("获取分时K线数据成功".to_owned(), data);
+    Ok(Json(res))
+}
+
 /// 单次返回当前交易日风险警示版的所有股票的行情数据
 #[utoipa::path(
     tag = API_TAG,
@@ -0,0 +1,52 @@
+use actix_web::{get, web::Json};
+use utoipa_actix_web::{scope, service_config::ServiceConfig};
+
+use crate::{
+    limiter::{LimiterStats, AKTOOLS_LIMITER},
+    schema::{common::OkRes, error::OrdinError},
+    upstream::{UpstreamStatus, AKTOOLS_UPSTREAMS},
+};
+
+pub const API_TAG: &str = "aktools状态";
+pub const API_DESC: &str =
+    "查看当前进程之中aktools上游健康状态以及请求限流器状态的接口集合";
+
+pub fn mount_aktools_scope(config: &mut ServiceConfig) {
+    config.service(
+        scope("/aktools")
+            .service(fetch_upstreams)
+            .service(fetch_limiter),
+    );
+}
+
+/// 获取所有aktools上游的健康状态
+#[utoipa::path(
+    tag = API_TAG,
+    responses(
+        (status = 200, description = "成功获取aktools上游健康状态", body = OkRes<Vec<UpstreamStatus>>),
+        (status = 401, description = "没有访问权限", body = OrdinError),
+    )
+)]
+#[get("/upstreams")]
+async fn fetch_upstreams() -> Json<OkRes<Vec<UpstreamStatus>>> {
+    Json(OkRes::from_with_msg(
+        "成功获取aktools上游健康状态".to_string(),
+        AKTOOLS_UPSTREAMS.status(),
+    ))
+}
+
+/// 获取aktools请求限流器的当前状态
+#[utoipa::path(
+    tag = API_TAG,
+    responses(
+        (status = 200, description = "成功获取aktools请求限流器状态", body = OkRes<LimiterStats>),
+        (status = 401, description = "没有访问权限", body = OrdinError),
+    )
+)]
+#[get("/limiter")]
+async fn fetch_limiter() -> Json<OkRes<LimiterStats>> {
+    Json(OkRes::from_with_msg(
+        "成功获取aktools请求限流器状态".to_string(),
+        AKTOOLS_LIMITER.stats(),
+    ))
+}
@@ -1,21 +1,25 @@
 //! a股相关技术指标的api handler
 
 use crate::{
-    repository::service::is_stock_code_exists,
+    repository::{akshare::FinancialPeriodType, service::is_stock_code_exists},
     schema::{
         akshare::{
-            AkStockFinancialAbstractThs, AkStockRankCxdThs, AkStockRankCxflThs, AkStockRankCxgThs,
-            AkStockRankCxslThs, AkStockRankLxszThs, AkStockRankLxxdThs,
+            AkStockRankCxdThs, AkStockRankCxflThs, AkStockRankCxgThs, AkStockRankCxslThs,
+            AkStockRankLxszThs, AkStockRankLxxdThs,
         },
         common::OkRes,
         error::{BadReqSnafu, InternalServerSnafu, NotFoundSnafu, OrdinError},
+        service::serv_astock::{FinancialMetric, ServFinancialAbstract, ServFinancialRank},
     },
 };
 use actix_web::{
     get,
     web::{self, Data, Json},
 };
+use chrono::NaiveDate;
+use serde::Deserialize;
 use snafu::ResultExt;
+use utoipa::IntoParams;
 use utoipa_actix_web::{scope, service_config::ServiceConfig};
 
 pub const API_TAG: &'static str = "技术指标";
@@ -25,6 +29,7 @@ pub fn mount_tech_indicator_scope(config: &mut ServiceConfig) {
     config.service(
         scope("/tech_indicator")
             .service(stock_financial_abstract_ths)
+            .service(stock_financial_abstract_ths_rank)
             .service(stock_rank_cxd_ths)
             .service(stock_rank_cxg_ths)
             .service(stock_rank_cxfl_ths)
@@ -34,7 +39,8 @@ pub fn mount_tech_indicator_scope(config: &mut ServiceConfig) {
     );
 }
 
-/// 同花顺-财务指标-主要指标  
+/// 同花顺-财务指标-主要指标，由每个季报披露截止日之后执行的`stock_financial_abstract_ths`任务收集，
+/// 金额单位为元，增长率、利润率、收益率以及资产负债率单位为%
 #[utoipa::path(
     tag = API_TAG,
     params(
@@ -42,7 +48,7 @@ pub fn mount_tech_indicator_scope(config: &mut ServiceConfig) {
         ("indicator", description = "获取数据时间范围指示器参数，example = '按报告期'; choice of {'按报告期', '按年度', '按单季度'}")
     ),
     responses(
-        (status = 200, description = "成功获取对应股票id的同花顺-财务指标-主要指标", body = OkRes<Vec<AkStockFinancialAbstractThs>>),
+        (status = 200, description = "成功获取对应股票id的同花顺-财务指标-主要指标", body = OkRes<Vec<ServFinancialAbstract>>),
         (status = 404, description = "指定的股票代码或报告期不存在", body = OrdinError),
         (status = 401, description = "没有访问权限", body = OrdinError),
         (status = 500, description = "发生服务器内部错误", body = OrdinError),
@@ -51,9 +57,8 @@ pub fn mount_tech_indicator_scope(config: &mut ServiceConfig) {
 #[get("/stock_financial_abstract_ths/{symbol_id}/{indicator}")]
 async fn stock_financial_abstract_ths(
     symbol_id: web::Path<(String, String)>,
-    reqwest_client: Data<reqwest::Client>,
     ch_client: Data<clickhouse::Client>,
-) -> Result<Json<OkRes<Vec<AkStockFinancialAbstractThs>>>, OrdinError> {
+) -> Result<Json<OkRes<Vec<ServFinancialAbstract>>>, OrdinError> {
     let (symbol_id, indicator) = symbol_id.into_inner();
     is_stock_code_exists(&ch_client, &symbol_id)
         .await
@@ -61,20 +66,10 @@ async fn stock_financial_abstract_ths(
         .then_some(())
         .ok_or(NotFoundSnafu.build())?;
 
-    (indicator == "按报告期" || indicator == "按年度" || indicator == "按单季度")
-        .then_some(())
-        .ok_or(
-            BadReqSnafu {
-                desc: "时间范围指示器参数indicator选择范围为{'按报告期', '按年度', '按单季度'}"
-                    .to_owned(),
-            }
-            .build(),
-        )?;
-
-    let data =
-        AkStockFinancialAbstractThs::from_astock_api(&reqwest_client, &symbol_id, &indicator)
-            .await
-            .context(InternalServerSnafu)?;
+    let period_type = parse_period_type(&indicator)?;
+    let data = ServFinancialAbstract::fetch_with_period(&ch_client, &symbol_id, period_type)
+        .await
+        .context(InternalServerSnafu)?;
 
     let res = OkRes::from_with_msg(
         "成功获取对应股票id的同花顺-财务指标-主要指标".to_owned(),
@@ -83,6 +78,70 @@ async fn stock_financial_abstract_ths(
     Ok(Json(res))
 }
 
+#[derive(Debug, Deserialize, IntoParams)]
+struct FinancialRankQuery {
+    /// 排名使用的财务指标
+    #[param(example = "net_profit_growth_rate")]
+    metric: FinancialMetric,
+    /// 报告期类型，choice of {'按报告期', '按年度', '按单季度'}，默认为按单季度
+    #[param(example = "按单季度")]
+    indicator: Option<String>,
+    /// 报告期末日期，格式为YYYY-MM-DD，不填写时使用已经收集到的最新报告期
+    #[param(example = "2025-03-31")]
+    report_date: Option<NaiveDate>,
+    /// 返回的股票数量，默认为50，最多500
+    #[param(example = 50)]
+    limit: Option<u32>,
+    /// 是否按照从低到高排序，默认从高到低
+    #[param(example = false)]
+    ascending: Option<bool>,
+}
+
+/// 同花顺-财务指标-主要指标的跨股票排名，例如本季度净利润同比增长率最高的50只股票
+#[utoipa::path(
+    tag = API_TAG,
+    params(
+        FinancialRankQuery
+    ),
+    responses(
+        (status = 200, description = "成功获取同花顺-财务指标-主要指标排名", body = OkRes<Vec<ServFinancialRank>>),
+        (status = 400, description = "报告期类型参数错误", body = OrdinError),
+        (status = 401, description = "没有访问权限", body = OrdinError),
+        (status = 500, description = "发生服务器内部错误", body = OrdinError),
+    )
+)]
+#[get("/stock_financial_abstract_ths_rank")]
+async fn stock_financial_abstract_ths_rank(
+    query: web::Query<FinancialRankQuery>,
+    ch_client: Data<clickhouse::Client>,
+) -> Result<Json<OkRes<Vec<ServFinancialRank>>>, OrdinError> {
+    let period_type = parse_period_type(query.indicator.as_deref().unwrap_or("按单季度"))?;
+    let data = ServFinancialRank::fetch_top(
+        &ch_client,
+        period_type,
+        query.metric,
+        query.report_date,
+        query.ascending.unwrap_or_default(),
+        query.limit.unwrap_or(50).min(500),
+    )
+    .await
+    .context(InternalServerSnafu)?;
+
+    let res = OkRes::from_with_msg("成功获取同花顺-财务指标-主要指标排名".to_owned(), data);
+    Ok(Json(res))
+}
+
+fn parse_period_type(indicator: &str) -> Result<FinancialPeriodType, OrdinError> {
+    let period_type = FinancialPeriodType::from_indicator(indicator).ok_or(
+        BadReqSnafu {
+            desc: "时间范围指示器参数indicator选择范围为{'按报告期', '按年度', '按单季度'}"
+                .to_owned(),
+        }
+        .build(),
+    )?;
+    Ok(period_type)
+}
+
 /// 同花顺-数据中心-技术选股-创新高
 #[utoipa::path(
     tag = API_TAG,
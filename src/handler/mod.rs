@@ -2,5 +2,7 @@
 
 pub mod a_index;
 pub mod a_stock;
+pub mod aktools;
 pub mod indicator;
 pub mod news;
+pub mod sector;
@@ -0,0 +1,218 @@
+//! 东方财富行业板块以及概念板块相关的api handler
+
+use crate::{
+    repository::service::is_stock_code_exists,
+    schema::{
+        common::OkRes,
+        error::{InternalServerSnafu, NotFoundSnafu, OrdinError},
+        service::serv_sector::{
+            BoardKind, ServBoardConsChange, ServBoardKline, ServBoardRank, ServStockBoard,
+        },
+    },
+};
+use actix_web::{
+    get,
+    web::{self, Data, Json},
+};
+use chrono::NaiveDate;
+use serde::Deserialize;
+use snafu::ResultExt;
+use utoipa::IntoParams;
+use utoipa_actix_web::{scope, service_config::ServiceConfig};
+
+pub const API_TAG: &str = "行业概念板块";
+pub const API_DESC: &str =
+    "从东方财富收集的行业板块以及概念板块数据，包括板块行情排名、板块K线、板块成份股变动以及股票所属的板块";
+
+pub fn mount_sector_scope(config: &mut ServiceConfig) {
+    config.service(
+        scope("/sector")
+            .service(board_rank)
+            .service(board_kline)
+            .service(board_cons_changes)
+            .service(stock_boards),
+    );
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+struct BoardRankQuery {
+    /// 板块种类(行业板块 = 0、概念板块 = 1)
+    #[param(example = 0)]
+    kind: BoardKind,
+    /// 交易日期，格式为YYYY-MM-DD，不填写时使用已经收集到的最近一个交易日
+    #[param(example = "2025-05-08")]
+    date: Option<NaiveDate>,
+    /// 返回的板块数量，默认为20，最多500
+    #[param(example = 20)]
+    limit: Option<u32>,
+    /// 是否按照涨跌幅从低到高排序，默认从高到低
+    #[param(example = false)]
+    ascending: Option<bool>,
+}
+
+/// 东方财富-沪深京板块-行业板块/概念板块某一个交易日收盘之后按照涨跌幅的排名
+#[utoipa::path(
+    tag = API_TAG,
+    params(
+        BoardRankQuery
+    ),
+    responses(
+        (status = 200, description = "成功获取板块涨跌幅排名", body = OkRes<Vec<ServBoardRank>>),
+        (status = 401, description = "没有访问权限", body = OrdinError),
+        (status = 500, description = "发生服务器内部错误", body = OrdinError),
+    )
+)]
+#[get("/board_rank")]
+async fn board_rank(
+    query: web::Query<BoardRankQuery>,
+    ch_client: Data<clickhouse::Client>,
+) -> Result<Json<OkRes<Vec<ServBoardRank>>>, OrdinError> {
+    let data = ServBoardRank::fetch_with_date(
+        &ch_client,
+        query.kind,
+        query.date,
+        query.ascending.unwrap_or_default(),
+        query.limit.unwrap_or(20).min(500),
+    )
+    .await
+    .context(InternalServerSnafu)?;
+
+    let res = OkRes::from_with_msg("成功获取板块涨跌幅排名".to_owned(), data);
+    Ok(Json(res))
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+struct BoardKlineQuery {
+    /// 板块种类(行业板块 = 0、概念板块 = 1)
+    #[param(example = 0)]
+    kind: BoardKind,
+    /// 板块代码
+    #[param(example = "BK1027")]
+    board_code: String,
+    /// 从今日开始的倒推时间范围
+    #[param(example = 30)]
+    limit_days: u32,
+}
+
+/// 东方财富-沪深京板块-行业板块/概念板块的日频K线
+#[utoipa::path(
+    tag = API_TAG,
+    params(
+        BoardKlineQuery
+    ),
+    responses(
+        (status = 200, description = "成功获取板块日频K线数据", body = OkRes<Vec<ServBoardKline>>),
+        (status = 404, description = "指定的板块代码不存在或者还没有收集到数据", body = OrdinError),
+        (status = 401, description = "没有访问权限", body = OrdinError),
+        (status = 500, description = "发生服务器内部错误", body = OrdinError),
+    )
+)]
+#[get("/board_kline")]
+async fn board_kline(
+    query: web::Query<BoardKlineQuery>,
+    ch_client: Data<clickhouse::Client>,
+) -> Result<Json<OkRes<Vec<ServBoardKline>>>, OrdinError> {
+    let data = ServBoardKline::fetch_with_limit(
+        &ch_client,
+        query.kind,
+        &query.board_code,
+        query.limit_days,
+    )
+    .await
+    .context(InternalServerSnafu)?;
+    (!data.is_empty())
+        .then_some(())
+        .ok_or(NotFoundSnafu.build())?;
+
+    let res = OkRes::from_with_msg("成功获取板块日频K线数据".to_owned(), data);
+    Ok(Json(res))
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+struct BoardConsChangesQuery {
+    /// 板块种类(行业板块 = 0、概念板块 = 1)
+    #[param(example = 0)]
+    kind: BoardKind,
+    /// 板块代码
+    #[param(example = "BK1027")]
+    board_code: String,
+    /// 从今日开始的倒推时间范围
+    #[param(example = 30)]
+    limit_days: u32,
+}
+
+/// 东方财富-沪深京板块-行业板块/概念板块的成份股调入以及调出，由相邻两次成份股快照比较得到
+#[utoipa::path(
+    tag = API_TAG,
+    params(
+        BoardConsChangesQuery
+    ),
+    responses(
+        (status = 200, description = "成功获取板块成份股变动", body = OkRes<Vec<ServBoardConsChange>>),
+        (status = 401, description = "没有访问权限", body = OrdinError),
+        (status = 500, description = "发生服务器内部错误", body = OrdinError),
+    )
+)]
+#[get("/board_cons_changes")]
+async fn board_cons_changes(
+    query: web::Query<BoardConsChangesQuery>,
+    ch_client: Data<clickhouse::Client>,
+) -> Result<Json<OkRes<Vec<ServBoardConsChange>>>, OrdinError> {
+    let data = ServBoardConsChange::fetch_with_board(
+        &ch_client,
+        query.kind,
+        &query.board_code,
+        query.limit_days,
+    )
+    .await
+    .context(InternalServerSnafu)?;
+
+    let res = OkRes::from_with_msg("成功获取板块成份股变动".to_owned(), data);
+    Ok(Json(res))
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+struct StockBoardsQuery {
+    /// 需要查询所属板块的股票代码
+    #[param(example = "603777")]
+    stock_id: String,
+    /// 是否同时返回已经调出的板块，默认只返回当前所属的板块
+    #[param(example = false)]
+    include_history: Option<bool>,
+}
+
+/// 获取一只股票所属的东方财富行业板块以及概念板块，以及第一次和最后一次出现在板块成份股快照之中的日期
+#[utoipa::path(
+    tag = API_TAG,
+    params(
+        StockBoardsQuery
+    ),
+    responses(
+        (status = 200, description = "成功获取股票所属的板块", body = OkRes<Vec<ServStockBoard>>),
+        (status = 404, description = "指定的股票代码不存在", body = OrdinError),
+        (status = 401, description = "没有访问权限", body = OrdinError),
+        (status = 500, description = "发生服务器内部错误", body = OrdinError),
+    )
+)]
+#[get("/stock_boards")]
+async fn stock_boards(
+    query: web::Query<StockBoardsQuery>,
+    ch_client: Data<clickhouse::Client>,
+) -> Result<Json<OkRes<Vec<ServStockBoard>>>, OrdinError> {
+    is_stock_code_exists(&ch_client, &query.stock_id)
+        .await
+        .context(InternalServerSnafu)?
+        .then_some(())
+        .ok_or(NotFoundSnafu.build())?;
+
+    let data = ServStockBoard::fetch_with_stock(
+        &ch_client,
+        &query.stock_id,
+        query.include_history.unwrap_or_default(),
+    )
+    .await
+    .context(InternalServerSnafu)?;
+
+    let res = OkRes::from_with_msg("成功获取股票所属的板块".to_owned(), data);
+    Ok(Json(res))
+}
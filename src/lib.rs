@@ -1,4 +1,7 @@
 pub mod handler;
+pub mod limiter;
 pub mod repository;
 pub mod schema;
+pub mod security;
+pub mod upstream;
 pub mod utils;
@@ -0,0 +1,372 @@
+//! aktools请求的全局限流器
+//!
+//! 同一个进程之中所有对aktools的请求都应该经过[`AKTOOLS_LIMITER`]，它由两部分组成：
+//!
+//! - 速率：令牌桶，每秒补充`requests_per_second`个令牌，每次请求按照接口权重消耗令牌
+//! - 并发：AIMD(加性增、乘性减)，上游返回429/5xx或者请求超时时并发上限减半，
+//!   之后每连续成功"并发上限"次请求，并发上限加一，直到恢复为`max_concurrency`
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{LazyLock, Mutex},
+    time::{Duration, Instant},
+};
+
+use reqwest::{RequestBuilder, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Notify;
+use utoipa::ToSchema;
+
+use crate::upstream::{AKTOOLS_UPSTREAMS, DEFAULT_AKTOOLS_BASE_URL};
+
+/// 两次减小并发上限之间的最小间隔，避免同一批并发请求的失败让并发上限连续减半
+const DECREASE_COOLDOWN: Duration = Duration::from_secs(1);
+
+pub static AKTOOLS_LIMITER: LazyLock<AktoolsLimiter> =
+    LazyLock::new(|| AktoolsLimiter::new(LimiterConfig::default()));
+
+/// 限流器配置
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct LimiterConfig {
+    /// 每秒的请求预算(按照权重计算)，小于等于0代表不限制速率
+    pub requests_per_second: f64,
+    /// 并发上限的最大值，也是初始值
+    pub max_concurrency: usize,
+    /// 并发上限的最小值
+    pub min_concurrency: usize,
+    /// 接口权重，key为接口路径(例如`/stock_zh_a_hist`)，没有配置的接口权重为1
+    pub weights: HashMap<String, f64>,
+}
+
+impl Default for LimiterConfig {
+    fn default() -> Self {
+        Self {
+            requests_per_second: 20.0,
+            max_concurrency: 32,
+            min_concurrency: 1,
+            weights: HashMap::new(),
+        }
+    }
+}
+
+/// 限流器当前状态，用于查看
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct LimiterStats {
+    pub requests_per_second: f64,
+    /// 当前的并发上限
+    pub concurrency_limit: usize,
+    pub min_concurrency: usize,
+    pub max_concurrency: usize,
+    /// 正在进行的请求数量
+    pub in_flight: usize,
+    /// 正在等待并发或者令牌的请求数量
+    pub waiting: usize,
+    /// 当前剩余的令牌数量
+    pub tokens: f64,
+    /// 限流器创建(或者重新配置)之后的请求总数
+    pub total_requests: u64,
+    /// 被上游限流(429/5xx或者超时)的请求总数
+    pub throttled_requests: u64,
+    /// 并发上限被减半的次数
+    pub decreases: u64,
+    pub weights: HashMap<String, f64>,
+}
+
+struct LimiterState {
+    config: LimiterConfig,
+    tokens: f64,
+    refilled_at: Instant,
+    limit: usize,
+    in_flight: usize,
+    waiting: usize,
+    /// 上一次调整并发上限之后连续成功的请求数量
+    successes: usize,
+    last_decrease: Option<Instant>,
+    total_requests: u64,
+    throttled_requests: u64,
+    decreases: u64,
+}
+
+impl LimiterState {
+    fn new(config: LimiterConfig) -> Self {
+        Self {
+            tokens: config.requests_per_second.max(0.0),
+            refilled_at: Instant::now(),
+            limit: config.max_concurrency.max(1),
+            in_flight: 0,
+            waiting: 0,
+            successes: 0,
+            last_decrease: None,
+            total_requests: 0,
+            throttled_requests: 0,
+            decreases: 0,
+            config,
+        }
+    }
+
+    /// 尝试消耗`path`对应权重的令牌，令牌不足时返回需要等待的时间，
+    /// 令牌桶的容量为一秒的预算，超过容量的权重按照容量计算
+    fn take_tokens(&mut self, path: &str, now: Instant) -> Option<Duration> {
+        let rps = self.config.requests_per_second;
+        if rps <= 0.0 {
+            return None;
+        }
+        let elapsed = now.duration_since(self.refilled_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * rps).min(rps);
+        self.refilled_at = now;
+
+        let weight = self
+            .config
+            .weights
+            .get(path)
+            .copied()
+            .unwrap_or(1.0)
+            .min(rps);
+        if self.tokens >= weight {
+            self.tokens -= weight;
+            None
+        } else {
+            Some(Duration::from_secs_f64((weight - self.tokens) / rps))
+        }
+    }
+
+    /// 记录一次请求的结果并调整并发上限
+    fn record(&mut self, throttled: bool, now: Instant) {
+        self.total_requests += 1;
+        let min = self.config.min_concurrency.max(1);
+        let max = self.config.max_concurrency.max(min);
+
+        if throttled {
+            self.throttled_requests += 1;
+            self.successes = 0;
+            if self
+                .last_decrease
+                .is_none_or(|last| now.duration_since(last) >= DECREASE_COOLDOWN)
+            {
+                self.limit = (self.limit / 2).max(min);
+                self.last_decrease = Some(now);
+                self.decreases += 1;
+            }
+        } else {
+            self.successes += 1;
+            if self.successes >= self.limit {
+                self.limit = (self.limit + 1).min(max);
+                self.successes = 0;
+            }
+        }
+    }
+}
+
+/// aktools请求限流器
+pub struct AktoolsLimiter {
+    state: Mutex<LimiterState>,
+    /// 每一个请求结束之后通知等待并发的请求
+    released: Notify,
+}
+
+impl AktoolsLimiter {
+    pub fn new(config: LimiterConfig) -> Self {
+        Self {
+            state: Mutex::new(LimiterState::new(config)),
+            released: Notify::new(),
+        }
+    }
+
+    /// 使用新的配置替换当前配置，统计数据会被重置，一般在进程启动时调用
+    pub fn configure(&self, config: LimiterConfig) {
+        let mut state = self.state.lock().unwrap();
+        let in_flight = state.in_flight;
+        let waiting = state.waiting;
+        *state = LimiterState::new(config);
+        state.in_flight = in_flight;
+        state.waiting = waiting;
+        drop(state);
+        self.released.notify_waiters();
+    }
+
+    /// 获取限流器当前状态
+    pub fn stats(&self) -> LimiterStats {
+        let state = self.state.lock().unwrap();
+        LimiterStats {
+            requests_per_second: state.config.requests_per_second,
+            concurrency_limit: state.limit,
+            min_concurrency: state.config.min_concurrency,
+            max_concurrency: state.config.max_concurrency,
+            in_flight: state.in_flight,
+            waiting: state.waiting,
+            tokens: state.tokens,
+            total_requests: state.total_requests,
+            throttled_requests: state.throttled_requests,
+            decreases: state.decreases,
+            weights: state.config.weights.clone(),
+        }
+    }
+
+    /// 等待并发以及令牌之后发送请求，`path`为接口路径，用于查找接口权重
+    pub async fn send(
+        &self,
+        path: &str,
+        request: impl Future<Output = reqwest::Result<Response>>,
+    ) -> reqwest::Result<Response> {
+        let _permit = self.acquire(path).await;
+        let result = request.await;
+        let throttled = match &result {
+            Ok(response) => {
+                response.status() == StatusCode::TOO_MANY_REQUESTS
+                    || response.status().is_server_error()
+            }
+            Err(err) => err.is_timeout(),
+        };
+        self.state.lock().unwrap().record(throttled, Instant::now());
+        result
+    }
+
+    async fn acquire(&self, path: &str) -> Permit<'_> {
+        let _waiting = Waiting::new(self);
+        loop {
+            // 在检查之前创建，保证不会错过检查之后、等待之前的通知
+            let released = self.released.notified();
+            {
+                let mut state = self.state.lock().unwrap();
+                if state.in_flight < state.limit {
+                    state.in_flight += 1;
+                    break;
+                }
+            }
+            released.await;
+        }
+        let permit = Permit { limiter: self };
+
+        loop {
+            let wait = self.state.lock().unwrap().take_tokens(path, Instant::now());
+            match wait {
+                Some(wait) => tokio::time::sleep(wait).await,
+                None => break,
+            }
+        }
+        permit
+    }
+}
+
+/// 占用一个并发，被drop时释放
+struct Permit<'a> {
+    limiter: &'a AktoolsLimiter,
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        self.limiter.state.lock().unwrap().in_flight -= 1;
+        self.limiter.released.notify_waiters();
+    }
+}
+
+/// 统计正在等待的请求数量，请求被取消时同样会减少
+struct Waiting<'a> {
+    limiter: &'a AktoolsLimiter,
+}
+
+impl<'a> Waiting<'a> {
+    fn new(limiter: &'a AktoolsLimiter) -> Self {
+        limiter.state.lock().unwrap().waiting += 1;
+        Self { limiter }
+    }
+}
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        self.limiter.state.lock().unwrap().waiting -= 1;
+    }
+}
+
+/// 经过[`AKTOOLS_LIMITER`]以及[`AKTOOLS_UPSTREAMS`]的aktools GET请求
+pub struct AktoolsRequest<'a> {
+    reqwest_client: reqwest::Client,
+    path: &'a str,
+    /// 地址只是一个占位符，实际发送时会被替换为选中的上游地址
+    builder: RequestBuilder,
+}
+
+/// 创建一个aktools GET请求，`path`为接口路径，例如`/stock_zh_a_hist`，
+/// 请求会经过全局限流器，并被发送到一个健康的aktools上游
+pub fn aktools_get<'a>(reqwest_client: &reqwest::Client, path: &'a str) -> AktoolsRequest<'a> {
+    AktoolsRequest {
+        reqwest_client: reqwest_client.clone(),
+        path,
+        builder: reqwest_client.get(format!("{}{}", DEFAULT_AKTOOLS_BASE_URL, path)),
+    }
+}
+
+impl AktoolsRequest<'_> {
+    pub fn query<T: Serialize + ?Sized>(mut self, query: &T) -> Self {
+        self.builder = self.builder.query(query);
+        self
+    }
+
+    pub async fn send(self) -> reqwest::Result<Response> {
+        let request = self.builder.build()?;
+        AKTOOLS_LIMITER
+            .send(
+                self.path,
+                AKTOOLS_UPSTREAMS.send(&self.reqwest_client, self.path, request),
+            )
+            .await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_aimd() {
+        let mut state = LimiterState::new(LimiterConfig {
+            max_concurrency: 8,
+            min_concurrency: 2,
+            ..Default::default()
+        });
+        let now = Instant::now();
+
+        state.record(true, now);
+        assert_eq!(state.limit, 4);
+        // 冷却时间之内的失败不会继续减小并发上限
+        state.record(true, now);
+        assert_eq!(state.limit, 4);
+        state.record(true, now + DECREASE_COOLDOWN);
+        assert_eq!(state.limit, 2);
+        state.record(true, now + DECREASE_COOLDOWN * 2);
+        assert_eq!(state.limit, 2);
+
+        for _ in 0..2 {
+            state.record(false, now);
+        }
+        assert_eq!(state.limit, 3);
+        for _ in 0..100 {
+            state.record(false, now);
+        }
+        assert_eq!(state.limit, 8);
+        assert_eq!(state.throttled_requests, 4);
+        assert_eq!(state.decreases, 3);
+    }
+
+    #[test]
+    fn test_token_bucket() {
+        let mut state = LimiterState::new(LimiterConfig {
+            requests_per_second: 4.0,
+            weights: HashMap::from([("/stock_zh_a_hist".to_owned(), 3.0)]),
+            ..Default::default()
+        });
+        let now = state.refilled_at;
+
+        assert_eq!(state.take_tokens("/stock_zh_a_hist", now), None);
+        assert_eq!(state.take_tokens("/stock_zt_pool_em", now), None);
+        assert_eq!(
+            state.take_tokens("/stock_zh_a_hist", now),
+            Some(Duration::from_millis(750))
+        );
+        assert_eq!(
+            state.take_tokens("/stock_zh_a_hist", now + Duration::from_millis(750)),
+            None
+        );
+    }
+}
@@ -1,12 +1,17 @@
+use std::str::FromStr;
+
 use chrono::{DateTime, NaiveDate, Utc};
 use clickhouse::Row;
 use serde::{Deserialize, Serialize};
 
-use crate::schema::akshare::{AkIndexOption50EtfQvix, AkStockZhIndexDaily};
+use crate::{
+    schema::akshare::{AkIndexOption50EtfQvix, AkIndexStockConsWeightCsindex, AkStockZhIndexDaily},
+    utils::splite_date_naive,
+};
 
 pub use crate::schema::akshare::AkIndexStockInfo as IndexStockInfoInsert;
 
-/// 指数日频历史行情数据-新浪
+/// 指数日频历史行情数据-东方财富
 ///
 /// clickhouse数据模型
 #[derive(Debug, Serialize, Deserialize, Row)]
@@ -32,16 +37,10 @@ pub struct StockZhIndexDailyInsert {
 }
 
 impl StockZhIndexDailyInsert {
-    pub fn from_with_ts(mut value: AkStockZhIndexDaily, code: &str, ts: DateTime<Utc>) -> Self {
-        value.date.push('Z');
-        let date = value
-            .date
-            .parse::<DateTime<Utc>>()
-            .unwrap()
-            .naive_utc()
-            .date();
-
-        Self {
+    /// 日期无法解析时返回`None`
+    pub fn from_with_ts(value: AkStockZhIndexDaily, code: &str, ts: DateTime<Utc>) -> Option<Self> {
+        let date = NaiveDate::from_str(splite_date_naive(&value.date)).ok()?;
+        Some(Self {
             code: code.to_owned(),
             high: value.high,
             low: value.low,
@@ -50,7 +49,7 @@ impl StockZhIndexDailyInsert {
             volume: value.volume,
             date,
             ts,
-        }
+        })
     }
 }
 
@@ -106,6 +105,51 @@ impl IndexOption50EtfQvixInsert {
     }
 }
 
+// --------------
+
+/// 中证指数-指数成份股以及权重，每一个权重生效日期保存一份完整的成份股列表，
+/// 某一天的成份股为该日期之前(含)最近一个权重生效日期的列表，相邻两份列表的差异即为成份股的调入以及调出
+///
+/// clickhouse数据模型
+#[derive(Debug, Deserialize, Serialize, Row)]
+pub struct IndexStockConsWeightInsert {
+    /// 6位指数代码，例如`000300`
+    pub index_code: String,
+    /// 权重生效日期
+    #[serde(with = "clickhouse::serde::chrono::date")]
+    pub date: NaiveDate,
+    /// 6位成分券代码
+    pub stock_code: String,
+    /// 指数名称
+    pub index_name: String,
+    /// 成分券名称
+    pub stock_name: String,
+    /// 交易所
+    pub exchange: String,
+    /// 权重(%)
+    pub weight: f64,
+    /// 数据收集时间戳，毫秒等级
+    #[serde(with = "clickhouse::serde::chrono::datetime64::millis")]
+    pub ts: DateTime<Utc>,
+}
+
+impl IndexStockConsWeightInsert {
+    /// 权重生效日期无法解析时返回`None`
+    pub fn from_with_ts(value: AkIndexStockConsWeightCsindex, ts: DateTime<Utc>) -> Option<Self> {
+        let date = NaiveDate::from_str(splite_date_naive(&value.date)).ok()?;
+        Some(Self {
+            index_code: value.index_code,
+            date,
+            stock_code: value.stock_code,
+            index_name: value.index_name,
+            stock_name: value.stock_name,
+            exchange: value.exchange,
+            weight: value.weight,
+            ts,
+        })
+    }
+}
+
 #[cfg(test)]
 mod test {
     #![allow(unused)]
@@ -120,4 +164,23 @@ mod test {
         let date2 = date;
         println!("date = {date:?}");
     }
+
+    #[test]
+    fn test_index_cons_weight_from_api() {
+        let value = crate::schema::akshare::AkIndexStockConsWeightCsindex {
+            date: "2025-04-30T00:00:00.000".to_owned(),
+            index_code: "000300".to_owned(),
+            index_name: "沪深300".to_owned(),
+            stock_code: "600519".to_owned(),
+            stock_name: "贵州茅台".to_owned(),
+            exchange: "上海证券交易所".to_owned(),
+            weight: 4.512,
+        };
+        let row = super::IndexStockConsWeightInsert::from_with_ts(value, Utc::now()).unwrap();
+        assert_eq!(
+            row.date,
+            chrono::NaiveDate::from_ymd_opt(2025, 4, 30).unwrap()
+        );
+        assert_eq!(row.stock_code, "600519");
+    }
 }
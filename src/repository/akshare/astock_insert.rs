@@ -14,6 +14,7 @@ use crate::{
             ak_astock, AkStockHsgtHistEm, AkStockNewsMainCx, AkStockZhAHist, AkStockZtPoolEm,
         },
     },
+    security::{to_cents, Board},
     utils::splite_date_naive,
 };
 
@@ -46,27 +47,45 @@ impl TradingStatus {
         }
     }
 
-    /// 判断股票是否停牌
+    /// 按照股票所在板块的涨跌幅限制判断交易状态
+    ///
+    /// - 最新价为空视为停牌
+    /// - 名称以`N`(上市首日)或者`C`(上市后前五个交易日)开头的新股没有涨跌幅限制，视为新上市
+    /// - 其余股票使用昨收价以及板块的涨跌幅比例按照交易所规则四舍五入到分计算涨跌停价，
+    ///   最新价达到涨跌停价时视为涨跌停
     pub fn determine_status(data: &ak_astock::RealtimeStockMarketRecord) -> Self {
-        // 如果最新价为None，可能表示停牌
-        if data.latest_price.is_none() {
+        let Some(latest_price) = data.latest_price else {
             return TradingStatus::Suspended;
+        };
+        if data.name.starts_with('N') || data.name.starts_with('C') {
+            return TradingStatus::NewListing;
         }
 
-        // 根据涨跌幅判断涨跌停
-        // if let Some(change_percentage) = data.change_percentage {
-        //     if change_percentage >= 9.9 {
-        //         return TradingStatus::LimitUp;
-        //     } else if change_percentage <= -9.9 {
-        //         return TradingStatus::LimitDown;
-        //     }
-        // }
-
-        // 其他情况视为正常交易
-        TradingStatus::Active
+        let (Some(board), Some(previous_close)) =
+            (Board::from_code(&data.code), data.previous_close)
+        else {
+            return TradingStatus::Active;
+        };
+        if previous_close <= 0.0 {
+            return TradingStatus::Active;
+        }
+        let (limit_up, limit_down) = board.limit_prices(previous_close, is_st(&data.name));
+        let latest_cents = to_cents(latest_price);
+        if latest_cents >= limit_up {
+            TradingStatus::LimitUp
+        } else if latest_cents <= limit_down {
+            TradingStatus::LimitDown
+        } else {
+            TradingStatus::Active
+        }
     }
 }
 
+/// 名称之中带有ST(包括*ST)的股票为风险警示股票
+fn is_st(name: &str) -> bool {
+    name.contains("ST")
+}
+
 /// 与ClickHouse表结构对应的Rust结构体
 #[derive(Debug, Clone, Row, Serialize, Deserialize)]
 pub struct RealtimeStockMarketRecord {
@@ -277,6 +296,52 @@ impl StockZhAHistInsert {
 
 // ------------------------------------------------------------------------------
 
+/// 新浪财经-A股后复权因子，前复权以及后复权价格在查询时由不复权价格以及复权因子计算：
+///
+/// - 后复权价格 = 不复权价格 x 当日生效的复权因子
+/// - 前复权价格 = 不复权价格 x 当日生效的复权因子 / 最新的复权因子
+///
+/// clickhouse数据模型
+#[derive(Debug, Deserialize, Serialize, Row)]
+pub struct StockAdjustFactorInsert {
+    /// 6位股票代码
+    pub code: String,
+    /// 复权因子开始生效的日期
+    #[serde(with = "clickhouse::serde::chrono::date")]
+    pub date: NaiveDate,
+    /// 后复权因子
+    pub hfq_factor: f64,
+    /// 数据收集时间戳，毫秒等级
+    #[serde(with = "clickhouse::serde::chrono::datetime64::millis")]
+    pub ts: DateTime<Utc>,
+}
+
+impl StockAdjustFactorInsert {
+    /// 日期或者复权因子无法解析时返回`None`
+    pub fn from_with_code(
+        value: ak_astock::AkStockZhADailyHfqFactor,
+        code: &str,
+        ts: DateTime<Utc>,
+    ) -> Option<Self> {
+        let date = NaiveDate::from_str(splite_date_naive(value.date.trim())).ok()?;
+        let hfq_factor = value
+            .hfq_factor
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .filter(|factor| factor.is_finite() && *factor > 0.0)?;
+
+        Some(Self {
+            code: code.to_owned(),
+            date,
+            hfq_factor,
+            ts,
+        })
+    }
+}
+
+// ------------------------------------------------------------------------------
+
 #[derive(Serialize_repr, Deserialize_repr, PartialEq, Debug, EnumIter, Clone, Copy)]
 #[repr(u8)]
 pub enum FlowDirection {
@@ -513,6 +578,87 @@ impl StockNewsMainCxInsert {
 mod test {
     use chrono::{DateTime, Utc};
 
+    use super::*;
+
+    #[test]
+    fn test_determine_status() {
+        let record = |code: &str, name: &str, latest: Option<f64>, previous: f64| {
+            serde_json::from_value::<ak_astock::RealtimeStockMarketRecord>(serde_json::json!({
+                "序号": 1, "代码": code, "名称": name, "最新价": latest, "昨收": previous,
+            }))
+            .unwrap()
+        };
+        let status = |code, name, latest, previous| {
+            TradingStatus::determine_status(&record(code, name, latest, previous))
+        };
+
+        assert_eq!(
+            status("600000", "浦发银行", None, 10.0),
+            TradingStatus::Suspended
+        );
+        // 10.05 * 1.1 = 11.055，四舍五入之后涨停价为11.06
+        assert_eq!(
+            status("600000", "浦发银行", Some(11.06), 10.05),
+            TradingStatus::LimitUp
+        );
+        assert_eq!(
+            status("600000", "浦发银行", Some(11.05), 10.05),
+            TradingStatus::Active
+        );
+        assert_eq!(
+            status("000001", "平安银行", Some(9.05), 10.05),
+            TradingStatus::LimitDown
+        );
+        assert_eq!(
+            status("600001", "*ST某某", Some(10.55), 10.05),
+            TradingStatus::LimitUp
+        );
+        assert_eq!(
+            status("300750", "宁德时代", Some(11.06), 10.05),
+            TradingStatus::Active
+        );
+        assert_eq!(
+            status("300750", "宁德时代", Some(12.06), 10.05),
+            TradingStatus::LimitUp
+        );
+        assert_eq!(
+            status("688981", "中芯国际", Some(8.04), 10.05),
+            TradingStatus::LimitDown
+        );
+        assert_eq!(
+            status("830799", "艾融软件", Some(13.07), 10.05),
+            TradingStatus::LimitUp
+        );
+        assert_eq!(
+            status("301999", "N新股", Some(30.15), 10.05),
+            TradingStatus::NewListing
+        );
+        assert_eq!(
+            status("688999", "C新股", Some(5.0), 10.05),
+            TradingStatus::NewListing
+        );
+    }
+
+    #[test]
+    fn test_adjust_factor_from_api() {
+        let factor = |date: &str, hfq_factor: &str| {
+            let value = ak_astock::AkStockZhADailyHfqFactor {
+                date: date.to_owned(),
+                hfq_factor: hfq_factor.to_owned(),
+            };
+            StockAdjustFactorInsert::from_with_code(value, "600000", Utc::now())
+        };
+
+        let row = factor("2024-07-15T00:00:00.000", "12.0476372003586").unwrap();
+        assert_eq!(row.code, "600000");
+        assert_eq!(row.date, NaiveDate::from_ymd_opt(2024, 7, 15).unwrap());
+        assert_eq!(row.hfq_factor, 12.0476372003586);
+        assert!(factor("1900-01-01", "1.0").is_some());
+        assert!(factor("2024-07-15", "0").is_none());
+        assert!(factor("2024-07-15", "").is_none());
+        assert!(factor("", "1.0").is_none());
+    }
+
     #[test]
     fn test_parse_time_from_str() {
         let interval_time_str = "2025-04-23 12:30 +08:00";
@@ -0,0 +1,212 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use clickhouse::Row;
+use serde::{Deserialize, Serialize};
+use serde_repr::{Deserialize_repr, Serialize_repr};
+use strum::EnumIter;
+use utoipa::ToSchema;
+
+use crate::{schema::akshare::AkStockFinancialAbstractThs, utils::splite_date_naive};
+
+/// 同花顺财务指标的报告期类型
+#[derive(Serialize_repr, Deserialize_repr, PartialEq, Debug, EnumIter, Clone, Copy, ToSchema)]
+#[repr(u8)]
+pub enum FinancialPeriodType {
+    /// 按报告期，一季报、半年报以及三季报为年初至报告期末的累计数据
+    Report,
+    /// 按年度
+    Annual,
+    /// 按单季度
+    Quarter,
+}
+
+impl FinancialPeriodType {
+    /// `stock_financial_abstract_ths`接口的indicator参数
+    pub fn to_str(&self) -> &'static str {
+        match self {
+            Self::Report => "按报告期",
+            Self::Annual => "按年度",
+            Self::Quarter => "按单季度",
+        }
+    }
+
+    pub fn from_indicator(indicator: &str) -> Option<Self> {
+        match indicator {
+            "按报告期" => Some(Self::Report),
+            "按年度" => Some(Self::Annual),
+            "按单季度" => Some(Self::Quarter),
+            _ => None,
+        }
+    }
+}
+
+/// 同花顺-财务指标-主要指标，金额单位为元，增长率、利润率、收益率以及资产负债率单位为%
+///
+/// clickhouse数据模型
+#[derive(Debug, Deserialize, Serialize, Row)]
+pub struct StockFinancialAbstractThsInsert {
+    /// 股票代码
+    pub code: String,
+    pub period_type: FinancialPeriodType,
+    /// 报告期末日期，按年度的数据为当年12月31日
+    #[serde(with = "clickhouse::serde::chrono::date")]
+    pub report_date: NaiveDate,
+    /// 净利润(元)
+    pub net_profit: Option<f64>,
+    /// 净利润同比增长率(%)
+    pub net_profit_growth_rate: Option<f64>,
+    /// 扣非净利润(元)
+    pub deducted_net_profit: Option<f64>,
+    /// 扣非净利润同比增长率(%)
+    pub deducted_net_profit_growth_rate: Option<f64>,
+    /// 营业总收入(元)
+    pub total_revenue: Option<f64>,
+    /// 营业总收入同比增长率(%)
+    pub total_revenue_growth_rate: Option<f64>,
+    /// 基本每股收益(元)
+    pub basic_eps: Option<f64>,
+    /// 每股净资产(元)
+    pub net_assets_per_share: Option<f64>,
+    /// 每股资本公积金(元)
+    pub capital_reserve_per_share: Option<f64>,
+    /// 每股未分配利润(元)
+    pub undistributed_profit_per_share: Option<f64>,
+    /// 每股经营现金流(元)
+    pub operating_cash_flow_per_share: Option<f64>,
+    /// 销售净利率(%)
+    pub net_profit_margin: Option<f64>,
+    /// 销售毛利率(%)
+    pub gross_profit_margin: Option<f64>,
+    /// 净资产收益率(%)
+    pub roe: Option<f64>,
+    /// 净资产收益率-摊薄(%)
+    pub roe_diluted: Option<f64>,
+    /// 营业周期(天)
+    pub operating_cycle: Option<f64>,
+    /// 存货周转率(次)
+    pub inventory_turnover: Option<f64>,
+    /// 存货周转天数(天)
+    pub inventory_turnover_days: Option<f64>,
+    /// 应收账款周转天数(天)
+    pub accounts_receivable_turnover_days: Option<f64>,
+    /// 流动比率
+    pub current_ratio: Option<f64>,
+    /// 速动比率
+    pub quick_ratio: Option<f64>,
+    /// 保守速动比率
+    pub conservative_quick_ratio: Option<f64>,
+    /// 产权比率
+    pub equity_ratio: Option<f64>,
+    /// 资产负债率(%)
+    pub debt_asset_ratio: Option<f64>,
+    /// 数据收集时间戳，毫秒等级
+    #[serde(with = "clickhouse::serde::chrono::datetime64::millis")]
+    pub ts: DateTime<Utc>,
+}
+
+impl StockFinancialAbstractThsInsert {
+    /// 报告期无法解析时返回`None`
+    pub fn from_with_type(
+        value: AkStockFinancialAbstractThs,
+        code: &str,
+        period_type: FinancialPeriodType,
+        ts: DateTime<Utc>,
+    ) -> Option<Self> {
+        let report_date = parse_report_date(&value.report_date)?;
+        let parse = |value: &Option<String>| value.as_deref().and_then(parse_financial_value);
+        Some(Self {
+            code: code.to_owned(),
+            period_type,
+            report_date,
+            net_profit: parse(&value.net_profit),
+            net_profit_growth_rate: parse(&value.net_profit_growth_rate),
+            deducted_net_profit: parse(&value.deducted_net_profit),
+            deducted_net_profit_growth_rate: parse(&value.deducted_net_profit_growth_rate),
+            total_revenue: parse(&value.total_revenue),
+            total_revenue_growth_rate: parse(&value.total_revenue_growth_rate),
+            basic_eps: parse(&value.basic_eps),
+            net_assets_per_share: parse(&value.net_assets_per_share),
+            capital_reserve_per_share: parse(&value.capital_reserve_per_share),
+            undistributed_profit_per_share: parse(&value.undistributed_profit_per_share),
+            operating_cash_flow_per_share: parse(&value.operating_cash_flow_per_share),
+            net_profit_margin: parse(&value.net_profit_margin),
+            gross_profit_margin: parse(&value.gross_profit_margin),
+            roe: parse(&value.roe),
+            roe_diluted: parse(&value.roe_diluted),
+            operating_cycle: parse(&value.operating_cycle),
+            inventory_turnover: parse(&value.inventory_turnover),
+            inventory_turnover_days: parse(&value.inventory_turnover_days),
+            accounts_receivable_turnover_days: parse(&value.accounts_receivable_turnover_days),
+            current_ratio: parse(&value.current_ratio),
+            quick_ratio: parse(&value.quick_ratio),
+            conservative_quick_ratio: parse(&value.conservative_quick_ratio),
+            equity_ratio: parse(&value.equity_ratio),
+            debt_asset_ratio: parse(&value.debt_asset_ratio),
+            ts,
+        })
+    }
+}
+
+/// 报告期，按年度的数据只有年份(例如`2024`)，视为当年12月31日
+fn parse_report_date(report_date: &str) -> Option<NaiveDate> {
+    let report_date = splite_date_naive(report_date.trim());
+    if report_date.len() == 4 {
+        let year = report_date.parse().ok()?;
+        return NaiveDate::from_ymd_opt(year, 12, 31);
+    }
+    NaiveDate::parse_from_str(report_date, "%Y-%m-%d")
+        .or_else(|_| NaiveDate::parse_from_str(report_date, "%Y%m%d"))
+        .ok()
+}
+
+/// 解析同花顺财务指标之中带单位的数值，例如`1.23亿`、`-5012.36万`、`15.6%`、`0.52元`，
+/// 百分比保留百分数的数值，`--`、`False`等无法解析的值返回`None`
+pub fn parse_financial_value(value: &str) -> Option<f64> {
+    let value = value.trim();
+    let value = value.strip_suffix('元').unwrap_or(value);
+    let value = value.strip_suffix('%').unwrap_or(value);
+    let (number, multiplier) = if let Some(number) = value.strip_suffix("万亿") {
+        (number, 1e12)
+    } else if let Some(number) = value.strip_suffix('亿') {
+        (number, 1e8)
+    } else if let Some(number) = value.strip_suffix('万') {
+        (number, 1e4)
+    } else {
+        (value, 1.0)
+    };
+    number
+        .trim()
+        .replace(',', "")
+        .parse::<f64>()
+        .ok()
+        .filter(|number| number.is_finite())
+        .map(|number| number * multiplier)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_financial_value() {
+        assert_eq!(parse_financial_value("1.23亿"), Some(123_000_000.0));
+        assert_eq!(parse_financial_value("-5012.36万"), Some(-50_123_600.0));
+        assert_eq!(parse_financial_value("1.5万亿"), Some(1_500_000_000_000.0));
+        assert_eq!(parse_financial_value("15.6%"), Some(15.6));
+        assert_eq!(parse_financial_value("-3.21%"), Some(-3.21));
+        assert_eq!(parse_financial_value("0.52元"), Some(0.52));
+        assert_eq!(parse_financial_value("1,234.5"), Some(1234.5));
+        for value in ["", "--", "False", "亿", "nan"] {
+            assert_eq!(parse_financial_value(value), None, "{value}");
+        }
+
+        assert_eq!(
+            parse_report_date("2024-09-30"),
+            NaiveDate::from_ymd_opt(2024, 9, 30)
+        );
+        assert_eq!(
+            parse_report_date("2023"),
+            NaiveDate::from_ymd_opt(2023, 12, 31)
+        );
+        assert_eq!(parse_report_date("报告期"), None);
+    }
+}
@@ -2,6 +2,14 @@
 
 pub mod aindex_insert;
 pub mod astock_insert;
+pub mod financial_insert;
+pub mod sector_insert;
+pub mod security_insert;
+pub mod tool_insert;
 
 pub use aindex_insert::*;
 pub use astock_insert::*;
+pub use financial_insert::*;
+pub use sector_insert::*;
+pub use security_insert::*;
+pub use tool_insert::*;
@@ -0,0 +1,219 @@
+use std::str::FromStr;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use clickhouse::Row;
+use serde::{Deserialize, Serialize};
+use serde_repr::{Deserialize_repr, Serialize_repr};
+use strum::EnumIter;
+use utoipa::ToSchema;
+
+use crate::{
+    schema::akshare::{AkStockBoardConsEm, AkStockBoardHistEm, AkStockBoardNameEm},
+    utils::splite_date_naive,
+};
+
+/// 东方财富板块种类
+#[derive(Serialize_repr, Deserialize_repr, PartialEq, Debug, EnumIter, Clone, Copy, ToSchema)]
+#[repr(u8)]
+pub enum BoardKind {
+    /// 行业板块
+    Industry,
+    /// 概念板块
+    Concept,
+}
+
+impl BoardKind {
+    /// 接口名称之中的板块种类，例如`stock_board_industry_name_em`之中的`industry`
+    pub fn to_str(&self) -> &'static str {
+        match self {
+            Self::Industry => "industry",
+            Self::Concept => "concept",
+        }
+    }
+
+    /// 历史行情接口的日频period参数，行业板块与概念板块的写法不同
+    pub fn daily_period(&self) -> &'static str {
+        match self {
+            Self::Industry => "日k",
+            Self::Concept => "daily",
+        }
+    }
+}
+
+/// 东方财富-沪深京板块-行业板块/概念板块每个交易日收盘之后的板块列表以及行情
+///
+/// clickhouse数据模型
+#[derive(Debug, Deserialize, Serialize, Row)]
+pub struct StockBoardEmInsert {
+    pub kind: BoardKind,
+    /// 交易日期
+    #[serde(with = "clickhouse::serde::chrono::date")]
+    pub date: NaiveDate,
+    /// 板块代码，例如`BK1027`
+    pub code: String,
+    /// 板块名称
+    pub name: String,
+    /// 按照涨跌幅的排名
+    pub rank: u32,
+    /// 最新价
+    pub latest_price: Option<f64>,
+    /// 涨跌额
+    pub change_amount: Option<f64>,
+    /// 涨跌幅(%)
+    pub change_percentage: Option<f64>,
+    /// 总市值(元)
+    pub total_market_value: Option<f64>,
+    /// 换手率(%)
+    pub turnover_rate: Option<f64>,
+    /// 上涨家数
+    pub rise_count: u32,
+    /// 下跌家数
+    pub fall_count: u32,
+    /// 领涨股票
+    pub leading_stock: String,
+    /// 领涨股票-涨跌幅(%)
+    pub leading_stock_change_percentage: Option<f64>,
+    /// 数据收集时间戳，毫秒等级
+    #[serde(with = "clickhouse::serde::chrono::datetime64::millis")]
+    pub ts: DateTime<Utc>,
+}
+
+impl StockBoardEmInsert {
+    pub fn from_with_kind(
+        value: AkStockBoardNameEm,
+        kind: BoardKind,
+        date: NaiveDate,
+        ts: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            kind,
+            date,
+            code: value.code,
+            name: value.name,
+            rank: value.rank,
+            latest_price: value.latest_price,
+            change_amount: value.change_amount,
+            change_percentage: value.change_percentage,
+            total_market_value: value.total_market_value,
+            turnover_rate: value.turnover_rate,
+            rise_count: value.rise_count.unwrap_or_default() as u32,
+            fall_count: value.fall_count.unwrap_or_default() as u32,
+            leading_stock: value.leading_stock.unwrap_or_default(),
+            leading_stock_change_percentage: value.leading_stock_change_percentage,
+            ts,
+        }
+    }
+}
+
+/// 东方财富-沪深京板块-行业板块/概念板块的日频历史行情
+///
+/// clickhouse数据模型
+#[derive(Debug, Deserialize, Serialize, Row)]
+pub struct StockBoardHistEmInsert {
+    pub kind: BoardKind,
+    /// 板块代码
+    pub code: String,
+    /// 板块名称
+    pub name: String,
+    /// 交易日期
+    #[serde(with = "clickhouse::serde::chrono::date")]
+    pub date: NaiveDate,
+    /// 开盘
+    pub open: f64,
+    /// 收盘
+    pub close: f64,
+    /// 最高
+    pub high: f64,
+    /// 最低
+    pub low: f64,
+    /// 涨跌幅(%)
+    pub change_percentage: f64,
+    /// 涨跌额
+    pub change_amount: f64,
+    /// 成交量(手)
+    pub trading_volume: f64,
+    /// 成交额(元)
+    pub trading_value: f64,
+    /// 振幅(%)
+    pub amplitude: f64,
+    /// 换手率(%)
+    pub turnover_rate: f64,
+    /// 数据收集时间戳，毫秒等级
+    #[serde(with = "clickhouse::serde::chrono::datetime64::millis")]
+    pub ts: DateTime<Utc>,
+}
+
+impl StockBoardHistEmInsert {
+    /// 日期无法解析时返回`None`
+    pub fn from_with_board(
+        value: AkStockBoardHistEm,
+        kind: BoardKind,
+        code: &str,
+        name: &str,
+        ts: DateTime<Utc>,
+    ) -> Option<Self> {
+        let date = NaiveDate::from_str(splite_date_naive(&value.date)).ok()?;
+        Some(Self {
+            kind,
+            code: code.to_owned(),
+            name: name.to_owned(),
+            date,
+            open: value.open,
+            close: value.close,
+            high: value.high,
+            low: value.low,
+            change_percentage: value.change_percentage,
+            change_amount: value.change_amount,
+            trading_volume: value.trading_volume,
+            trading_value: value.trading_value,
+            amplitude: value.amplitude,
+            turnover_rate: value.turnover_rate,
+            ts,
+        })
+    }
+}
+
+/// 东方财富-沪深京板块-行业板块/概念板块的成份股，每次收集保存一份当天的成份股快照，
+/// 比较相邻两次快照得到成份股的调入以及调出
+///
+/// clickhouse数据模型
+#[derive(Debug, Deserialize, Serialize, Row)]
+pub struct StockBoardConsEmInsert {
+    pub kind: BoardKind,
+    /// 板块代码
+    pub board_code: String,
+    /// 快照日期
+    #[serde(with = "clickhouse::serde::chrono::date")]
+    pub date: NaiveDate,
+    /// 6位股票代码
+    pub stock_code: String,
+    /// 板块名称
+    pub board_name: String,
+    /// 股票名称
+    pub stock_name: String,
+    /// 数据收集时间戳，毫秒等级
+    #[serde(with = "clickhouse::serde::chrono::datetime64::millis")]
+    pub ts: DateTime<Utc>,
+}
+
+impl StockBoardConsEmInsert {
+    /// `date`为本次收集的日期
+    pub fn from_with_board(
+        value: AkStockBoardConsEm,
+        kind: BoardKind,
+        board_code: &str,
+        board_name: &str,
+        date: NaiveDate,
+        ts: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            kind,
+            board_code: board_code.to_owned(),
+            date,
+            stock_code: value.code,
+            board_name: board_name.to_owned(),
+            stock_name: value.name,
+            ts,
+        }
+    }
+}
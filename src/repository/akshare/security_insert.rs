@@ -0,0 +1,83 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use clickhouse::Row;
+use serde::{Deserialize, Serialize};
+use serde_repr::{Deserialize_repr, Serialize_repr};
+
+use crate::{security::SecurityCode, utils::splite_date_naive};
+
+/// 证券种类
+#[derive(Debug, Clone, Copy, PartialEq, Serialize_repr, Deserialize_repr)]
+#[repr(u8)]
+pub enum SecurityKind {
+    /// A股股票
+    Stock,
+    /// 指数
+    Index,
+}
+
+/// 上市状态
+#[derive(Debug, Clone, Copy, PartialEq, Serialize_repr, Deserialize_repr)]
+#[repr(u8)]
+pub enum ListingStatus {
+    /// 正常上市(包括停牌)
+    Listed,
+    /// 终止上市
+    Delisted,
+}
+
+/// 证券主表，所有数据收集任务的代码范围以及接口之中的代码校验都以这张表为准
+///
+/// clickhouse数据模型
+#[derive(Debug, Clone, Serialize, Deserialize, Row)]
+pub struct SecurityMasterInsert {
+    /// 交易所后缀写法的代码，例如`600198.SH`
+    pub code: String,
+    /// 6位数字代码，例如`600198`
+    pub symbol: String,
+    /// 证券简称
+    pub name: String,
+    pub kind: SecurityKind,
+    /// 交易所，`SH`、`SZ`或者`BJ`
+    pub exchange: String,
+    /// 板块，`Main`、`ChiNext`、`Star`或者`Bse`，指数以及B股为空字符串
+    pub board: String,
+    /// 上市日期，指数为空
+    #[serde(with = "clickhouse::serde::chrono::date::option")]
+    pub list_date: Option<NaiveDate>,
+    pub status: ListingStatus,
+    /// 数据收集时间戳
+    #[serde(with = "clickhouse::serde::chrono::datetime64::millis")]
+    pub ts: DateTime<Utc>,
+}
+
+impl SecurityMasterInsert {
+    /// `list_date`为接口返回的上市日期，无法解析时为空
+    pub fn new(
+        code: &SecurityCode,
+        name: String,
+        kind: SecurityKind,
+        list_date: &str,
+        status: ListingStatus,
+        ts: DateTime<Utc>,
+    ) -> Self {
+        let list_date = splite_date_naive(list_date);
+        let list_date = NaiveDate::parse_from_str(list_date, "%Y-%m-%d")
+            .or_else(|_| NaiveDate::parse_from_str(list_date, "%Y%m%d"))
+            .ok();
+        Self {
+            code: code.suffixed(),
+            symbol: code.symbol().to_owned(),
+            name,
+            kind,
+            exchange: code.exchange().as_str().to_owned(),
+            board: code
+                .board()
+                .map(|board| board.as_str())
+                .unwrap_or_default()
+                .to_owned(),
+            list_date,
+            status,
+            ts,
+        }
+    }
+}
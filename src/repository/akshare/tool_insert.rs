@@ -0,0 +1,29 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use clickhouse::Row;
+use serde::{Deserialize, Serialize};
+
+use crate::schema::akshare::AkToolTradeDateHistSina;
+
+/// 交易日历-新浪
+///
+/// clickhouse数据模型
+#[derive(Debug, Serialize, Deserialize, Row)]
+pub struct ToolTradeDateHistSinaInsert {
+    /// 交易日
+    #[serde(with = "clickhouse::serde::chrono::date")]
+    pub trade_date: NaiveDate,
+    /// 数据收集时间戳
+    #[serde(with = "clickhouse::serde::chrono::datetime64::millis")]
+    pub ts: DateTime<Utc>,
+}
+
+impl ToolTradeDateHistSinaInsert {
+    pub fn from_with_ts(
+        value: AkToolTradeDateHistSina,
+        ts: DateTime<Utc>,
+    ) -> chrono::ParseResult<Self> {
+        let date = value.trade_date.split('T').next().unwrap_or_default();
+        let trade_date = NaiveDate::parse_from_str(date, "%Y-%m-%d")?;
+        Ok(Self { trade_date, ts })
+    }
+}
@@ -3,18 +3,15 @@ use clickhouse::Row;
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
-use crate::utils::limit_or_not;
+use super::is_security_code_exists;
+use crate::{repository::akshare::SecurityKind, utils::limit_or_not};
 
-/// 判断一个指数代码是否存在
+/// 判断一个指数代码(例如`sz399982`)是否存在于证券主表之中
 pub async fn is_index_code_exists(
     ch_client: &clickhouse::Client,
     index_code: &str,
 ) -> anyhow::Result<bool> {
-    Ok(ch_client
-        .query("SELECT exists(SELECT 1 FROM stock_zh_index_daily WHERE code = ?) AS code_exists")
-        .bind(index_code)
-        .fetch_one()
-        .await?)
+    is_security_code_exists(ch_client, index_code, SecurityKind::Index).await
 }
 
 /// 50ETF 期权波动率指数 QVIX; 又称中国版的恐慌指数 K线数据
@@ -321,6 +318,114 @@ LIMIT ?, ?
 
 // ---------------------------------------------------------------------------------
 
+/// 指数在某一天的成份股以及权重，带有每只成份股在该日期之前(含)最近一个交易日的不复权日频行情，
+/// 最近30天之内没有行情(例如长期停牌)的成份股行情字段为`null`
+#[derive(Debug, Deserialize, Serialize, Row, ToSchema)]
+pub struct IndexConstituentFetch {
+    /// 6位成分券代码
+    #[schema(example = "600519")]
+    pub stock_code: String,
+    /// 成分券名称
+    pub stock_name: String,
+    /// 交易所
+    pub exchange: String,
+    /// 权重(%)
+    pub weight: f64,
+    /// 权重生效日期，格式为YYYY-MM-DD
+    #[serde(deserialize_with = "clickhouse::serde::chrono::date::deserialize")]
+    pub weight_date: NaiveDate,
+    /// 行情日期，格式为YYYY-MM-DD
+    #[serde(deserialize_with = "clickhouse::serde::chrono::date::option::deserialize")]
+    pub quote_date: Option<NaiveDate>,
+    /// 开盘价
+    pub open: Option<f64>,
+    /// 收盘价
+    pub close: Option<f64>,
+    /// 最高价
+    pub high: Option<f64>,
+    /// 最低价
+    pub low: Option<f64>,
+    /// 涨跌幅(%)
+    pub change_percentage: Option<f64>,
+    /// 成交量(手)
+    pub trading_volume: Option<f64>,
+    /// 成交额(元)
+    pub trading_value: Option<f64>,
+    /// 换手率(%)
+    pub turnover_rate: Option<f64>,
+}
+
+impl IndexConstituentFetch {
+    /// 获取6位指数代码`index_code`在`date`的成份股，即该日期之前(含)最近一个权重生效日期的成份股列表，
+    /// 按照权重从高到低排序
+    pub async fn fetch_with_date(
+        ch_client: &clickhouse::Client,
+        index_code: &str,
+        date: NaiveDate,
+    ) -> anyhow::Result<Vec<Self>> {
+        let sql = r#"
+SELECT
+    cons.stock_code AS stock_code,
+    cons.stock_name AS stock_name,
+    cons.exchange AS exchange,
+    cons.weight AS weight,
+    cons.date AS weight_date,
+    quote.date AS quote_date,
+    quote.open AS open,
+    quote.close AS close,
+    quote.high AS high,
+    quote.low AS low,
+    quote.change_percentage AS change_percentage,
+    quote.trading_volume AS trading_volume,
+    quote.trading_value AS trading_value,
+    quote.turnover_rate AS turnover_rate
+FROM (
+    SELECT stock_code, stock_name, exchange, weight, date
+    FROM index_stock_cons_weight FINAL
+    WHERE index_code = ? AND date = (
+        SELECT max(date) FROM index_stock_cons_weight WHERE index_code = ? AND date <= toDate(?)
+    )
+) AS cons
+LEFT JOIN (
+    SELECT
+        code,
+        max(date) AS date,
+        argMax(open, date) AS open,
+        argMax(close, date) AS close,
+        argMax(high, date) AS high,
+        argMax(low, date) AS low,
+        argMax(change_percentage, date) AS change_percentage,
+        argMax(trading_volume, date) AS trading_volume,
+        argMax(trading_value, date) AS trading_value,
+        argMax(turnover_rate, date) AS turnover_rate
+    FROM stock_zh_a_hist FINAL
+    WHERE adj_type = 'None'
+        AND date <= toDate(?)
+        AND date > toDate(?) - 30
+        AND code IN (SELECT stock_code FROM index_stock_cons_weight WHERE index_code = ?)
+    GROUP BY code
+) AS quote ON cons.stock_code = quote.code
+ORDER BY weight DESC, stock_code ASC
+SETTINGS join_use_nulls = 1
+"#;
+
+        let date = date.to_string();
+        let data = ch_client
+            .query(sql)
+            .bind(index_code)
+            .bind(index_code)
+            .bind(&date)
+            .bind(&date)
+            .bind(&date)
+            .bind(index_code)
+            .fetch_all()
+            .await?;
+        Ok(data)
+    }
+}
+
+// ---------------------------------------------------------------------------------
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -336,4 +441,13 @@ mod test {
             .await
             .unwrap());
     }
+
+    #[tokio::test]
+    async fn test_fetch_index_constituent() {
+        let today = chrono::Utc::now().date_naive();
+        let data = IndexConstituentFetch::fetch_with_date(&TEST_CH_CLIENT, "000300", today)
+            .await
+            .unwrap();
+        println!("{}", serde_json::to_string_pretty(&data).unwrap());
+    }
 }
@@ -1,20 +1,17 @@
-use chrono::NaiveDate;
+use chrono::{DateTime, NaiveDate, Utc};
 use clickhouse::Row;
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
-use crate::repository::akshare::StockAdjustmentType;
+use super::is_security_code_exists;
+use crate::repository::akshare::{SecurityKind, StockAdjustmentType};
 
-/// 判断一个指定的stock code是否存在
+/// 判断一个指定的stock code是否存在于证券主表之中
 pub async fn is_stock_code_exists(
     ch_client: &clickhouse::Client,
     stock_code: &str,
 ) -> anyhow::Result<bool> {
-    Ok(ch_client
-        .query("SELECT exists(SELECT 1 FROM stock_zh_a_hist WHERE code = ?) AS code_exists")
-        .bind(stock_code)
-        .fetch_one()
-        .await?)
+    is_security_code_exists(ch_client, stock_code, SecurityKind::Stock).await
 }
 
 /// 移动平均线数据(MA5/MA10/MA20)
@@ -130,41 +127,44 @@ pub struct DailyKlineFetch {
 }
 
 impl DailyKlineFetch {
+    /// 获取对应`stock_id`最近`limit_days`个交易日的K线，复权价格由不复权价格以及复权因子计算
     pub async fn fetch_with_limit(
         ch_client: &clickhouse::Client,
         adj_type: StockAdjustmentType,
         stock_id: &str,
         limit_days: u32,
     ) -> anyhow::Result<Vec<Self>> {
-        let data = ch_client
-            .query(
-                r#"
-SELECT
-    date,
-    open,
-    close,
-    high,
-    low
-FROM (
+        let sql = adjusted_sql(
+            adj_type,
+            r#"
     SELECT
+        code,
         date,
         argMax(open, ts) as open,
         argMax(close, ts) as close,
         argMax(high, ts) as high,
         argMax(low, ts) as low
     FROM stock_zh_a_hist
-    WHERE adj_type = ?
+    WHERE adj_type = 'None'
         AND code = ?
     GROUP BY code, date
     ORDER BY date DESC
     LIMIT ?
-) AS sub
-ORDER BY date ASC 
-        "#,
-            )
-            .bind(adj_type)
+"#,
+            r#"
+    date,
+    round(open * factor, 2) AS open,
+    round(close * factor, 2) AS close,
+    round(high * factor, 2) AS high,
+    round(low * factor, 2) AS low
+"#,
+        );
+        let data = ch_client
+            .query(&sql)
+            .bind(stock_id)
             .bind(stock_id)
             .bind(limit_days)
+            .bind(stock_id)
             .fetch_all()
             .await?;
 
@@ -183,9 +183,10 @@ pub struct DailyTradingVolumeFetch {
 }
 
 impl DailyTradingVolumeFetch {
+    /// 成交量不受复权影响，所有复权方式返回相同的数据
     pub async fn fetch_with_limit(
         ch_client: &clickhouse::Client,
-        adj_type: StockAdjustmentType,
+        _adj_type: StockAdjustmentType,
         stock_id: &str,
         limit_days: u32,
     ) -> anyhow::Result<Vec<Self>> {
@@ -200,7 +201,7 @@ FROM (
         date,
         argMax(trading_volume, ts) as trading_volume
     FROM stock_zh_a_hist
-    WHERE adj_type = ?
+    WHERE adj_type = 'None'
         AND code = ?
     GROUP BY code, date
     ORDER BY date DESC
@@ -209,7 +210,6 @@ FROM (
 ORDER BY date ASC 
     "#,
             )
-            .bind(adj_type)
             .bind(stock_id)
             .bind(limit_days)
             .fetch_all()
@@ -238,24 +238,18 @@ pub struct DailyIndicatorFetch {
 }
 
 impl DailyIndicatorFetch {
+    /// 成交额、振幅、换手率以及涨跌幅不受复权影响，涨跌额按照复权因子换算
     pub async fn fetch_with_limit(
         ch_client: &clickhouse::Client,
         adj_type: StockAdjustmentType,
         stock_id: &str,
         limit_days: u32,
     ) -> anyhow::Result<Vec<Self>> {
-        let data = ch_client
-            .query(
-                r#"
-SELECT
-    date,
-    trading_value,
-    amplitude,
-    turnover_rate,
-    change_percentage,
-    change_amount
-FROM (
+        let sql = adjusted_sql(
+            adj_type,
+            r#"
     SELECT
+        code,
         date,
         argMax(trading_value, ts) as trading_value,
         argMax(amplitude, ts) as amplitude,
@@ -263,24 +257,167 @@ FROM (
         argMax(change_percentage, ts) as change_percentage,
         argMax(change_amount, ts) as change_amount
     FROM stock_zh_a_hist
-    WHERE adj_type = ?
+    WHERE adj_type = 'None'
         AND code = ?
     GROUP BY code, date
     ORDER BY date DESC
     LIMIT ?
-) AS sub
-ORDER BY date ASC 
-        "#,
-            )
-            .bind(adj_type)
+"#,
+            r#"
+    date,
+    trading_value,
+    amplitude,
+    turnover_rate,
+    change_percentage,
+    round(change_amount * factor, 2) AS change_amount
+"#,
+        );
+        let data = ch_client
+            .query(&sql)
+            .bind(stock_id)
             .bind(stock_id)
             .bind(limit_days)
+            .bind(stock_id)
             .fetch_all()
             .await?;
         Ok(data)
     }
 }
 
+/// 在不复权的日频数据之上计算复权数据的sql，`kline_sql`需要返回`code`以及`date`列，
+/// `columns`之中使用`factor`作为复权乘数，sql之中的参数依次为：
+/// 计算最新复权因子的股票代码、`kline_sql`之中的参数、计算当日复权因子的股票代码
+///
+/// 每一个交易日使用不晚于该日的最近一条复权因子，没有复权因子数据的股票返回不复权数据
+fn adjusted_sql(adj_type: StockAdjustmentType, kline_sql: &str, columns: &str) -> String {
+    let factor = match adj_type {
+        StockAdjustmentType::None => "1",
+        StockAdjustmentType::Forward => "day_factor / if(latest_factor > 0, latest_factor, 1)",
+        StockAdjustmentType::Backward => "day_factor",
+    };
+    format!(
+        r#"
+WITH
+    (
+        SELECT argMax(hfq_factor, date)
+        FROM stock_adjust_factor FINAL
+        WHERE code = ?
+    ) AS latest_factor
+SELECT {columns}
+FROM (
+    SELECT
+        kline.*,
+        if(adjust.hfq_factor > 0, adjust.hfq_factor, 1) AS day_factor,
+        {factor} AS factor
+    FROM ({kline_sql}) AS kline
+    ASOF LEFT JOIN (
+        SELECT code, date, hfq_factor
+        FROM stock_adjust_factor FINAL
+        WHERE code = ?
+    ) AS adjust
+    ON kline.code = adjust.code AND kline.date >= adjust.date
+) AS sub
+ORDER BY date ASC
+"#
+    )
+}
+
+/// 分时K线的周期
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema)]
+pub enum IntradayPeriod {
+    /// 1分钟K线
+    #[serde(rename = "1m")]
+    Minute1,
+    /// 5分钟K线
+    #[serde(rename = "5m")]
+    Minute5,
+}
+
+impl IntradayPeriod {
+    /// 对应的clickhouse表名，由`astock_realtime_data`的物化视图写入
+    pub fn table(&self) -> &'static str {
+        match self {
+            IntradayPeriod::Minute1 => "astock_bar_1m",
+            IntradayPeriod::Minute5 => "astock_bar_5m",
+        }
+    }
+}
+
+/// 由实时行情快照聚合得到的分时K线数据
+#[derive(Debug, Serialize, Deserialize, Row, ToSchema)]
+pub struct IntradayBarFetch {
+    /// K线的开始时间
+    #[serde(deserialize_with = "clickhouse::serde::chrono::datetime::deserialize")]
+    pub bar_time: DateTime<Utc>,
+    /// 开盘价(元)
+    pub open: f64,
+    /// 最高价(元)
+    pub high: f64,
+    /// 最低价(元)
+    pub low: f64,
+    /// 收盘价(元)
+    pub close: f64,
+    /// 该周期之内的成交量，注意单位(手)，当天第一根K线包括集合竞价的成交量
+    pub volume: f64,
+    /// 该周期之内的成交额，注意单位(元)
+    pub value: f64,
+}
+
+impl IntradayBarFetch {
+    /// 获取对应`stock_id`在`date`当天的分时K线，`date`为空时使用该股票有数据的最近一天，
+    /// 每个周期的成交量为该周期结束时的累计成交量减去上一个周期结束时的累计成交量
+    pub async fn fetch_with_date(
+        ch_client: &clickhouse::Client,
+        period: IntradayPeriod,
+        stock_id: &str,
+        date: Option<NaiveDate>,
+    ) -> anyhow::Result<Vec<Self>> {
+        let date_filter = match date {
+            Some(_) => "?".to_owned(),
+            None => format!(
+                "(SELECT max(toDate(bar_time)) FROM {} WHERE code = ?)",
+                period.table()
+            ),
+        };
+        let sql = format!(
+            r#"
+SELECT
+    bar_time,
+    open,
+    high,
+    low,
+    close,
+    greatest(volume_cum - lagInFrame(volume_cum, 1, 0) OVER w, 0) AS volume,
+    greatest(value_cum - lagInFrame(value_cum, 1, 0) OVER w, 0) AS value
+FROM (
+    SELECT
+        bar_time,
+        argMinMerge(open) AS open,
+        max(high) AS high,
+        min(low) AS low,
+        argMaxMerge(close) AS close,
+        max(volume_cum) AS volume_cum,
+        max(value_cum) AS value_cum
+    FROM {table}
+    WHERE code = ?
+        AND toDate(bar_time) = {date_filter}
+    GROUP BY bar_time
+)
+WINDOW w AS (ORDER BY bar_time ASC ROWS BETWEEN UNBOUNDED PRECEDING AND CURRENT ROW)
+ORDER BY bar_time ASC
+        "#,
+            table = period.table(),
+        );
+
+        let query = ch_client.query(&sql).bind(stock_id);
+        let query = match date {
+            Some(date) => query.bind(date.to_string()),
+            None => query.bind(stock_id),
+        };
+        Ok(query.fetch_all().await?)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use crate::{repository::akshare::StockAdjustmentType, utils::TEST_CH_CLIENT};
@@ -296,6 +433,20 @@ mod test {
         println!("{}", serde_json::to_string_pretty(&data).unwrap())
     }
 
+    #[tokio::test]
+    async fn test_fetch_intraday_bars() {
+        let data = IntradayBarFetch::fetch_with_date(
+            &TEST_CH_CLIENT,
+            IntradayPeriod::Minute5,
+            "603777",
+            None,
+        )
+        .await
+        .unwrap();
+
+        println!("{}", serde_json::to_string_pretty(&data).unwrap())
+    }
+
     #[tokio::test]
     async fn test_fetch_daily_stock_infos() {
         let data = DailyKlineFetch::fetch_with_limit(
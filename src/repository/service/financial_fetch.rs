@@ -0,0 +1,267 @@
+use chrono::NaiveDate;
+use clickhouse::Row;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::repository::akshare::FinancialPeriodType;
+
+/// 同花顺-财务指标-主要指标，金额单位为元，增长率、利润率、收益率以及资产负债率单位为%，无法解析的指标为`null`
+#[derive(Debug, Serialize, Deserialize, Row, ToSchema)]
+pub struct FinancialAbstractFetch {
+    /// 报告期末日期，格式为YYYY-MM-DD，按年度的数据为当年12月31日
+    #[schema(example = "2025-03-31")]
+    #[serde(deserialize_with = "clickhouse::serde::chrono::date::deserialize")]
+    pub report_date: NaiveDate,
+    /// 净利润(元)
+    pub net_profit: Option<f64>,
+    /// 净利润同比增长率(%)
+    pub net_profit_growth_rate: Option<f64>,
+    /// 扣非净利润(元)
+    pub deducted_net_profit: Option<f64>,
+    /// 扣非净利润同比增长率(%)
+    pub deducted_net_profit_growth_rate: Option<f64>,
+    /// 营业总收入(元)
+    pub total_revenue: Option<f64>,
+    /// 营业总收入同比增长率(%)
+    pub total_revenue_growth_rate: Option<f64>,
+    /// 基本每股收益(元)
+    pub basic_eps: Option<f64>,
+    /// 每股净资产(元)
+    pub net_assets_per_share: Option<f64>,
+    /// 每股资本公积金(元)
+    pub capital_reserve_per_share: Option<f64>,
+    /// 每股未分配利润(元)
+    pub undistributed_profit_per_share: Option<f64>,
+    /// 每股经营现金流(元)
+    pub operating_cash_flow_per_share: Option<f64>,
+    /// 销售净利率(%)
+    pub net_profit_margin: Option<f64>,
+    /// 销售毛利率(%)
+    pub gross_profit_margin: Option<f64>,
+    /// 净资产收益率(%)
+    pub roe: Option<f64>,
+    /// 净资产收益率-摊薄(%)
+    pub roe_diluted: Option<f64>,
+    /// 营业周期(天)
+    pub operating_cycle: Option<f64>,
+    /// 存货周转率(次)
+    pub inventory_turnover: Option<f64>,
+    /// 存货周转天数(天)
+    pub inventory_turnover_days: Option<f64>,
+    /// 应收账款周转天数(天)
+    pub accounts_receivable_turnover_days: Option<f64>,
+    /// 流动比率
+    pub current_ratio: Option<f64>,
+    /// 速动比率
+    pub quick_ratio: Option<f64>,
+    /// 保守速动比率
+    pub conservative_quick_ratio: Option<f64>,
+    /// 产权比率
+    pub equity_ratio: Option<f64>,
+    /// 资产负债率(%)
+    pub debt_asset_ratio: Option<f64>,
+}
+
+impl FinancialAbstractFetch {
+    /// 获取对应`stock_id`某一种报告期类型的所有财务指标，按照报告期从新到旧排序
+    pub async fn fetch_with_period(
+        ch_client: &clickhouse::Client,
+        stock_id: &str,
+        period_type: FinancialPeriodType,
+    ) -> anyhow::Result<Vec<Self>> {
+        let data = ch_client
+            .query(
+                r#"
+SELECT
+    report_date,
+    net_profit,
+    net_profit_growth_rate,
+    deducted_net_profit,
+    deducted_net_profit_growth_rate,
+    total_revenue,
+    total_revenue_growth_rate,
+    basic_eps,
+    net_assets_per_share,
+    capital_reserve_per_share,
+    undistributed_profit_per_share,
+    operating_cash_flow_per_share,
+    net_profit_margin,
+    gross_profit_margin,
+    roe,
+    roe_diluted,
+    operating_cycle,
+    inventory_turnover,
+    inventory_turnover_days,
+    accounts_receivable_turnover_days,
+    current_ratio,
+    quick_ratio,
+    conservative_quick_ratio,
+    equity_ratio,
+    debt_asset_ratio
+FROM stock_financial_abstract_ths FINAL
+WHERE code = ? AND period_type = ?
+ORDER BY report_date DESC
+"#,
+            )
+            .bind(stock_id)
+            .bind(format!("{:?}", period_type))
+            .fetch_all()
+            .await?;
+        Ok(data)
+    }
+}
+
+/// 可以用于跨股票排名的财务指标
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FinancialMetric {
+    /// 净利润
+    NetProfit,
+    /// 净利润同比增长率
+    NetProfitGrowthRate,
+    /// 扣非净利润同比增长率
+    DeductedNetProfitGrowthRate,
+    /// 营业总收入
+    TotalRevenue,
+    /// 营业总收入同比增长率
+    TotalRevenueGrowthRate,
+    /// 基本每股收益
+    BasicEps,
+    /// 每股经营现金流
+    OperatingCashFlowPerShare,
+    /// 销售净利率
+    NetProfitMargin,
+    /// 销售毛利率
+    GrossProfitMargin,
+    /// 净资产收益率
+    Roe,
+    /// 资产负债率
+    DebtAssetRatio,
+}
+
+impl FinancialMetric {
+    /// 对应的clickhouse列名
+    pub fn column(&self) -> &'static str {
+        match self {
+            FinancialMetric::NetProfit => "net_profit",
+            FinancialMetric::NetProfitGrowthRate => "net_profit_growth_rate",
+            FinancialMetric::DeductedNetProfitGrowthRate => "deducted_net_profit_growth_rate",
+            FinancialMetric::TotalRevenue => "total_revenue",
+            FinancialMetric::TotalRevenueGrowthRate => "total_revenue_growth_rate",
+            FinancialMetric::BasicEps => "basic_eps",
+            FinancialMetric::OperatingCashFlowPerShare => "operating_cash_flow_per_share",
+            FinancialMetric::NetProfitMargin => "net_profit_margin",
+            FinancialMetric::GrossProfitMargin => "gross_profit_margin",
+            FinancialMetric::Roe => "roe",
+            FinancialMetric::DebtAssetRatio => "debt_asset_ratio",
+        }
+    }
+}
+
+/// 某一个报告期之中按照某一项财务指标排名的股票
+#[derive(Debug, Serialize, Deserialize, Row, ToSchema)]
+pub struct FinancialRankFetch {
+    /// 排名，从1开始
+    pub rank: u64,
+    /// 股票代码
+    #[schema(example = "603777")]
+    pub code: String,
+    /// 股票简称
+    pub name: String,
+    /// 报告期末日期，格式为YYYY-MM-DD
+    #[schema(example = "2025-03-31")]
+    #[serde(deserialize_with = "clickhouse::serde::chrono::date::deserialize")]
+    pub report_date: NaiveDate,
+    /// 排名使用的财务指标的值
+    pub value: f64,
+}
+
+impl FinancialRankFetch {
+    /// 获取某一个报告期之中`metric`最高(`ascending`为`true`时最低)的`limit`只股票，没有该指标的股票不参与排名，
+    /// `report_date`为空时使用已经收集到的最新报告期
+    pub async fn fetch_top(
+        ch_client: &clickhouse::Client,
+        period_type: FinancialPeriodType,
+        metric: FinancialMetric,
+        report_date: Option<NaiveDate>,
+        ascending: bool,
+        limit: u32,
+    ) -> anyhow::Result<Vec<Self>> {
+        let period_type = format!("{:?}", period_type);
+        let report_date = match report_date {
+            Some(report_date) => report_date,
+            None => {
+                let latest: Option<LatestReportDate> = ch_client
+                    .query(
+                        "SELECT max(report_date) AS report_date FROM stock_financial_abstract_ths \
+                        WHERE period_type = ? HAVING count() > 0",
+                    )
+                    .bind(&period_type)
+                    .fetch_optional()
+                    .await?;
+                let Some(latest) = latest else {
+                    return Ok(Vec::new());
+                };
+                latest.report_date
+            }
+        };
+
+        let sql = format!(
+            r#"
+SELECT
+    row_number() OVER (ORDER BY value {order}) AS rank,
+    code,
+    name,
+    report_date,
+    value
+FROM (
+    SELECT code, report_date, assumeNotNull({column}) AS value
+    FROM stock_financial_abstract_ths FINAL
+    WHERE period_type = ? AND report_date = ? AND {column} IS NOT NULL
+) AS financial
+LEFT JOIN (
+    SELECT symbol, name FROM security_master FINAL WHERE kind = 'Stock'
+) AS security ON financial.code = security.symbol
+ORDER BY value {order}
+LIMIT ?
+"#,
+            column = metric.column(),
+            order = if ascending { "ASC" } else { "DESC" },
+        );
+        let data = ch_client
+            .query(&sql)
+            .bind(&period_type)
+            .bind(report_date.to_string())
+            .bind(limit)
+            .fetch_all()
+            .await?;
+        Ok(data)
+    }
+}
+
+#[derive(Debug, Row, Deserialize)]
+struct LatestReportDate {
+    #[serde(deserialize_with = "clickhouse::serde::chrono::date::deserialize")]
+    report_date: NaiveDate,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::utils::TEST_CH_CLIENT;
+
+    #[tokio::test]
+    async fn test_fetch_financial_rank() {
+        let data = FinancialRankFetch::fetch_top(
+            &TEST_CH_CLIENT,
+            FinancialPeriodType::Quarter,
+            FinancialMetric::NetProfitGrowthRate,
+            None,
+            false,
+            50,
+        )
+        .await
+        .unwrap();
+        println!("{}", serde_json::to_string_pretty(&data).unwrap());
+    }
+}
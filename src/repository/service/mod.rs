@@ -1,7 +1,13 @@
 mod aindex_fetch;
 mod alarm_hist;
 mod astock_fetch;
+mod financial_fetch;
+mod sector_fetch;
+mod security_fetch;
 
 pub use aindex_fetch::*;
 pub use alarm_hist::*;
 pub use astock_fetch::*;
+pub use financial_fetch::*;
+pub use sector_fetch::*;
+pub use security_fetch::*;
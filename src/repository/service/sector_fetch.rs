@@ -0,0 +1,346 @@
+use chrono::NaiveDate;
+use clickhouse::Row;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::repository::akshare::BoardKind;
+
+/// 某一个交易日收盘之后的板块行情排名
+#[derive(Debug, Serialize, Deserialize, Row, ToSchema)]
+pub struct BoardRankFetch {
+    /// 交易日期，格式为YYYY-MM-DD
+    #[schema(example = "2025-05-08")]
+    #[serde(deserialize_with = "clickhouse::serde::chrono::date::deserialize")]
+    pub date: NaiveDate,
+    /// 板块代码
+    #[schema(example = "BK1027")]
+    pub code: String,
+    /// 板块名称
+    pub name: String,
+    /// 按照涨跌幅的排名
+    pub rank: u32,
+    /// 最新价
+    pub latest_price: Option<f64>,
+    /// 涨跌幅(%)
+    pub change_percentage: Option<f64>,
+    /// 总市值(元)
+    pub total_market_value: Option<f64>,
+    /// 换手率(%)
+    pub turnover_rate: Option<f64>,
+    /// 上涨家数
+    pub rise_count: u32,
+    /// 下跌家数
+    pub fall_count: u32,
+    /// 领涨股票
+    pub leading_stock: String,
+    /// 领涨股票-涨跌幅(%)
+    pub leading_stock_change_percentage: Option<f64>,
+}
+
+impl BoardRankFetch {
+    /// 获取某一个交易日涨跌幅最高(`ascending`为`true`时最低)的`limit`个板块，
+    /// `date`为空时使用已经收集到的最近一个交易日
+    pub async fn fetch_with_date(
+        ch_client: &clickhouse::Client,
+        kind: BoardKind,
+        date: Option<NaiveDate>,
+        ascending: bool,
+        limit: u32,
+    ) -> anyhow::Result<Vec<Self>> {
+        let kind = format!("{:?}", kind);
+        let date = match date {
+            Some(date) => date,
+            None => {
+                let latest: Option<LatestBoardDate> = ch_client
+                    .query(
+                        "SELECT max(date) AS date FROM stock_board_em \
+                        WHERE kind = ? HAVING count() > 0",
+                    )
+                    .bind(&kind)
+                    .fetch_optional()
+                    .await?;
+                let Some(latest) = latest else {
+                    return Ok(Vec::new());
+                };
+                latest.date
+            }
+        };
+
+        let sql = format!(
+            r#"
+SELECT
+    date,
+    code,
+    name,
+    rank,
+    latest_price,
+    change_percentage,
+    total_market_value,
+    turnover_rate,
+    rise_count,
+    fall_count,
+    leading_stock,
+    leading_stock_change_percentage
+FROM stock_board_em FINAL
+WHERE kind = ? AND date = ?
+ORDER BY change_percentage {order}
+LIMIT ?
+"#,
+            order = if ascending { "ASC" } else { "DESC" },
+        );
+        let data = ch_client
+            .query(&sql)
+            .bind(&kind)
+            .bind(date.to_string())
+            .bind(limit)
+            .fetch_all()
+            .await?;
+        Ok(data)
+    }
+}
+
+#[derive(Debug, Row, Deserialize)]
+struct LatestBoardDate {
+    #[serde(deserialize_with = "clickhouse::serde::chrono::date::deserialize")]
+    date: NaiveDate,
+}
+
+/// 板块日频K线数据
+#[derive(Debug, Serialize, Deserialize, Row, ToSchema)]
+pub struct BoardKlineFetch {
+    /// 数据日期，格式为YYYY-MM-DD
+    #[serde(deserialize_with = "clickhouse::serde::chrono::date::deserialize")]
+    pub date: NaiveDate,
+    /// 开盘
+    pub open: f64,
+    /// 收盘
+    pub close: f64,
+    /// 最高
+    pub high: f64,
+    /// 最低
+    pub low: f64,
+    /// 涨跌幅(%)
+    pub change_percentage: f64,
+    /// 成交量(手)
+    pub trading_volume: f64,
+    /// 成交额(元)
+    pub trading_value: f64,
+    /// 换手率(%)
+    pub turnover_rate: f64,
+}
+
+impl BoardKlineFetch {
+    /// 获取对应板块最近`limit_days`个交易日的K线
+    pub async fn fetch_with_limit(
+        ch_client: &clickhouse::Client,
+        kind: BoardKind,
+        board_code: &str,
+        limit_days: u32,
+    ) -> anyhow::Result<Vec<Self>> {
+        let data = ch_client
+            .query(
+                r#"
+SELECT
+    date,
+    open,
+    close,
+    high,
+    low,
+    change_percentage,
+    trading_volume,
+    trading_value,
+    turnover_rate
+FROM (
+    SELECT *
+    FROM stock_board_hist_em FINAL
+    WHERE kind = ? AND code = ?
+    ORDER BY date DESC
+    LIMIT ?
+) AS sub
+ORDER BY date ASC
+"#,
+            )
+            .bind(format!("{:?}", kind))
+            .bind(board_code)
+            .bind(limit_days)
+            .fetch_all()
+            .await?;
+        Ok(data)
+    }
+}
+
+/// 一只股票所属的板块
+#[derive(Debug, Serialize, Deserialize, Row, ToSchema)]
+pub struct StockBoardFetch {
+    pub kind: BoardKind,
+    /// 板块代码
+    #[schema(example = "BK1027")]
+    pub board_code: String,
+    /// 板块名称
+    pub board_name: String,
+    /// 第一次出现在板块快照之中的日期，格式为YYYY-MM-DD
+    #[serde(deserialize_with = "clickhouse::serde::chrono::date::deserialize")]
+    pub first_date: NaiveDate,
+    /// 最后一次出现在板块快照之中的日期，格式为YYYY-MM-DD，
+    /// 中间调出以及重新调入的记录参见板块成份股变动
+    #[serde(deserialize_with = "clickhouse::serde::chrono::date::deserialize")]
+    pub last_date: NaiveDate,
+    /// 是否为当前成份股，即出现在该板块最新一次的快照之中
+    pub is_current: bool,
+}
+
+impl StockBoardFetch {
+    /// 获取一只股票所属的行业板块以及概念板块，`include_history`为`true`时同时返回已经调出的板块
+    pub async fn fetch_with_stock(
+        ch_client: &clickhouse::Client,
+        stock_id: &str,
+        include_history: bool,
+    ) -> anyhow::Result<Vec<Self>> {
+        let data = ch_client
+            .query(
+                r#"
+SELECT
+    member.kind AS kind,
+    member.board_code AS board_code,
+    member.board_name AS board_name,
+    member.first_date AS first_date,
+    member.last_date AS last_date,
+    member.last_date = board.latest_date AS is_current
+FROM (
+    SELECT
+        kind,
+        board_code,
+        argMax(board_name, date) AS board_name,
+        min(date) AS first_date,
+        max(date) AS last_date
+    FROM stock_board_cons_em
+    WHERE stock_code = ?
+    GROUP BY kind, board_code
+) AS member
+INNER JOIN (
+    SELECT kind, board_code, max(date) AS latest_date
+    FROM stock_board_cons_em
+    GROUP BY kind, board_code
+) AS board
+ON member.kind = board.kind AND member.board_code = board.board_code
+WHERE ? OR is_current
+ORDER BY kind, is_current DESC, board_code
+"#,
+            )
+            .bind(stock_id)
+            .bind(include_history)
+            .fetch_all()
+            .await?;
+        Ok(data)
+    }
+}
+
+/// 板块成份股的一次调入或者调出
+#[derive(Debug, Serialize, Deserialize, Row, ToSchema)]
+pub struct BoardConsChangeFetch {
+    /// 发现变动的快照日期，格式为YYYY-MM-DD
+    #[serde(deserialize_with = "clickhouse::serde::chrono::date::deserialize")]
+    pub date: NaiveDate,
+    /// 6位股票代码
+    #[schema(example = "603777")]
+    pub stock_code: String,
+    /// 股票名称
+    pub stock_name: String,
+    /// `true`为调入，`false`为调出
+    pub added: bool,
+}
+
+impl BoardConsChangeFetch {
+    /// 比较板块相邻两次成份股快照，获取最近`limit_days`天之内的调入以及调出，按照日期从新到旧排序
+    pub async fn fetch_with_board(
+        ch_client: &clickhouse::Client,
+        kind: BoardKind,
+        board_code: &str,
+        limit_days: u32,
+    ) -> anyhow::Result<Vec<Self>> {
+        let kind = format!("{:?}", kind);
+        let data = ch_client
+            .query(
+                r#"
+SELECT
+    change.date AS date,
+    change.stock_code AS stock_code,
+    name.stock_name AS stock_name,
+    change.added AS added
+FROM (
+    SELECT date, change.1 AS stock_code, change.2 AS added
+    FROM (
+        SELECT
+            date,
+            arrayConcat(
+                arrayMap(x -> (x, true), arrayFilter(x -> NOT has(prev_members, x), members)),
+                arrayMap(x -> (x, false), arrayFilter(x -> NOT has(members, x), prev_members))
+            ) AS changes
+        FROM (
+            SELECT
+                date,
+                members,
+                lagInFrame(members) OVER w AS prev_members,
+                row_number() OVER w AS seq
+            FROM (
+                SELECT date, groupArray(stock_code) AS members
+                FROM stock_board_cons_em FINAL
+                WHERE kind = ? AND board_code = ?
+                GROUP BY date
+            )
+            WINDOW w AS (ORDER BY date ASC ROWS BETWEEN UNBOUNDED PRECEDING AND CURRENT ROW)
+        )
+        WHERE seq > 1 AND date >= today() - ?
+    )
+    ARRAY JOIN changes AS change
+) AS change
+LEFT JOIN (
+    SELECT stock_code, argMax(stock_name, date) AS stock_name
+    FROM stock_board_cons_em
+    WHERE kind = ? AND board_code = ?
+    GROUP BY stock_code
+) AS name
+ON change.stock_code = name.stock_code
+ORDER BY date DESC, added DESC, stock_code
+"#,
+            )
+            .bind(&kind)
+            .bind(board_code)
+            .bind(limit_days)
+            .bind(&kind)
+            .bind(board_code)
+            .fetch_all()
+            .await?;
+        Ok(data)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::utils::TEST_CH_CLIENT;
+
+    #[tokio::test]
+    async fn test_fetch_sector() {
+        let data =
+            BoardRankFetch::fetch_with_date(&TEST_CH_CLIENT, BoardKind::Industry, None, false, 10)
+                .await
+                .unwrap();
+        println!("{}", serde_json::to_string_pretty(&data).unwrap());
+
+        let data = StockBoardFetch::fetch_with_stock(&TEST_CH_CLIENT, "603777", false)
+            .await
+            .unwrap();
+        println!("{}", serde_json::to_string_pretty(&data).unwrap());
+
+        let data = BoardConsChangeFetch::fetch_with_board(
+            &TEST_CH_CLIENT,
+            BoardKind::Industry,
+            "BK1027",
+            30,
+        )
+        .await
+        .unwrap();
+        println!("{}", serde_json::to_string_pretty(&data).unwrap());
+    }
+}
@@ -0,0 +1,51 @@
+use clickhouse::Row;
+use serde::Deserialize;
+
+use crate::{
+    repository::akshare::SecurityKind,
+    security::{Exchange, SecurityCode},
+};
+
+/// 判断一个代码是否存在于证券主表之中，支持[`SecurityCode`]可以解析的所有写法，无法解析的代码视为不存在
+pub async fn is_security_code_exists(
+    ch_client: &clickhouse::Client,
+    code: &str,
+    kind: SecurityKind,
+) -> anyhow::Result<bool> {
+    let Ok(code) = code.parse::<SecurityCode>() else {
+        return Ok(false);
+    };
+    Ok(ch_client
+        .query("SELECT exists(SELECT 1 FROM security_master WHERE code = ? AND kind = ?) AS code_exists")
+        .bind(code.suffixed())
+        .bind(kind as u8)
+        .fetch_one()
+        .await?)
+}
+
+#[derive(Debug, Row, Deserialize)]
+struct ListedCode {
+    symbol: String,
+    exchange: Exchange,
+}
+
+/// 获取证券主表之中所有正常上市的某一种证券的代码，作为数据收集任务的代码范围
+pub async fn listed_security_codes(
+    ch_client: &clickhouse::Client,
+    kind: SecurityKind,
+) -> anyhow::Result<Vec<SecurityCode>> {
+    let listed: Vec<ListedCode> = ch_client
+        .query(
+            "SELECT symbol, exchange FROM security_master FINAL \
+            WHERE kind = ? AND status = 'Listed' \
+            ORDER BY code",
+        )
+        .bind(kind as u8)
+        .fetch_all()
+        .await?;
+
+    listed
+        .into_iter()
+        .map(|listed| SecurityCode::new(listed.exchange, &listed.symbol))
+        .collect()
+}
@@ -4,7 +4,7 @@ use clickhouse::Row;
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
-use crate::utils::with_base_url;
+use crate::limiter::aktools_get;
 
 /// 实时行情数据-新浪
 ///
@@ -46,12 +46,25 @@ pub struct AkStockZhIndexSpotSina {
     pub value: f64,
 }
 
-/// 历史行情数据-新浪
+impl AkStockZhIndexSpotSina {
+    pub async fn from_astock_api(reqwest_client: &reqwest::Client) -> anyhow::Result<Vec<Self>> {
+        let data: Vec<Self> = aktools_get(reqwest_client, "/stock_zh_index_spot_sina")
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        Ok(data)
+    }
+}
+
+/// 指数历史行情数据-东方财富
 ///
-/// 接口: stock_zh_index_daily 股票指数的历史数据按日频率更新
+/// 接口: stock_zh_index_daily_em 股票指数的历史数据按日频率更新，支持start_date以及end_date参数
 #[derive(Debug, Deserialize)]
 pub struct AkStockZhIndexDaily {
-    /// 时间戳
+    /// 日期
     pub date: String,
     /// 最高
     pub high: f64,
@@ -95,8 +108,7 @@ pub struct AkIndexStockInfo {
 
 impl AkIndexStockInfo {
     pub async fn from_astock_api(reqwest_client: &reqwest::Client) -> anyhow::Result<Vec<Self>> {
-        let data: Vec<Self> = reqwest_client
-            .get(with_base_url("/index_stock_info"))
+        let data: Vec<Self> = aktools_get(reqwest_client, "/index_stock_info")
             .send()
             .await?
             .error_for_status()?
@@ -105,3 +117,33 @@ impl AkIndexStockInfo {
         Ok(data)
     }
 }
+
+/// 中证指数-指数成份股以及权重，权重每个月更新一次
+///
+/// 接口: index_stock_cons_weight_csindex，symbol为6位指数代码，例如`000300`
+///
+/// akshare api数据模型
+#[derive(Debug, Deserialize)]
+pub struct AkIndexStockConsWeightCsindex {
+    /// 权重生效日期
+    #[serde(rename(deserialize = "日期"))]
+    pub date: String,
+    /// 指数代码
+    #[serde(rename(deserialize = "指数代码"))]
+    pub index_code: String,
+    /// 指数名称
+    #[serde(rename(deserialize = "指数名称"))]
+    pub index_name: String,
+    /// 成分券代码
+    #[serde(rename(deserialize = "成分券代码"))]
+    pub stock_code: String,
+    /// 成分券名称
+    #[serde(rename(deserialize = "成分券名称"))]
+    pub stock_name: String,
+    /// 交易所，例如`上海证券交易所`
+    #[serde(rename(deserialize = "交易所"))]
+    pub exchange: String,
+    /// 权重(%)
+    #[serde(rename(deserialize = "权重"))]
+    pub weight: f64,
+}
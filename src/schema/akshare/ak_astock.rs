@@ -4,7 +4,7 @@ use serde::{Deserialize, Deserializer, Serialize};
 use serde_json::Value;
 use utoipa::ToSchema;
 
-use crate::utils::with_base_url;
+use crate::limiter::aktools_get;
 
 /// Real-time market data 数据来源为东方财经
 #[derive(Debug, Deserialize, Serialize)]
@@ -123,6 +123,21 @@ pub struct AkStockZhAHist {
     pub code: String,
 }
 
+/// 新浪财经-A股后复权因子，复权因子只在除权除息日变化，每一条数据从`date`开始生效
+///
+/// 接口: stock_zh_a_daily，symbol为带交易所前缀的代码(例如`sh600000`)，adjust="hfq-factor"
+///
+/// akshare api数据模型
+#[derive(Debug, Deserialize)]
+pub struct AkStockZhADailyHfqFactor {
+    /// 除权除息日期
+    #[serde(deserialize_with = "always_string")]
+    pub date: String,
+    /// 后复权因子，不复权价格乘以该因子为后复权价格
+    #[serde(deserialize_with = "always_string")]
+    pub hfq_factor: String,
+}
+
 /// 沪深港通历史数据
 ///
 /// 目标地址: https://data.eastmoney.com/hsgt/index.html  
@@ -255,8 +270,7 @@ impl AkStockNewsEm {
         reqwest_client: &reqwest::Client,
         symbol: &str,
     ) -> anyhow::Result<Vec<Self>> {
-        let res: Vec<Self> = reqwest_client
-            .get(with_base_url("/stock_news_em"))
+        let res: Vec<Self> = aktools_get(reqwest_client, "/stock_news_em")
             .query(&[("symbol", symbol)])
             .send()
             .await?
@@ -485,8 +499,7 @@ impl AkStockFinancialAbstractThs {
         symbol: &str,
         indicator: &str,
     ) -> anyhow::Result<Vec<Self>> {
-        let res: Vec<Self> = reqwest_client
-            .get(with_base_url("/stock_financial_abstract_ths"))
+        let res: Vec<Self> = aktools_get(reqwest_client, "/stock_financial_abstract_ths")
             .query(&[("symbol", symbol), ("indicator", indicator)])
             .send()
             .await?
@@ -537,8 +550,7 @@ impl AkStockRankCxgThs {
         reqwest_client: &reqwest::Client,
         symbol: &str,
     ) -> anyhow::Result<Vec<Self>> {
-        let res: Vec<Self> = reqwest_client
-            .get(with_base_url("/stock_rank_cxg_ths"))
+        let res: Vec<Self> = aktools_get(reqwest_client, "/stock_rank_cxg_ths")
             .query(&[("symbol", symbol)])
             .send()
             .await?
@@ -589,8 +601,7 @@ impl AkStockRankCxdThs {
         reqwest_client: &reqwest::Client,
         symbol: &str,
     ) -> anyhow::Result<Vec<Self>> {
-        let res: Vec<Self> = reqwest_client
-            .get(with_base_url("/stock_rank_cxd_ths"))
+        let res: Vec<Self> = aktools_get(reqwest_client, "/stock_rank_cxd_ths")
             .query(&[("symbol", symbol)])
             .send()
             .await?
@@ -643,8 +654,7 @@ pub struct AkStockRankLxszThs {
 impl AkStockRankLxszThs {
     /// 从akshare获取对应的一组数据
     pub async fn from_astock_api(reqwest_client: &reqwest::Client) -> anyhow::Result<Vec<Self>> {
-        let res: Vec<Self> = reqwest_client
-            .get(with_base_url("/stock_rank_lxsz_ths"))
+        let res: Vec<Self> = aktools_get(reqwest_client, "/stock_rank_lxsz_ths")
             .send()
             .await?
             .error_for_status()?
@@ -696,8 +706,7 @@ pub struct AkStockRankLxxdThs {
 impl AkStockRankLxxdThs {
     /// 从akshare获取对应的一组数据
     pub async fn from_astock_api(reqwest_client: &reqwest::Client) -> anyhow::Result<Vec<Self>> {
-        let res: Vec<Self> = reqwest_client
-            .get(with_base_url("/stock_rank_lxxd_ths"))
+        let res: Vec<Self> = aktools_get(reqwest_client, "/stock_rank_lxxd_ths")
             .send()
             .await?
             .error_for_status()?
@@ -749,8 +758,7 @@ pub struct AkStockRankCxflThs {
 impl AkStockRankCxflThs {
     /// 从akshare获取到对应的数据
     pub async fn from_astock_api(reqwest_client: &reqwest::Client) -> anyhow::Result<Vec<Self>> {
-        let res: Vec<Self> = reqwest_client
-            .get(with_base_url("/stock_rank_cxfl_ths"))
+        let res: Vec<Self> = aktools_get(reqwest_client, "/stock_rank_cxfl_ths")
             .send()
             .await?
             .error_for_status()?
@@ -801,8 +809,7 @@ pub struct AkStockRankCxslThs {
 
 impl AkStockRankCxslThs {
     pub async fn from_astock_api(reqwest_client: &reqwest::Client) -> anyhow::Result<Vec<Self>> {
-        let res: Vec<Self> = reqwest_client
-            .get(with_base_url("/stock_rank_cxsl_ths"))
+        let res: Vec<Self> = aktools_get(reqwest_client, "/stock_rank_cxsl_ths")
             .send()
             .await?
             .error_for_status()?
@@ -858,8 +865,7 @@ impl AkStockIndividualInfoEm {
             value: Value,
         }
 
-        let raw_json = reqwest_client
-            .get(with_base_url("/stock_individual_info_em"))
+        let raw_json = aktools_get(reqwest_client, "/stock_individual_info_em")
             .query(&[("symbol", stock_code)])
             .send()
             .await?
@@ -942,8 +948,162 @@ pub struct AkStockZhAStEm {
 
 impl AkStockZhAStEm {
     pub async fn from_astock_api(reqwest_client: &reqwest::Client) -> anyhow::Result<Vec<Self>> {
-        let data: Vec<Self> = reqwest_client
-            .get(with_base_url("/stock_zh_a_st_em"))
+        let data: Vec<Self> = aktools_get(reqwest_client, "/stock_zh_a_st_em")
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        Ok(data)
+    }
+}
+
+/// 上海证券交易所股票列表
+///
+/// 接口: stock_info_sh_name_code，symbol可选{"主板A股", "主板B股", "科创板"}
+#[derive(Debug, Deserialize)]
+pub struct AkStockInfoShNameCode {
+    /// 证券代码
+    #[serde(deserialize_with = "always_string", rename(deserialize = "证券代码"))]
+    pub code: String,
+    /// 证券简称
+    #[serde(rename(deserialize = "证券简称"))]
+    pub name: String,
+    /// 上市日期
+    #[serde(deserialize_with = "always_string", rename(deserialize = "上市日期"))]
+    pub list_date: String,
+}
+
+impl AkStockInfoShNameCode {
+    pub async fn from_astock_api(
+        reqwest_client: &reqwest::Client,
+        symbol: &str,
+    ) -> anyhow::Result<Vec<Self>> {
+        let data: Vec<Self> = aktools_get(reqwest_client, "/stock_info_sh_name_code")
+            .query(&[("symbol", symbol)])
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        Ok(data)
+    }
+}
+
+/// 深圳证券交易所A股列表
+///
+/// 接口: stock_info_sz_name_code，symbol="A股列表"
+#[derive(Debug, Deserialize)]
+pub struct AkStockInfoSzNameCode {
+    /// A股代码
+    #[serde(deserialize_with = "always_string", rename(deserialize = "A股代码"))]
+    pub code: String,
+    /// A股简称
+    #[serde(rename(deserialize = "A股简称"))]
+    pub name: String,
+    /// A股上市日期
+    #[serde(
+        deserialize_with = "always_string",
+        rename(deserialize = "A股上市日期")
+    )]
+    pub list_date: String,
+}
+
+impl AkStockInfoSzNameCode {
+    pub async fn from_astock_api(reqwest_client: &reqwest::Client) -> anyhow::Result<Vec<Self>> {
+        let data: Vec<Self> = aktools_get(reqwest_client, "/stock_info_sz_name_code")
+            .query(&[("symbol", "A股列表")])
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        Ok(data)
+    }
+}
+
+/// 北京证券交易所股票列表
+///
+/// 接口: stock_info_bj_name_code
+#[derive(Debug, Deserialize)]
+pub struct AkStockInfoBjNameCode {
+    /// 证券代码
+    #[serde(deserialize_with = "always_string", rename(deserialize = "证券代码"))]
+    pub code: String,
+    /// 证券简称
+    #[serde(rename(deserialize = "证券简称"))]
+    pub name: String,
+    /// 上市日期
+    #[serde(deserialize_with = "always_string", rename(deserialize = "上市日期"))]
+    pub list_date: String,
+}
+
+impl AkStockInfoBjNameCode {
+    pub async fn from_astock_api(reqwest_client: &reqwest::Client) -> anyhow::Result<Vec<Self>> {
+        let data: Vec<Self> = aktools_get(reqwest_client, "/stock_info_bj_name_code")
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        Ok(data)
+    }
+}
+
+/// 上海证券交易所终止上市公司
+///
+/// 接口: stock_info_sh_delist，symbol="全部"
+#[derive(Debug, Deserialize)]
+pub struct AkStockInfoShDelist {
+    /// 公司代码
+    #[serde(deserialize_with = "always_string", rename(deserialize = "公司代码"))]
+    pub code: String,
+    /// 公司简称
+    #[serde(rename(deserialize = "公司简称"))]
+    pub name: String,
+    /// 上市日期
+    #[serde(deserialize_with = "always_string", rename(deserialize = "上市日期"))]
+    pub list_date: String,
+}
+
+impl AkStockInfoShDelist {
+    pub async fn from_astock_api(reqwest_client: &reqwest::Client) -> anyhow::Result<Vec<Self>> {
+        let data: Vec<Self> = aktools_get(reqwest_client, "/stock_info_sh_delist")
+            .query(&[("symbol", "全部")])
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        Ok(data)
+    }
+}
+
+/// 深圳证券交易所终止上市公司
+///
+/// 接口: stock_info_sz_delist，symbol="终止上市公司"
+#[derive(Debug, Deserialize)]
+pub struct AkStockInfoSzDelist {
+    /// 证券代码
+    #[serde(deserialize_with = "always_string", rename(deserialize = "证券代码"))]
+    pub code: String,
+    /// 证券简称
+    #[serde(rename(deserialize = "证券简称"))]
+    pub name: String,
+    /// 上市日期
+    #[serde(deserialize_with = "always_string", rename(deserialize = "上市日期"))]
+    pub list_date: String,
+}
+
+impl AkStockInfoSzDelist {
+    pub async fn from_astock_api(reqwest_client: &reqwest::Client) -> anyhow::Result<Vec<Self>> {
+        let data: Vec<Self> = aktools_get(reqwest_client, "/stock_info_sz_delist")
+            .query(&[("symbol", "终止上市公司")])
             .send()
             .await?
             .error_for_status()?
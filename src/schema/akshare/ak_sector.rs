@@ -0,0 +1,104 @@
+//! AKShare 东方财富行业板块以及概念板块数据
+use serde::Deserialize;
+
+/// 东方财富-沪深京板块-行业板块/概念板块的板块列表以及实时行情
+///
+/// 接口: stock_board_industry_name_em、stock_board_concept_name_em
+///
+/// akshare api数据模型
+#[derive(Debug, Deserialize)]
+pub struct AkStockBoardNameEm {
+    /// 排名
+    #[serde(rename(deserialize = "排名"))]
+    pub rank: u32,
+    /// 板块名称
+    #[serde(rename(deserialize = "板块名称"))]
+    pub name: String,
+    /// 板块代码，例如`BK1027`
+    #[serde(rename(deserialize = "板块代码"))]
+    pub code: String,
+    /// 最新价
+    #[serde(rename(deserialize = "最新价"))]
+    pub latest_price: Option<f64>,
+    /// 涨跌额
+    #[serde(rename(deserialize = "涨跌额"))]
+    pub change_amount: Option<f64>,
+    /// 涨跌幅(%)
+    #[serde(rename(deserialize = "涨跌幅"))]
+    pub change_percentage: Option<f64>,
+    /// 总市值(元)
+    #[serde(rename(deserialize = "总市值"))]
+    pub total_market_value: Option<f64>,
+    /// 换手率(%)
+    #[serde(rename(deserialize = "换手率"))]
+    pub turnover_rate: Option<f64>,
+    /// 上涨家数
+    #[serde(rename(deserialize = "上涨家数"))]
+    pub rise_count: Option<f64>,
+    /// 下跌家数
+    #[serde(rename(deserialize = "下跌家数"))]
+    pub fall_count: Option<f64>,
+    /// 领涨股票
+    #[serde(rename(deserialize = "领涨股票"))]
+    pub leading_stock: Option<String>,
+    /// 领涨股票-涨跌幅(%)
+    #[serde(rename(deserialize = "领涨股票-涨跌幅"))]
+    pub leading_stock_change_percentage: Option<f64>,
+}
+
+/// 东方财富-沪深京板块-行业板块/概念板块的历史行情数据
+///
+/// 接口: stock_board_industry_hist_em、stock_board_concept_hist_em，symbol为板块名称
+///
+/// akshare api数据模型
+#[derive(Debug, Deserialize)]
+pub struct AkStockBoardHistEm {
+    /// 日期
+    #[serde(rename(deserialize = "日期"))]
+    pub date: String,
+    /// 开盘
+    #[serde(rename(deserialize = "开盘"))]
+    pub open: f64,
+    /// 收盘
+    #[serde(rename(deserialize = "收盘"))]
+    pub close: f64,
+    /// 最高
+    #[serde(rename(deserialize = "最高"))]
+    pub high: f64,
+    /// 最低
+    #[serde(rename(deserialize = "最低"))]
+    pub low: f64,
+    /// 涨跌幅(%)
+    #[serde(rename(deserialize = "涨跌幅"))]
+    pub change_percentage: f64,
+    /// 涨跌额
+    #[serde(rename(deserialize = "涨跌额"))]
+    pub change_amount: f64,
+    /// 成交量(手)
+    #[serde(rename(deserialize = "成交量"))]
+    pub trading_volume: f64,
+    /// 成交额(元)
+    #[serde(rename(deserialize = "成交额"))]
+    pub trading_value: f64,
+    /// 振幅(%)
+    #[serde(rename(deserialize = "振幅"))]
+    pub amplitude: f64,
+    /// 换手率(%)
+    #[serde(rename(deserialize = "换手率"))]
+    pub turnover_rate: f64,
+}
+
+/// 东方财富-沪深京板块-行业板块/概念板块的成份股，只使用代码以及名称
+///
+/// 接口: stock_board_industry_cons_em、stock_board_concept_cons_em，symbol为板块名称
+///
+/// akshare api数据模型
+#[derive(Debug, Deserialize)]
+pub struct AkStockBoardConsEm {
+    /// 股票代码
+    #[serde(rename(deserialize = "代码"))]
+    pub code: String,
+    /// 股票名称
+    #[serde(rename(deserialize = "名称"))]
+    pub name: String,
+}
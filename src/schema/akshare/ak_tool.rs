@@ -0,0 +1,26 @@
+//! akshare 工具类数据
+
+use serde::Deserialize;
+
+use crate::limiter::aktools_get;
+
+/// 交易日历-新浪
+///
+/// 接口: tool_trade_date_hist_sina 新浪财经的股票交易日历数据，包含当年剩余的交易日
+#[derive(Debug, Deserialize)]
+pub struct AkToolTradeDateHistSina {
+    /// 交易日，格式为%Y-%m-%dT%H:%M:%S%.3f
+    pub trade_date: String,
+}
+
+impl AkToolTradeDateHistSina {
+    pub async fn from_astock_api(reqwest_client: &reqwest::Client) -> anyhow::Result<Vec<Self>> {
+        let data: Vec<Self> = aktools_get(reqwest_client, "/tool_trade_date_hist_sina")
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(data)
+    }
+}
@@ -3,6 +3,10 @@
 
 pub mod ak_aindex;
 pub mod ak_astock;
+pub mod ak_sector;
+pub mod ak_tool;
 
 pub use ak_aindex::*;
 pub use ak_astock::*;
+pub use ak_sector::*;
+pub use ak_tool::*;
@@ -4,4 +4,5 @@
 pub mod news;
 pub mod serv_aindex;
 pub mod serv_astock;
+pub mod serv_sector;
 pub mod webhook;
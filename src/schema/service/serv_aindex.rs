@@ -1,4 +1,5 @@
 pub use crate::repository::service::{
+    IndexConstituentFetch as ServIndexConstituent,
     IndexOption50EtfQvixKlineFetch as ServIndexOption50EtfQvixKline,
     IndexOption50EtfQvixMAFetch as ServIndexOption50EtfQvixMA,
     StockZhIndexDailyKlineFetch as ServStockZhIndexDailyKline,
@@ -2,7 +2,10 @@
 
 pub use crate::repository::service::{
     DailyIndicatorFetch as ServDailyIndicator, DailyKlineFetch as ServDailyKline,
-    DailyTradingVolumeFetch as ServDailyTradingVolume, MALinesFetch as ServMALines,
+    DailyTradingVolumeFetch as ServDailyTradingVolume,
+    FinancialAbstractFetch as ServFinancialAbstract, FinancialMetric,
+    FinancialRankFetch as ServFinancialRank, IntradayBarFetch as ServIntradayBar, IntradayPeriod,
+    MALinesFetch as ServMALines,
 };
 pub use crate::schema::akshare::{
     AkStockIndividualInfoEm as ServStockIndividualInfoEm, AkStockZhAStEm as ServStockZhAStEm,
@@ -0,0 +1,9 @@
+//! 行业板块以及概念板块相关数据dto实体定义
+
+pub use crate::repository::{
+    akshare::BoardKind,
+    service::{
+        BoardConsChangeFetch as ServBoardConsChange, BoardKlineFetch as ServBoardKline,
+        BoardRankFetch as ServBoardRank, StockBoardFetch as ServStockBoard,
+    },
+};
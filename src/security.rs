@@ -0,0 +1,267 @@
+//! 证券代码
+//!
+//! 不同的数据表以及aktools接口使用不同的代码写法：
+//!
+//! - 6位数字代码，例如`603777`，`stock_zh_a_hist`等股票数据使用，交易所由代码前缀推断
+//! - 带交易所前缀的代码，例如`sz399982`(新浪指数)、`SH603777`(雪球)
+//! - 带交易所后缀的代码，例如`600198.SH`
+//!
+//! [`SecurityCode`]可以解析上面所有的写法，统一为交易所加上6位数字代码，并且可以按照需要的写法格式化
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+/// 交易所
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Exchange {
+    /// 上海证券交易所
+    #[serde(rename = "SH")]
+    Sh,
+    /// 深圳证券交易所
+    #[serde(rename = "SZ")]
+    Sz,
+    /// 北京证券交易所
+    #[serde(rename = "BJ")]
+    Bj,
+}
+
+impl Exchange {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Exchange::Sh => "SH",
+            Exchange::Sz => "SZ",
+            Exchange::Bj => "BJ",
+        }
+    }
+
+    /// 不区分大小写，`SS`为部分数据源对上海证券交易所的写法
+    fn parse(s: &str) -> Option<Self> {
+        match s.to_ascii_uppercase().as_str() {
+            "SH" | "SS" => Some(Exchange::Sh),
+            "SZ" => Some(Exchange::Sz),
+            "BJ" => Some(Exchange::Bj),
+            _ => None,
+        }
+    }
+
+    /// 通过6位股票代码推断所在交易所，指数代码无法推断(例如`000001`既是平安银行也是上证指数)
+    pub fn from_stock_symbol(symbol: &str) -> Option<Self> {
+        if !is_symbol(symbol) {
+            return None;
+        }
+        match &symbol[..3] {
+            "600" | "601" | "603" | "605" | "688" | "689" | "900" => Some(Exchange::Sh),
+            "000" | "001" | "002" | "003" | "200" | "300" | "301" | "302" => Some(Exchange::Sz),
+            _ if symbol.starts_with('8')
+                || symbol.starts_with("43")
+                || symbol.starts_with("92") =>
+            {
+                Some(Exchange::Bj)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// A股板块，不同板块的涨跌幅限制不同
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Board {
+    /// 沪深主板，涨跌幅限制10%，ST股票5%
+    Main,
+    /// 创业板，涨跌幅限制20%
+    ChiNext,
+    /// 科创板，涨跌幅限制20%
+    Star,
+    /// 北交所，涨跌幅限制30%
+    Bse,
+}
+
+impl Board {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Board::Main => "Main",
+            Board::ChiNext => "ChiNext",
+            Board::Star => "Star",
+            Board::Bse => "Bse",
+        }
+    }
+
+    /// 通过6位股票代码判断所在板块，无法识别(包括B股)时返回`None`
+    pub fn from_code(code: &str) -> Option<Self> {
+        match Exchange::from_stock_symbol(code)? {
+            Exchange::Bj => Some(Board::Bse),
+            _ => match &code[..3] {
+                "600" | "601" | "603" | "605" | "000" | "001" | "002" | "003" => Some(Board::Main),
+                "300" | "301" | "302" => Some(Board::ChiNext),
+                "688" | "689" => Some(Board::Star),
+                _ => None,
+            },
+        }
+    }
+
+    /// 涨跌幅限制(%)，创业板、科创板以及北交所的ST股票与普通股票相同
+    pub fn limit_percent(&self, is_st: bool) -> i64 {
+        match self {
+            Board::Main if is_st => 5,
+            Board::Main => 10,
+            Board::ChiNext | Board::Star => 20,
+            Board::Bse => 30,
+        }
+    }
+
+    /// 以分为单位的(涨停价, 跌停价)，昨收价乘以(1 ± 涨跌幅限制)之后四舍五入到分，跌停价最低为1分
+    pub fn limit_prices(&self, previous_close: f64, is_st: bool) -> (i64, i64) {
+        let percent = self.limit_percent(is_st);
+        let previous_cents = to_cents(previous_close);
+        let limit_up = (previous_cents * (100 + percent) + 50) / 100;
+        let limit_down = ((previous_cents * (100 - percent) + 50) / 100).max(1);
+        (limit_up, limit_down)
+    }
+}
+
+/// 将价格转换为分，避免浮点数计算涨跌停价时的舍入误差
+pub(crate) fn to_cents(price: f64) -> i64 {
+    (price * 100.0).round() as i64
+}
+
+fn is_symbol(symbol: &str) -> bool {
+    symbol.len() == 6 && symbol.bytes().all(|b| b.is_ascii_digit())
+}
+
+/// 统一之后的证券代码，由交易所以及6位数字代码组成
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SecurityCode {
+    exchange: Exchange,
+    symbol: String,
+}
+
+impl SecurityCode {
+    pub fn new(exchange: Exchange, symbol: &str) -> anyhow::Result<Self> {
+        if !is_symbol(symbol) {
+            anyhow::bail!("invalid security symbol {:?}", symbol);
+        }
+        Ok(Self {
+            exchange,
+            symbol: symbol.to_owned(),
+        })
+    }
+
+    pub fn exchange(&self) -> Exchange {
+        self.exchange
+    }
+
+    /// 6位数字代码，例如`603777`
+    pub fn symbol(&self) -> &str {
+        &self.symbol
+    }
+
+    /// 小写交易所前缀的写法，例如`sz399982`，新浪接口以及`stock_zh_index_daily`使用
+    pub fn prefixed(&self) -> String {
+        format!(
+            "{}{}",
+            self.exchange.as_str().to_ascii_lowercase(),
+            self.symbol
+        )
+    }
+
+    /// 交易所后缀的写法，例如`600198.SH`，也是[`fmt::Display`]的写法
+    pub fn suffixed(&self) -> String {
+        format!("{}.{}", self.symbol, self.exchange.as_str())
+    }
+
+    /// 所在板块，只有A股股票代码才有板块，指数代码返回`None`
+    pub fn board(&self) -> Option<Board> {
+        if Exchange::from_stock_symbol(&self.symbol) != Some(self.exchange) {
+            return None;
+        }
+        Board::from_code(&self.symbol)
+    }
+}
+
+impl FromStr for SecurityCode {
+    type Err = anyhow::Error;
+
+    /// 解析`603777`、`sz399982`、`SH603777`、`600198.SH`这几种写法，
+    /// 没有交易所的6位代码只能是股票代码
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Some((symbol, exchange)) = s.split_once('.') {
+            let exchange = Exchange::parse(exchange)
+                .ok_or_else(|| anyhow::anyhow!("unknown exchange in security code {:?}", s))?;
+            return Self::new(exchange, symbol);
+        }
+        if s.len() == 8 && s.is_char_boundary(2) {
+            let (exchange, symbol) = s.split_at(2);
+            let exchange = Exchange::parse(exchange)
+                .ok_or_else(|| anyhow::anyhow!("unknown exchange in security code {:?}", s))?;
+            return Self::new(exchange, symbol);
+        }
+        let exchange = Exchange::from_stock_symbol(s)
+            .ok_or_else(|| anyhow::anyhow!("can not infer exchange of security code {:?}", s))?;
+        Self::new(exchange, s)
+    }
+}
+
+impl fmt::Display for SecurityCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.symbol, self.exchange.as_str())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_security_code() {
+        let code: SecurityCode = "603777".parse().unwrap();
+        assert_eq!(code.exchange(), Exchange::Sh);
+        assert_eq!(code.board(), Some(Board::Main));
+        assert_eq!(code.prefixed(), "sh603777");
+        assert_eq!(code.to_string(), "603777.SH");
+
+        for s in ["600198.SH", "600198.ss", "SH600198", "sh600198", " 600198 "] {
+            assert_eq!(s.parse::<SecurityCode>().unwrap().suffixed(), "600198.SH");
+        }
+
+        let index: SecurityCode = "sz399982".parse().unwrap();
+        assert_eq!(index.exchange(), Exchange::Sz);
+        assert_eq!(index.board(), None);
+        // 上证指数与平安银行的数字代码相同，带交易所时按照指数处理
+        assert_eq!("sh000001".parse::<SecurityCode>().unwrap().board(), None);
+        assert_eq!(
+            "000001".parse::<SecurityCode>().unwrap().board(),
+            Some(Board::Main)
+        );
+
+        assert_eq!(
+            "300750".parse::<SecurityCode>().unwrap().board(),
+            Some(Board::ChiNext)
+        );
+        assert_eq!(
+            "688981".parse::<SecurityCode>().unwrap().board(),
+            Some(Board::Star)
+        );
+        assert_eq!(
+            "830799".parse::<SecurityCode>().unwrap().exchange(),
+            Exchange::Bj
+        );
+        assert_eq!(
+            "920099".parse::<SecurityCode>().unwrap().board(),
+            Some(Board::Bse)
+        );
+
+        for s in [
+            "",
+            "83",
+            "0w-1",
+            "399282",
+            "60019",
+            "xx600198",
+            "600198.HK",
+            "中国平安",
+        ] {
+            assert!(s.parse::<SecurityCode>().is_err(), "{s}");
+        }
+    }
+}
@@ -0,0 +1,354 @@
+//! aktools上游管理，支持配置多个aktools实例
+//!
+//! 每一个请求按照轮询的方式发送给健康的上游，上游连接失败、超时或者返回502/503/504时切换到下一个上游重试；
+//! 后台定期对每一个上游进行健康检查，连续失败`failure_threshold`次(包括实际请求的失败)的上游被标记为不健康，
+//! 健康检查恢复之后重新加入轮询。所有上游都不健康时依旧按照轮询的方式尝试每一个上游
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering},
+        Arc, LazyLock, Mutex, RwLock,
+    },
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use reqwest::{Request, Response, StatusCode, Url};
+use serde::{Deserialize, Serialize};
+use tokio::task::JoinHandle;
+use utoipa::ToSchema;
+
+/// 没有配置时使用的aktools地址
+pub const DEFAULT_AKTOOLS_BASE_URL: &str = "http://127.0.0.1:8080/api/public";
+/// 没有配置时使用的健康检查接口路径
+const DEFAULT_HEALTH_CHECK_PATH: &str = "/tool_trade_date_hist_sina";
+
+pub static AKTOOLS_UPSTREAMS: LazyLock<UpstreamPool> = LazyLock::new(|| {
+    UpstreamPool::new(UpstreamConfig::default()).expect("default aktools base url is valid")
+});
+
+/// aktools上游配置
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct UpstreamConfig {
+    /// aktools地址列表，例如`http://127.0.0.1:8080/api/public`
+    pub base_urls: Vec<String>,
+    /// 健康检查间隔(秒)
+    pub health_check_interval: u64,
+    /// 健康检查请求的超时时间(秒)
+    pub health_check_timeout: u64,
+    /// 健康检查请求的接口路径，默认为数据量很小的交易日历接口，
+    /// aktools在超时时间之内返回2xx的响应才认为是健康的
+    pub health_check_path: String,
+    /// 连续失败多少次之后标记为不健康
+    pub failure_threshold: u32,
+}
+
+impl Default for UpstreamConfig {
+    fn default() -> Self {
+        Self {
+            base_urls: vec![DEFAULT_AKTOOLS_BASE_URL.to_owned()],
+            health_check_interval: 10,
+            health_check_timeout: 3,
+            health_check_path: DEFAULT_HEALTH_CHECK_PATH.to_owned(),
+            failure_threshold: 2,
+        }
+    }
+}
+
+/// 一个上游的当前状态
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct UpstreamStatus {
+    pub base_url: String,
+    pub healthy: bool,
+    /// 连续失败次数
+    pub consecutive_failures: u32,
+    /// 发送到该上游的请求总数(不包括健康检查)
+    pub requests: u64,
+    /// 发送到该上游之后连接失败、超时或者返回502/503/504的请求总数
+    pub failures: u64,
+    /// 最近一次失败的原因
+    pub last_error: Option<String>,
+    /// 最近一次健康检查的时间
+    pub last_checked: Option<DateTime<Utc>>,
+}
+
+struct Upstream {
+    base_url: Url,
+    healthy: AtomicBool,
+    consecutive_failures: AtomicU32,
+    requests: AtomicU64,
+    failures: AtomicU64,
+    last_error: Mutex<Option<String>>,
+    last_checked: Mutex<Option<DateTime<Utc>>>,
+}
+
+impl Upstream {
+    fn new(base_url: &str) -> anyhow::Result<Self> {
+        let base_url = Url::parse(base_url)
+            .map_err(|err| anyhow::anyhow!("invalid aktools base url `{}`: {}", base_url, err))?;
+        Ok(Self {
+            base_url,
+            healthy: AtomicBool::new(true),
+            consecutive_failures: AtomicU32::new(0),
+            requests: AtomicU64::new(0),
+            failures: AtomicU64::new(0),
+            last_error: Mutex::new(None),
+            last_checked: Mutex::new(None),
+        })
+    }
+
+    /// 拼接出该上游之中`path`对应的完整地址
+    fn url(&self, path: &str, query: Option<&str>) -> Url {
+        let mut url = self.base_url.clone();
+        url.set_path(&format!(
+            "{}{}",
+            self.base_url.path().trim_end_matches('/'),
+            path
+        ));
+        url.set_query(query);
+        url
+    }
+
+    fn record_success(&self) {
+        self.consecutive_failures.store(0, Ordering::SeqCst);
+        if !self.healthy.swap(true, Ordering::SeqCst) {
+            ftlog::info!("[aktools upstream] {} is healthy again", self.base_url);
+        }
+    }
+
+    fn record_failure(&self, error: String, failure_threshold: u32) {
+        let failures = self.consecutive_failures.fetch_add(1, Ordering::SeqCst) + 1;
+        *self.last_error.lock().unwrap() = Some(error);
+        if failures >= failure_threshold.max(1) && self.healthy.swap(false, Ordering::SeqCst) {
+            ftlog::warn!(
+                "[aktools upstream] {} is unhealthy after {} consecutive failures",
+                self.base_url,
+                failures
+            );
+        }
+    }
+
+    fn status(&self) -> UpstreamStatus {
+        UpstreamStatus {
+            base_url: self.base_url.to_string(),
+            healthy: self.healthy.load(Ordering::SeqCst),
+            consecutive_failures: self.consecutive_failures.load(Ordering::SeqCst),
+            requests: self.requests.load(Ordering::SeqCst),
+            failures: self.failures.load(Ordering::SeqCst),
+            last_error: self.last_error.lock().unwrap().clone(),
+            last_checked: *self.last_checked.lock().unwrap(),
+        }
+    }
+}
+
+/// 一组aktools上游
+pub struct UpstreamPool {
+    upstreams: RwLock<Arc<Vec<Upstream>>>,
+    config: RwLock<UpstreamConfig>,
+    /// 轮询位置
+    next: AtomicUsize,
+}
+
+impl UpstreamPool {
+    pub fn new(config: UpstreamConfig) -> anyhow::Result<Self> {
+        let upstreams = Self::build(&config)?;
+        Ok(Self {
+            upstreams: RwLock::new(Arc::new(upstreams)),
+            config: RwLock::new(config),
+            next: AtomicUsize::new(0),
+        })
+    }
+
+    fn build(config: &UpstreamConfig) -> anyhow::Result<Vec<Upstream>> {
+        if config.base_urls.is_empty() {
+            anyhow::bail!("at least one aktools base url is required");
+        }
+        config
+            .base_urls
+            .iter()
+            .map(|base_url| Upstream::new(base_url))
+            .collect()
+    }
+
+    /// 使用新的配置替换当前的上游列表，一般在进程启动时调用
+    pub fn configure(&self, config: UpstreamConfig) -> anyhow::Result<()> {
+        let upstreams = Self::build(&config)?;
+        *self.upstreams.write().unwrap() = Arc::new(upstreams);
+        *self.config.write().unwrap() = config;
+        Ok(())
+    }
+
+    /// 获取所有上游的当前状态
+    pub fn status(&self) -> Vec<UpstreamStatus> {
+        self.snapshot()
+            .iter()
+            .map(|upstream| upstream.status())
+            .collect()
+    }
+
+    fn snapshot(&self) -> Arc<Vec<Upstream>> {
+        self.upstreams.read().unwrap().clone()
+    }
+
+    /// 按照轮询的顺序选择一个还没有尝试过的上游，优先选择健康的上游
+    fn pick(&self, upstreams: &[Upstream], tried: &[usize]) -> Option<usize> {
+        let start = self.next.fetch_add(1, Ordering::Relaxed);
+        let candidates = (0..upstreams.len())
+            .map(|offset| (start + offset) % upstreams.len())
+            .filter(|index| !tried.contains(index));
+
+        candidates
+            .clone()
+            .find(|index| upstreams[*index].healthy.load(Ordering::SeqCst))
+            .or_else(|| candidates.clone().next())
+    }
+
+    /// 将请求发送到一个上游，`request`之中的地址只有query部分会被保留，
+    /// 上游连接失败、超时或者返回502/503/504时切换到下一个上游，所有上游都失败时返回最后一次的结果
+    pub async fn send(
+        &self,
+        reqwest_client: &reqwest::Client,
+        path: &str,
+        request: Request,
+    ) -> reqwest::Result<Response> {
+        let upstreams = self.snapshot();
+        let failure_threshold = self.config.read().unwrap().failure_threshold;
+        let mut tried = Vec::with_capacity(upstreams.len());
+
+        loop {
+            let index = self
+                .pick(&upstreams, &tried)
+                .expect("upstream pool is never empty");
+            tried.push(index);
+            let upstream = &upstreams[index];
+
+            let mut attempt = request
+                .try_clone()
+                .expect("aktools request has no streaming body");
+            *attempt.url_mut() = upstream.url(path, request.url().query());
+            upstream.requests.fetch_add(1, Ordering::SeqCst);
+            let result = reqwest_client.execute(attempt).await;
+
+            let failure = match &result {
+                Ok(response) => matches!(
+                    response.status(),
+                    StatusCode::BAD_GATEWAY
+                        | StatusCode::SERVICE_UNAVAILABLE
+                        | StatusCode::GATEWAY_TIMEOUT
+                )
+                .then(|| format!("status {}", response.status())),
+                Err(err) if err.is_connect() || err.is_timeout() => Some(err.to_string()),
+                Err(_) => None,
+            };
+            let Some(error) = failure else {
+                upstream.record_success();
+                return result;
+            };
+
+            upstream.failures.fetch_add(1, Ordering::SeqCst);
+            upstream.record_failure(error.clone(), failure_threshold);
+            if tried.len() == upstreams.len() {
+                return result;
+            }
+            ftlog::warn!(
+                "[aktools upstream] request {} to {} failed: {}, fail over to next upstream",
+                path,
+                upstream.base_url,
+                error
+            );
+        }
+    }
+
+    /// 在后台定期对每一个上游进行健康检查
+    pub fn spawn_health_check(&'static self) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                let config = self.config.read().unwrap().clone();
+                self.health_check(&config).await;
+                tokio::time::sleep(Duration::from_secs(config.health_check_interval.max(1))).await;
+            }
+        })
+    }
+
+    async fn health_check(&self, config: &UpstreamConfig) {
+        let client = match reqwest::Client::builder()
+            .timeout(Duration::from_secs(config.health_check_timeout.max(1)))
+            .build()
+        {
+            Ok(client) => client,
+            Err(err) => {
+                ftlog::error!(
+                    "[aktools upstream] build health check client error: {:?}",
+                    err
+                );
+                return;
+            }
+        };
+
+        let upstreams = self.snapshot();
+        let checks = upstreams.iter().map(|upstream| {
+            let client = &client;
+            async move {
+                let url = upstream.url(&config.health_check_path, None);
+                let result = client.get(url).send().await;
+                *upstream.last_checked.lock().unwrap() = Some(Utc::now());
+                match result {
+                    Ok(response) if response.status().is_success() => upstream.record_success(),
+                    Ok(response) => upstream.record_failure(
+                        format!("health check status {}", response.status()),
+                        config.failure_threshold,
+                    ),
+                    Err(err) => upstream
+                        .record_failure(format!("health check: {}", err), config.failure_threshold),
+                }
+            }
+        });
+        futures::future::join_all(checks).await;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_upstream_url() {
+        let upstream = Upstream::new("http://127.0.0.1:8080/api/public/").unwrap();
+        assert_eq!(
+            upstream
+                .url("/stock_zh_a_hist", Some("symbol=000001&adjust=qfq"))
+                .as_str(),
+            "http://127.0.0.1:8080/api/public/stock_zh_a_hist?symbol=000001&adjust=qfq"
+        );
+        assert!(Upstream::new("127.0.0.1:8080").is_err());
+    }
+
+    #[test]
+    fn test_pick() {
+        let pool = UpstreamPool::new(UpstreamConfig {
+            base_urls: vec![
+                "http://aktools-0:8080/api/public".to_owned(),
+                "http://aktools-1:8080/api/public".to_owned(),
+            ],
+            ..Default::default()
+        })
+        .unwrap();
+        let upstreams = pool.snapshot();
+
+        upstreams[0].record_failure("connection refused".to_owned(), 2);
+        assert!(upstreams[0].healthy.load(Ordering::SeqCst));
+        upstreams[0].record_failure("connection refused".to_owned(), 2);
+        assert!(!upstreams[0].healthy.load(Ordering::SeqCst));
+
+        // 不健康的上游只有在其他上游都尝试过之后才会被选择
+        for _ in 0..4 {
+            assert_eq!(pool.pick(&upstreams, &[]), Some(1));
+        }
+        assert_eq!(pool.pick(&upstreams, &[1]), Some(0));
+        assert_eq!(pool.pick(&upstreams, &[0, 1]), None);
+
+        upstreams[0].record_success();
+        let picked: Vec<_> = (0..4).filter_map(|_| pool.pick(&upstreams, &[])).collect();
+        assert!(picked.contains(&0) && picked.contains(&1));
+    }
+}
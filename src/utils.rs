@@ -13,7 +13,8 @@ use rskafka::{
 };
 use sqlx::{Executor, MySqlPool};
 
-pub const AK_TOOLS_BASE_URL: &'static str = "http://127.0.0.1:8080/api/public";
+use crate::security::SecurityCode;
+
 #[cfg(test)]
 pub(super) static TEST_CH_CLIENT: std::sync::LazyLock<clickhouse::Client> =
     std::sync::LazyLock::new(|| {
@@ -33,11 +34,6 @@ pub(super) static TEST_HTTP_CLIENT: std::sync::LazyLock<reqwest::Client> =
             .unwrap()
     });
 
-/// 通过指定的数据字典项拼接出实际的aktools目标数据url
-pub fn with_base_url(path: &str) -> String {
-    format!("{}{}", AK_TOOLS_BASE_URL, path)
-}
-
 /// 用于clickhouse的limit语句，小于0时返回`u64::MAX`,
 /// 表示请求所有数据
 pub fn limit_or_not(limit: i32) -> u64 {
@@ -191,16 +187,12 @@ pub async fn connect_kafka(
         .unwrap()
 }
 
-/// 获取对应股票交易所的前缀
-pub fn get_exchange_prefix<'a, 'b>(stock_code: &'a str) -> Option<&'b str> {
-    match &stock_code[0..3] {
-        "600" | "601" | "603" | "688" => Some("SH"),
-        "900" => Some("SH"),
-        "000" | "001" | "002" | "003" => Some("SZ"),
-        "200" | "300" => Some("SZ"),
-        "83" | "87" => Some("BJ"),
-        _ => None,
-    }
+/// 获取对应股票交易所的前缀，例如`SH`，无法识别的代码返回`None`
+pub fn get_exchange_prefix(stock_code: &str) -> Option<&'static str> {
+    stock_code
+        .parse::<SecurityCode>()
+        .ok()
+        .map(|code| code.exchange().as_str())
 }
 
 #[cfg(test)]
@@ -210,6 +202,7 @@ mod test {
     use std::str::FromStr;
 
     use super::*;
+    use crate::limiter::aktools_get;
 
     #[test]
     fn test_date_transfer() {
@@ -242,8 +235,7 @@ mod test {
             let prefix = get_exchange_prefix(&code).unwrap();
             code.insert_str(0, prefix);
             println!("test {code}");
-            TEST_HTTP_CLIENT
-                .get(with_base_url("/stock_individual_basic_info_xq"))
+            aktools_get(&TEST_HTTP_CLIENT, "/stock_individual_basic_info_xq")
                 .query(&[("symbol", code.as_str())])
                 .send()
                 .await